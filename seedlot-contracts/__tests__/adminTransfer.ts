@@ -0,0 +1,204 @@
+import * as anchor from "@coral-xyz/anchor";
+import { web3 } from "@coral-xyz/anchor";
+import {
+  getAccount,
+  getAssociatedTokenAddressSync,
+  getMint,
+  getTokenMetadata,
+  TOKEN_2022_PROGRAM_ID,
+} from "@solana/spl-token";
+import {
+  airdrop,
+  findCertificationPDA,
+  initialize,
+  LegacyContract,
+  LOT_PRICE_IN_USDC,
+  migrateLegacyContract,
+  program,
+  setupLegacyContract,
+} from "../client/utils";
+
+describe("adminTransfer", () => {
+  let admin: web3.Keypair;
+  let contractPK: web3.PublicKey;
+  let newAdmin: web3.Keypair;
+  const setLotLimits = (signer: web3.Keypair) =>
+    program.methods
+      .setLotLimits([1, 2, 3, 4].map((l) => new anchor.BN(l)))
+      .accounts({ admin: signer.publicKey, contract: contractPK })
+      .signers([signer])
+      .rpc();
+  const proposeAdmin = (signer: web3.Keypair, proposed: web3.PublicKey) =>
+    program.methods
+      .proposeAdmin(proposed)
+      .accounts({ admin: signer.publicKey, contract: contractPK })
+      .signers([signer])
+      .rpc();
+  const acceptAdmin = (signer: web3.Keypair) =>
+    program.methods
+      .acceptAdmin()
+      .accounts({ newAdmin: signer.publicKey, contract: contractPK })
+      .signers([signer])
+      .rpc();
+
+  beforeAll(async () => {
+    ({ admin, contractPK } = await initialize());
+    newAdmin = web3.Keypair.generate();
+    await airdrop(newAdmin.publicKey);
+  });
+
+  it("only lets the admin propose a new admin", async () => {
+    await expect(proposeAdmin(newAdmin, newAdmin.publicKey)).rejects.toThrow();
+  });
+  it("keeps the current admin until the proposal is accepted", async () => {
+    await proposeAdmin(admin, newAdmin.publicKey);
+    const contract = await program.account.contract.fetch(contractPK);
+    expect(contract.admin).toEqual(admin.publicKey);
+    expect(contract.pendingAdmin).toEqual(newAdmin.publicKey);
+  });
+  it("only lets the proposed admin accept", async () => {
+    const someoneElse = web3.Keypair.generate();
+    await expect(acceptAdmin(someoneElse)).rejects.toThrow(
+      "Error Code: NotPendingAdmin"
+    );
+  });
+  it("hands the contract over once accepted", async () => {
+    await acceptAdmin(newAdmin);
+    const contract = await program.account.contract.fetch(contractPK);
+    expect(contract.admin).toEqual(newAdmin.publicKey);
+    expect(contract.pendingAdmin).toBeNull();
+
+    await expect(setLotLimits(admin)).rejects.toThrow();
+    await setLotLimits(newAdmin);
+  });
+  it("can't be accepted twice", async () => {
+    await expect(acceptAdmin(newAdmin)).rejects.toThrow(
+      "Error Code: NotPendingAdmin"
+    );
+  });
+  it("can't be handed to the arbitrator", async () => {
+    const arbitrator = web3.Keypair.generate();
+    await program.methods
+      .setArbitrator(arbitrator.publicKey, new anchor.BN(60))
+      .accounts({ admin: newAdmin.publicKey, contract: contractPK })
+      .signers([newAdmin])
+      .rpc();
+    await proposeAdmin(newAdmin, arbitrator.publicKey);
+    await expect(acceptAdmin(arbitrator)).rejects.toThrow(
+      "Error Code: ArbitratorCannotBeAdmin"
+    );
+  });
+
+  describe("legacy contract", () => {
+    const manager = web3.Keypair.generate();
+    let legacy: LegacyContract;
+    let migrated: Awaited<ReturnType<typeof migrateLegacyContract>>;
+
+    beforeAll(async () => {
+      // A tier 2 manager, back when tiers were counted in badge tokens
+      legacy = await setupLegacyContract({
+        name: "legacy-contract",
+        badges: [[manager.publicKey, 2]],
+      });
+      migrated = await migrateLegacyContract(legacy);
+    }, 30000);
+
+    it("migrates a contract seeded by its admin", async () => {
+      const contract = await program.account.contract.fetch(
+        migrated.contractPK
+      );
+      expect(contract.contractId).toEqual(migrated.contractId);
+      expect(contract.admin).toEqual(legacy.admin.publicKey);
+      expect(contract.legacyAdmin).toEqual(legacy.admin.publicKey);
+      expect(contract.treesPerLot.toNumber()).toEqual(10);
+      expect(contract.usdcMint).toEqual(legacy.usdc.mint);
+      expect(contract.certificationMint).toEqual(
+        legacy.certificationMint.publicKey
+      );
+      expect(contract.offersGroup).toEqual(migrated.offersGroup.publicKey);
+      expect(contract.lotsGroup).toEqual(migrated.lotsGroup.publicKey);
+      expect(contract.legacyLotsAccount).toEqual(web3.PublicKey.default);
+    });
+    it("moves the legacy contract's USDC over", async () => {
+      const usdc = await getAccount(
+        program.provider.connection,
+        migrated.contractUsdcTokenAccount
+      );
+      expect(usdc.amount).toEqual(BigInt(LOT_PRICE_IN_USDC));
+      const legacyUsdc = await getAccount(
+        program.provider.connection,
+        legacy.legacyUsdcTokenAccount
+      );
+      expect(legacyUsdc.amount).toEqual(BigInt(0));
+    });
+    it("closes the legacy contract so it can't be migrated twice", async () => {
+      expect(
+        await program.provider.connection.getAccountInfo(
+          legacy.legacyContractPK
+        )
+      ).toBeNull();
+      await expect(migrateLegacyContract(legacy)).rejects.toThrow();
+    });
+    it("hands the legacy mints over to the migrated contract", async () => {
+      const certificationMint = await getMint(
+        program.provider.connection,
+        legacy.certificationMint.publicKey,
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      );
+      expect(certificationMint.mintAuthority).toEqual(migrated.contractPK);
+      expect(certificationMint.freezeAuthority).toEqual(migrated.contractPK);
+      const metadata = await getTokenMetadata(
+        program.provider.connection,
+        legacy.certificationMint.publicKey
+      );
+      expect(metadata!.updateAuthority).toEqual(migrated.contractPK);
+
+      // The legacy contract has no authority left to hand over
+      await expect(
+        program.methods
+          .migrateMint()
+          .accounts({
+            admin: legacy.admin.publicKey,
+            contract: migrated.contractPK,
+            legacyContract: legacy.legacyContractPK,
+            mint: legacy.certificationMint.publicKey,
+            tokenProgram: TOKEN_2022_PROGRAM_ID,
+          })
+          .signers([legacy.admin])
+          .rpc()
+      ).rejects.toThrow();
+    });
+    it("certifies managers at the tier of their badge", async () => {
+      const certification = findCertificationPDA(
+        migrated.contractPK,
+        manager.publicKey
+      );
+      await program.methods
+        .migrateCertification()
+        .accounts({
+          admin: legacy.admin.publicKey,
+          contract: migrated.contractPK,
+          manager: manager.publicKey,
+          certificationMint: legacy.certificationMint.publicKey,
+          managerCertificationTokenAccount: getAssociatedTokenAddressSync(
+            legacy.certificationMint.publicKey,
+            manager.publicKey,
+            false,
+            TOKEN_2022_PROGRAM_ID
+          ),
+          certification,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          systemProgram: web3.SystemProgram.programId,
+        })
+        .signers([legacy.admin])
+        .rpc();
+      const { tier, status, issuer, expiresAt } =
+        await program.account.certification.fetch(certification);
+      expect(tier).toEqual({ tier2: {} });
+      expect(status).toEqual({ active: {} });
+      expect(issuer).toEqual(legacy.admin.publicKey);
+      expect(expiresAt).toBeNull();
+    });
+  });
+});
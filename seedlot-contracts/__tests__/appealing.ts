@@ -0,0 +1,124 @@
+import * as anchor from "@coral-xyz/anchor";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  TOKEN_2022_PROGRAM_ID,
+  getAccount,
+  getAssociatedTokenAddressSync,
+} from "@solana/spl-token";
+import {
+  CERTIFICATION_VALID_FOR,
+  EVIDENCE_HASH,
+  PreparedLot,
+  prepareAnotherLot,
+  program,
+  setupPreparedLot,
+} from "../client/utils";
+
+describe("appealing", () => {
+  let lot: PreparedLot;
+  let appeal: anchor.web3.PublicKey;
+  let managerTo: anchor.web3.PublicKey;
+  const appealEvidence = Array(32).fill(7);
+  const certifyAccounts = () => ({
+    admin: lot.admin.publicKey,
+    manager: lot.manager.publicKey,
+    contract: lot.contractPK,
+    certification: lot.certification,
+    certificationMint: lot.certificationMint.publicKey,
+    managerTo,
+    tokenProgram: TOKEN_2022_PROGRAM_ID,
+    associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+    systemProgram: anchor.web3.SystemProgram.programId,
+  });
+  const fileAppeal = () =>
+    program.methods
+      .fileAppeal(appealEvidence)
+      .accounts({
+        manager: lot.manager.publicKey,
+        contract: lot.contractPK,
+        certification: lot.certification,
+        appeal,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([lot.manager])
+      .rpc();
+  const reinstate = (
+    probation: { endsAt: anchor.BN; lotLimit: anchor.BN } | null
+  ) =>
+    program.methods
+      .reinstate({ tier2: {} }, CERTIFICATION_VALID_FOR, probation)
+      .accounts({ ...certifyAccounts(), appeal })
+      .signers([lot.admin])
+      .rpc();
+
+  beforeAll(async () => {
+    lot = await setupPreparedLot({ numOrders: 8, numLotsPrepared: 3 });
+    appeal = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("appeal"),
+        lot.contractPK.toBuffer(),
+        lot.manager.publicKey.toBuffer(),
+      ],
+      program.programId
+    )[0];
+    managerTo = getAssociatedTokenAddressSync(
+      lot.certificationMint.publicKey,
+      lot.manager.publicKey,
+      false,
+      TOKEN_2022_PROGRAM_ID,
+      ASSOCIATED_TOKEN_PROGRAM_ID
+    );
+  }, 15000);
+
+  it("only lets decertified managers appeal", async () => {
+    await expect(fileAppeal()).rejects.toThrow(
+      "Error Code: ManagerNotDecertified"
+    );
+  });
+  it("needs an appeal to reinstate", async () => {
+    await program.methods
+      .decertify()
+      .accounts(certifyAccounts())
+      .signers([lot.admin])
+      .rpc();
+    await expect(reinstate(null)).rejects.toThrow();
+  });
+  it("records the manager's appeal", async () => {
+    await fileAppeal();
+    const filed = await program.account.appeal.fetch(appeal);
+    expect(filed.manager).toEqual(lot.manager.publicKey);
+    expect(filed.evidenceHash).toEqual(appealEvidence);
+  });
+  it("reinstates at the chosen tier and keeps the history", async () => {
+    const now = Math.floor(Date.now() / 1000);
+    await reinstate({
+      endsAt: new anchor.BN(now + 60 * 60),
+      lotLimit: new anchor.BN(4),
+    });
+
+    const certification = await program.account.certification.fetch(
+      lot.certification
+    );
+    expect(certification.status).toEqual({ active: {} });
+    expect(certification.tier).toEqual({ tier2: {} });
+    expect(certification.evidenceHash).toEqual(appealEvidence);
+    expect(certification.decertifications).toEqual(1);
+    expect(certification.lastDecertifiedAt).not.toBeNull();
+    expect(certification.probation.lotLimit.toNumber()).toEqual(4);
+
+    const badge = await getAccount(
+      program.provider.connection,
+      managerTo,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    expect(badge.amount).toEqual(2n);
+    expect(await program.account.appeal.fetchNullable(appeal)).toBeNull();
+  });
+  it("caps lots during probation", async () => {
+    await expect(prepareAnotherLot(lot, 2)).rejects.toThrow(
+      "Error Code: LotLimitExceeded"
+    );
+    await prepareAnotherLot(lot, 1);
+  });
+});
@@ -0,0 +1,69 @@
+import {
+  authorizeManager,
+  PreparedLot,
+  prepareAnotherLot,
+  program,
+  setupPreparedLot,
+} from "../client/utils";
+
+describe("authorizingManagers", () => {
+  let lot: PreparedLot;
+  const lotsRemaining = async () =>
+    (
+      await program.account.managerAuthorization.fetch(lot.authorization)
+    ).lotsRemaining.toNumber();
+
+  beforeAll(async () => {
+    // The user authorizes all 5 of their orders and the manager prepares 3 of them.
+    lot = await setupPreparedLot({ numOrders: 5, numLotsPrepared: 3 });
+  }, 15000);
+
+  it("records who the user authorized", async () => {
+    const authorization = await program.account.managerAuthorization.fetch(
+      lot.authorization
+    );
+    expect(authorization.user).toEqual(lot.user.publicKey);
+    expect(authorization.manager).toEqual(lot.manager.publicKey);
+    expect(authorization.orderMint).toEqual(lot.orderMint.publicKey);
+  });
+  it("uses up the authorization as lots are prepared", async () => {
+    expect(await lotsRemaining()).toEqual(2);
+  });
+  it("does not let the manager prepare more lots than authorized", async () => {
+    await authorizeManager({
+      contractPK: lot.contractPK,
+      user: lot.user,
+      manager: lot.manager.publicKey,
+      orderMint: lot.orderMint.publicKey,
+      lots: 1,
+    });
+    await expect(prepareAnotherLot(lot, 2)).rejects.toThrow(
+      "Error Code: ManagerNotAuthorized"
+    );
+    await prepareAnotherLot(lot, 1);
+    expect(await lotsRemaining()).toEqual(0);
+  });
+  it("does not let a manager prepare lots once the user revokes them", async () => {
+    await authorizeManager({
+      contractPK: lot.contractPK,
+      user: lot.user,
+      manager: lot.manager.publicKey,
+      orderMint: lot.orderMint.publicKey,
+      lots: 1,
+    });
+    await program.methods
+      .revokeAuthorization()
+      .accounts({
+        user: lot.user.publicKey,
+        authorization: lot.authorization,
+      })
+      .signers([lot.user])
+      .rpc();
+    expect(
+      await program.provider.connection.getAccountInfo(lot.authorization)
+    ).toBeNull();
+    await expect(prepareAnotherLot(lot, 1)).rejects.toThrow(
+      "Error Code: AccountNotInitialized"
+    );
+  });
+});
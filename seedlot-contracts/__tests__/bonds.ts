@@ -0,0 +1,97 @@
+import * as anchor from "@coral-xyz/anchor";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  TOKEN_PROGRAM_ID,
+  getAccount,
+  getAssociatedTokenAddressSync,
+} from "@solana/spl-token";
+import {
+  LOT_PRICE_IN_USDC,
+  PreparedLot,
+  confirmLot,
+  postBond,
+  prepareAnotherLot,
+  program,
+  setupPreparedLot,
+} from "../client/utils";
+
+describe("bonds", () => {
+  let lot: PreparedLot;
+  let managerUsdcAccount: anchor.web3.PublicKey;
+  const balance = async (account: anchor.web3.PublicKey) =>
+    Number(
+      (
+        await getAccount(
+          program.provider.connection,
+          account,
+          undefined,
+          TOKEN_PROGRAM_ID
+        )
+      ).amount
+    );
+  const withdrawBond = (amount: number) =>
+    program.methods
+      .withdrawBond(new anchor.BN(amount))
+      .accounts({
+        manager: lot.manager.publicKey,
+        contract: lot.contractPK,
+        managerRecord: lot.managerRecord,
+        usdcMint: lot.usdc.mint,
+        bond: lot.bond,
+        managerUsdcTokenAccount: managerUsdcAccount,
+        tokenProgramStandard: TOKEN_PROGRAM_ID,
+      })
+      .signers([lot.manager])
+      .rpc();
+
+  beforeAll(async () => {
+    lot = await setupPreparedLot({ numOrders: 8, numLotsPrepared: 3 });
+    managerUsdcAccount = getAssociatedTokenAddressSync(
+      lot.usdc.mint,
+      lot.manager.publicKey,
+      false,
+      TOKEN_PROGRAM_ID,
+      ASSOCIATED_TOKEN_PROGRAM_ID
+    );
+  }, 15000);
+
+  it("needs a bond covering the contract's to prepare lots", async () => {
+    await program.methods
+      .setManagerBond(new anchor.BN(LOT_PRICE_IN_USDC))
+      .accounts({ admin: lot.admin.publicKey, contract: lot.contractPK })
+      .signers([lot.admin])
+      .rpc();
+    await expect(prepareAnotherLot(lot, 1)).rejects.toThrow(
+      "Error Code: InsufficientBond"
+    );
+
+    await postBond({
+      contractPK: lot.contractPK,
+      manager: lot.manager,
+      usdc: lot.usdc,
+      amount: LOT_PRICE_IN_USDC,
+    });
+    expect(await balance(lot.bond)).toEqual(LOT_PRICE_IN_USDC);
+  });
+  it("leaves the bond alone when a denied lot's fee was escrowed", async () => {
+    const another = await prepareAnotherLot(lot, 1);
+    await confirmLot({ ...lot, ...another }, 1);
+
+    expect(await balance(lot.bond)).toEqual(LOT_PRICE_IN_USDC);
+  }, 15000);
+  it("keeps the bond while the manager has open lots", async () => {
+    await expect(withdrawBond(1)).rejects.toThrow(
+      "Error Code: ManagerHasOpenLots"
+    );
+  });
+  it("lets the manager withdraw once their lots are closed", async () => {
+    await confirmLot(lot, 1);
+
+    const before = await balance(managerUsdcAccount);
+    await withdrawBond(LOT_PRICE_IN_USDC);
+    expect(await balance(lot.bond)).toEqual(0);
+    expect(await balance(managerUsdcAccount)).toEqual(
+      before + LOT_PRICE_IN_USDC
+    );
+  });
+});
@@ -0,0 +1,268 @@
+import * as anchor from "@coral-xyz/anchor";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  getAccount,
+  getAssociatedTokenAddressSync,
+  getOrCreateAssociatedTokenAccount,
+  TOKEN_2022_PROGRAM_ID,
+  TOKEN_PROGRAM_ID,
+} from "@solana/spl-token";
+import {
+  airdrop,
+  findAuthorizationPDA,
+  findOrderBalancePDA,
+  findReceiptPDA,
+  LegacyContract,
+  LOT_PRICE_IN_USDC,
+  migrateLegacyContract,
+  PreparedLot,
+  prepareAnotherLot,
+  program,
+  setupLegacyContract,
+  setupPreparedLot,
+  TREES_PER_LOT,
+} from "../client/utils";
+
+describe("cancellingOrders", () => {
+  let lot: PreparedLot;
+  const cancelOrder = (orderQuantity: number) =>
+    program.methods
+      .cancelOrder(new anchor.BN(orderQuantity))
+      .accounts({
+        user: lot.user.publicKey,
+        contract: lot.contractPK,
+        orderMint: lot.orderMint.publicKey,
+        userOrderTokenAccount: lot.userOrderTokenAccount,
+        orderBalance: lot.orderBalance,
+        receipt: lot.receipt,
+        usdcMint: lot.usdc.mint,
+        contractUsdcTokenAccount: lot.contractUsdcTokenAccount,
+        userUsdcTokenAccount: lot.userUsdcAccount,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        tokenProgramStandard: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([lot.user])
+      .rpc();
+  const orderTokens = async () =>
+    (
+      await getAccount(
+        program.provider.connection,
+        lot.userOrderTokenAccount,
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      )
+    ).amount;
+  const usdcBalance = async () =>
+    (await getAccount(program.provider.connection, lot.userUsdcAccount)).amount;
+
+  beforeAll(async () => {
+    lot = await setupPreparedLot({ numOrders: 5, numLotsPrepared: 2 });
+  }, 15000);
+
+  it("only keeps the orders that haven't been prepared", async () => {
+    const orderBalance = await program.account.orderBalance.fetch(
+      lot.orderBalance
+    );
+    expect(orderBalance.user).toEqual(lot.user.publicKey);
+    expect(orderBalance.quantity.toNumber()).toEqual(3);
+    const receipt = await program.account.orderReceipt.fetch(lot.receipt);
+    expect(receipt.quantityRemaining.toNumber()).toEqual(3);
+  });
+  it("burns the orders and refunds what the user paid", async () => {
+    const usdcBefore = await usdcBalance();
+    await cancelOrder(1);
+
+    expect(await orderTokens()).toEqual(BigInt(2));
+    expect(await usdcBalance()).toEqual(usdcBefore + BigInt(LOT_PRICE_IN_USDC));
+    const orderBalance = await program.account.orderBalance.fetch(
+      lot.orderBalance
+    );
+    expect(orderBalance.quantity.toNumber()).toEqual(2);
+    const receipt = await program.account.orderReceipt.fetch(lot.receipt);
+    expect(receipt.quantityRemaining.toNumber()).toEqual(2);
+  });
+  it("can't cancel more than the user holds", async () => {
+    await expect(cancelOrder(3)).rejects.toThrow(
+      "Error Code: InsufficientOrderBalance"
+    );
+  });
+  it("can't cancel orders once they are prepared", async () => {
+    await prepareAnotherLot(lot, 2);
+    await expect(cancelOrder(1)).rejects.toThrow(
+      "Error Code: InsufficientOrderBalance"
+    );
+  });
+
+  describe("legacy offers", () => {
+    const user = anchor.web3.Keypair.generate();
+    let legacy: LegacyContract;
+    let contractPK: anchor.web3.PublicKey;
+    let contractUsdcTokenAccount: anchor.web3.PublicKey;
+    let userUsdcTokenAccount: anchor.web3.PublicKey;
+    const legacyOffer = () =>
+      anchor.web3.PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("legacy_offer"),
+          contractPK.toBuffer(),
+          legacy.orderMint!.publicKey.toBuffer(),
+        ],
+        program.programId
+      )[0];
+    const userOrderTokenAccount = () =>
+      getAssociatedTokenAddressSync(
+        legacy.orderMint!.publicKey,
+        user.publicKey,
+        false,
+        TOKEN_2022_PROGRAM_ID,
+        ASSOCIATED_TOKEN_PROGRAM_ID
+      );
+    const orderBalance = () =>
+      findOrderBalancePDA(
+        contractPK,
+        legacy.orderMint!.publicKey,
+        user.publicKey
+      );
+    const receipt = () =>
+      findReceiptPDA(
+        contractPK,
+        legacy.orderMint!.publicKey,
+        user.publicKey,
+        0
+      );
+    const pricePerTree = 1000; // 10 dollars, less than the offer's price now
+    const migrateOrder = (allowed: boolean) =>
+      program.methods
+        .migrateOrder(new anchor.BN(1), new anchor.BN(pricePerTree))
+        .accounts({
+          admin: legacy.admin.publicKey,
+          contract: contractPK,
+          user: user.publicKey,
+          orderMint: legacy.orderMint!.publicKey,
+          legacyOffer: allowed ? legacyOffer() : null,
+          userOrderTokenAccount: userOrderTokenAccount(),
+          orderBalance: orderBalance(),
+          receipt: receipt(),
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([legacy.admin])
+        .rpc();
+    const cancelLegacyOrder = () =>
+      program.methods
+        .cancelOrder(new anchor.BN(1))
+        .accounts({
+          user: user.publicKey,
+          contract: contractPK,
+          orderMint: legacy.orderMint!.publicKey,
+          userOrderTokenAccount: userOrderTokenAccount(),
+          orderBalance: orderBalance(),
+          receipt: receipt(),
+          usdcMint: legacy.usdc.mint,
+          contractUsdcTokenAccount,
+          userUsdcTokenAccount,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          tokenProgramStandard: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([user])
+        .rpc();
+
+    beforeAll(async () => {
+      await airdrop(user.publicKey);
+      // Two orders placed before groups and receipts existed
+      legacy = await setupLegacyContract({
+        name: "legacy-offers",
+        orders: [[user.publicKey, 2]],
+        usdcAmount: 2 * LOT_PRICE_IN_USDC,
+      });
+      ({ contractPK, contractUsdcTokenAccount } = await migrateLegacyContract(
+        legacy
+      ));
+      userUsdcTokenAccount = (
+        await getOrCreateAssociatedTokenAccount(
+          program.provider.connection,
+          user,
+          legacy.usdc.mint,
+          user.publicKey,
+          false,
+          undefined,
+          undefined,
+          TOKEN_PROGRAM_ID,
+          ASSOCIATED_TOKEN_PROGRAM_ID
+        )
+      ).address;
+    }, 30000);
+
+    it("won't cancel orders placed before receipts", async () => {
+      await expect(cancelLegacyOrder()).rejects.toThrow(
+        "Error Code: AccountNotInitialized"
+      );
+    });
+    it("rejects legacy offers the admin hasn't allowed", async () => {
+      await expect(migrateOrder(false)).rejects.toThrow();
+    });
+    it("refunds migrated orders at the price the user paid", async () => {
+      await program.methods
+        .allowLegacyOffer()
+        .accounts({
+          admin: legacy.admin.publicKey,
+          contract: contractPK,
+          orderMint: legacy.orderMint!.publicKey,
+          legacyOffer: legacyOffer(),
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([legacy.admin])
+        .rpc();
+      await migrateOrder(true);
+      const { pricePerTree: receiptPrice } =
+        await program.account.orderReceipt.fetch(receipt());
+      expect(receiptPrice.toNumber()).toEqual(pricePerTree);
+      await cancelLegacyOrder();
+
+      const orderTokens = await getAccount(
+        program.provider.connection,
+        userOrderTokenAccount(),
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      );
+      expect(orderTokens.amount).toEqual(BigInt(1));
+      const usdc = await getAccount(
+        program.provider.connection,
+        userUsdcTokenAccount
+      );
+      expect(usdc.amount).toEqual(
+        BigInt(pricePerTree * 10 ** 4 * TREES_PER_LOT.toNumber())
+      );
+    });
+    it("lets the user authorize a manager for the rest", async () => {
+      const manager = anchor.web3.Keypair.generate().publicKey;
+      const authorization = findAuthorizationPDA(
+        contractPK,
+        legacy.orderMint!.publicKey,
+        user.publicKey,
+        manager
+      );
+      await program.methods
+        .authorizeManager(new anchor.BN(1))
+        .accounts({
+          user: user.publicKey,
+          contract: contractPK,
+          manager,
+          orderMint: legacy.orderMint!.publicKey,
+          legacyOffer: legacyOffer(),
+          authorization,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([user])
+        .rpc();
+      const { lotsRemaining } =
+        await program.account.managerAuthorization.fetch(authorization);
+      expect(lotsRemaining.toNumber()).toEqual(1);
+    });
+  });
+});
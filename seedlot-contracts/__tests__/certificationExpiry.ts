@@ -0,0 +1,119 @@
+import * as anchor from "@coral-xyz/anchor";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  TOKEN_2022_PROGRAM_ID,
+  getAssociatedTokenAddressSync,
+} from "@solana/spl-token";
+import {
+  CERTIFICATION_VALID_FOR,
+  EVIDENCE_HASH,
+  findCertificationPDA,
+  PreparedLot,
+  prepareAnotherLot,
+  program,
+  setupPreparedLot,
+} from "../client/utils";
+
+describe("certificationExpiry", () => {
+  let lot: PreparedLot;
+  const certifyAccounts = () => ({
+    admin: lot.admin.publicKey,
+    manager: lot.manager.publicKey,
+    contract: lot.contractPK,
+    certification: lot.certification,
+    certificationMint: lot.certificationMint.publicKey,
+    managerTo: getAssociatedTokenAddressSync(
+      lot.certificationMint.publicKey,
+      lot.manager.publicKey,
+      false,
+      TOKEN_2022_PROGRAM_ID,
+      ASSOCIATED_TOKEN_PROGRAM_ID
+    ),
+    tokenProgram: TOKEN_2022_PROGRAM_ID,
+    associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+    systemProgram: anchor.web3.SystemProgram.programId,
+  });
+  const renew = (validFor: anchor.BN) =>
+    program.methods
+      .renewCertification(EVIDENCE_HASH, validFor)
+      .accounts(certifyAccounts())
+      .signers([lot.admin])
+      .rpc();
+
+  beforeAll(async () => {
+    lot = await setupPreparedLot({ numOrders: 8, numLotsPrepared: 1 });
+  }, 15000);
+
+  it("sets when the certification lapses", async () => {
+    const certification = await program.account.certification.fetch(
+      lot.certification
+    );
+    expect(
+      certification.expiresAt.sub(certification.issuedAt).toString()
+    ).toEqual(CERTIFICATION_VALID_FOR.toString());
+  });
+  it("rejects validity periods that are not positive", async () => {
+    await expect(renew(new anchor.BN(0))).rejects.toThrow(
+      "Error Code: InvalidValidityPeriod"
+    );
+  });
+  it("only renews managers that are certified", async () => {
+    const manager = anchor.web3.Keypair.generate();
+    await expect(
+      program.methods
+        .renewCertification(EVIDENCE_HASH, CERTIFICATION_VALID_FOR)
+        .accounts({
+          ...certifyAccounts(),
+          manager: manager.publicKey,
+          certification: findCertificationPDA(
+            lot.contractPK,
+            manager.publicKey
+          ),
+          managerTo: getAssociatedTokenAddressSync(
+            lot.certificationMint.publicKey,
+            manager.publicKey,
+            false,
+            TOKEN_2022_PROGRAM_ID,
+            ASSOCIATED_TOKEN_PROGRAM_ID
+          ),
+        })
+        .signers([lot.admin])
+        .rpc()
+    ).rejects.toThrow("Error Code: ManagerNotCertified");
+  });
+  it("adds early renewals to what is left of the certification", async () => {
+    const before = await program.account.certification.fetch(
+      lot.certification
+    );
+    await renew(CERTIFICATION_VALID_FOR);
+    const after = await program.account.certification.fetch(
+      lot.certification
+    );
+    expect(after.expiresAt.toString()).toEqual(
+      before.expiresAt.add(CERTIFICATION_VALID_FOR).toString()
+    );
+  });
+  it("blocks preparing lots once lapsed until renewed", async () => {
+    // Certifying starts a new period, so this one lapses straight away
+    await program.methods
+      .certify({ tier2: {} }, EVIDENCE_HASH, new anchor.BN(1))
+      .accounts(certifyAccounts())
+      .signers([lot.admin])
+      .rpc();
+    await new Promise((resolve) => setTimeout(resolve, 3000));
+    await expect(prepareAnotherLot(lot, 1)).rejects.toThrow(
+      "Error Code: CertificationLapsed"
+    );
+
+    await renew(CERTIFICATION_VALID_FOR);
+    const certification = await program.account.certification.fetch(
+      lot.certification
+    );
+    expect(certification.tier).toEqual({ tier2: {} });
+    // Lapsed certifications are renewed from now
+    expect(
+      certification.expiresAt.sub(certification.issuedAt).toString()
+    ).toEqual(CERTIFICATION_VALID_FOR.toString());
+    await prepareAnotherLot(lot, 1);
+  }, 15000);
+});
@@ -13,6 +13,9 @@ import {
   airdrop,
   program,
   confirmTx,
+  findCertificationPDA,
+  EVIDENCE_HASH,
+  CERTIFICATION_VALID_FOR,
 } from "../client/utils";
 
 enum ClientCertificationTierMirror {
@@ -67,10 +70,11 @@ describe("Certifying", () => {
       systemProgram: web3.SystemProgram.programId,
       manager: manager.publicKey,
       certificationMint: certificationMint.publicKey,
+      certification: findCertificationPDA(contractPK, manager.publicKey),
     };
     await expect(
       program.methods
-        .certify({ undefined: {} })
+        .certify({ undefined: {} }, EVIDENCE_HASH, CERTIFICATION_VALID_FOR)
         .accounts(accounts)
         .signers([manager])
         .rpc()
@@ -86,6 +90,7 @@ describe("Certifying", () => {
       systemProgram: web3.PublicKey;
       manager: web3.PublicKey;
       certificationMint: web3.PublicKey;
+      certification: web3.PublicKey;
     };
     beforeEach(async () => {
       accounts = {
@@ -97,12 +102,17 @@ describe("Certifying", () => {
         systemProgram: web3.SystemProgram.programId,
         manager: manager.publicKey,
         certificationMint: certificationMint.publicKey,
+        certification: findCertificationPDA(contractPK, manager.publicKey),
       };
     });
     it("Can certify at tiers 1 - 4", async () => {
       for (let tier = 1; tier < 5; tier++) {
         const txHash = await program.methods
-          .certify(convertToCertificationTier(tier))
+          .certify(
+            convertToCertificationTier(tier),
+            EVIDENCE_HASH,
+            CERTIFICATION_VALID_FOR
+          )
           .accounts(accounts)
           .signers([admin])
           .rpc();
@@ -120,7 +130,11 @@ describe("Certifying", () => {
     it("Fails if you try to certify as decertified", async () => {
       for (let tier = 1; tier < 5; tier++) {
         const txHash = await program.methods
-          .certify(convertToCertificationTier(tier))
+          .certify(
+            convertToCertificationTier(tier),
+            EVIDENCE_HASH,
+            CERTIFICATION_VALID_FOR
+          )
           .accounts(accounts)
           .signers([admin])
           .rpc();
@@ -128,7 +142,7 @@ describe("Certifying", () => {
       }
       await expect(
         program.methods
-          .certify({ decertified: {} })
+          .certify({ decertified: {} }, EVIDENCE_HASH, CERTIFICATION_VALID_FOR)
           .accounts(accounts)
           .signers([admin])
           .rpc()
@@ -137,7 +151,7 @@ describe("Certifying", () => {
     it("Fails you try to certify at tier 0", async () => {
       await expect(
         program.methods
-          .certify({ undefined: {} })
+          .certify({ undefined: {} }, EVIDENCE_HASH, CERTIFICATION_VALID_FOR)
           .accounts(accounts)
           .signers([admin])
           .rpc()
@@ -145,13 +159,13 @@ describe("Certifying", () => {
     });
     it("Fails you try to certify at a tier that is not one more than the previous tier", async () => {
       await program.methods
-        .certify({ tier1: {} })
+        .certify({ tier1: {} }, EVIDENCE_HASH, CERTIFICATION_VALID_FOR)
         .accounts(accounts)
         .signers([admin])
         .rpc();
       await expect(
         program.methods
-          .certify({ tier3: {} })
+          .certify({ tier3: {} }, EVIDENCE_HASH, CERTIFICATION_VALID_FOR)
           .accounts(accounts)
           .signers([admin])
           .rpc()
@@ -159,18 +173,64 @@ describe("Certifying", () => {
     });
     it("Does not allow for multiple certifications of the same tier.", async () => {
       const txHash = await program.methods
-        .certify({ tier1: {} })
+        .certify({ tier1: {} }, EVIDENCE_HASH, CERTIFICATION_VALID_FOR)
         .accounts(accounts)
         .signers([admin])
         .rpc();
       await confirmTx(txHash);
       await expect(
         program.methods
-          .certify({ tier1: {} })
+          .certify({ tier1: {} }, EVIDENCE_HASH, CERTIFICATION_VALID_FOR)
           .accounts(accounts)
           .signers([admin])
           .rpc()
-      ).rejects.toThrow("Error Code: CertificationsMustIncreaseByOneTier");
+      ).rejects.toThrow("Error Code: CertificationTierUnchanged");
+    });
+    it("Can lower a certification by any number of tiers.", async () => {
+      for (let tier = 1; tier < 4; tier++) {
+        await program.methods
+          .certify(
+            convertToCertificationTier(tier),
+            EVIDENCE_HASH,
+            CERTIFICATION_VALID_FOR
+          )
+          .accounts(accounts)
+          .signers([admin])
+          .rpc();
+      }
+      const txHash = await program.methods
+        .certify({ tier1: {} }, EVIDENCE_HASH, CERTIFICATION_VALID_FOR)
+        .accounts(accounts)
+        .signers([admin])
+        .rpc();
+      await confirmTx(txHash);
+      const managerTokenAccount = await getAccount(
+        program.provider.connection,
+        managerAta,
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      );
+      expect(managerTokenAccount.amount).toBe(1n);
+      const certification = await program.account.certification.fetch(
+        accounts.certification
+      );
+      expect(certification.tier).toEqual({ tier1: {} });
+    });
+    it("Records the issuer and evidence of the certification.", async () => {
+      const txHash = await program.methods
+        .certify({ tier1: {} }, EVIDENCE_HASH, CERTIFICATION_VALID_FOR)
+        .accounts(accounts)
+        .signers([admin])
+        .rpc();
+      await confirmTx(txHash);
+      const certification = await program.account.certification.fetch(
+        accounts.certification
+      );
+      expect(certification.manager).toEqual(manager.publicKey);
+      expect(certification.status).toEqual({ active: {} });
+      expect(certification.issuer).toEqual(admin.publicKey);
+      expect(certification.evidenceHash).toEqual(EVIDENCE_HASH);
+      expect(certification.issuedAt.toNumber()).toBeGreaterThan(0);
     });
   });
   it("Cannot be transferred", async () => {
@@ -183,9 +243,10 @@ describe("Certifying", () => {
       systemProgram: web3.SystemProgram.programId,
       manager: manager.publicKey,
       certificationMint: certificationMint.publicKey,
+      certification: findCertificationPDA(contractPK, manager.publicKey),
     };
     await program.methods
-      .certify({ tier1: {} })
+      .certify({ tier1: {} }, EVIDENCE_HASH, CERTIFICATION_VALID_FOR)
       .accounts(accounts)
       .signers([admin])
       .rpc();
@@ -230,9 +291,10 @@ describe("Certifying", () => {
       systemProgram: web3.SystemProgram.programId,
       manager: manager.publicKey,
       certificationMint: certificationMint.publicKey,
+      certification: findCertificationPDA(contractPK, manager.publicKey),
     };
     await program.methods
-      .certify({ tier1: {} })
+      .certify({ tier1: {} }, EVIDENCE_HASH, CERTIFICATION_VALID_FOR)
       .accounts(accounts)
       .signers([admin])
       .rpc();
@@ -289,6 +351,7 @@ describe("Decertifying", () => {
       systemProgram: web3.SystemProgram.programId,
       manager: manager.publicKey,
       certificationMint: certificationMint.publicKey,
+      certification: findCertificationPDA(contractPK, manager.publicKey),
     };
     const txHash = await program.methods
       .decertify()
@@ -303,6 +366,10 @@ describe("Decertifying", () => {
       TOKEN_2022_PROGRAM_ID
     );
     expect(managerTokenAccount.amount).toBe(DECERTIFIED_TIER_TOKEN_AMOUNT);
+    const certification = await program.account.certification.fetch(
+      accounts.certification
+    );
+    expect(certification.status).toEqual({ decertified: {} });
   });
   it("Can decertify at any tier.", async () => {
     await Promise.all(
@@ -324,10 +391,15 @@ describe("Decertifying", () => {
             systemProgram: web3.SystemProgram.programId,
             manager: manager.publicKey,
             certificationMint: certificationMint.publicKey,
+            certification: findCertificationPDA(contractPK, manager.publicKey),
           };
           for (let tier = 1; tier <= tierUnderTest; tier++) {
             await program.methods
-              .certify(convertToCertificationTier(tier))
+              .certify(
+                convertToCertificationTier(tier),
+                EVIDENCE_HASH,
+                CERTIFICATION_VALID_FOR
+              )
               .accounts(accounts)
               .signers([admin])
               .rpc();
@@ -368,6 +440,7 @@ describe("Decertifying", () => {
       systemProgram: web3.SystemProgram.programId,
       manager: manager.publicKey,
       certificationMint: certificationMint.publicKey,
+      certification: findCertificationPDA(contractPK, manager.publicKey),
     };
     await program.methods.decertify().accounts(accounts).signers([admin]).rpc();
     await expect(
@@ -391,11 +464,12 @@ describe("Decertifying", () => {
       systemProgram: web3.SystemProgram.programId,
       manager: manager.publicKey,
       certificationMint: certificationMint.publicKey,
+      certification: findCertificationPDA(contractPK, manager.publicKey),
     };
     await program.methods.decertify().accounts(accounts).signers([admin]).rpc();
     await expect(
       program.methods
-        .certify({ tier1: {} })
+        .certify({ tier1: {} }, EVIDENCE_HASH, CERTIFICATION_VALID_FOR)
         .accounts(accounts)
         .signers([admin])
         .rpc()
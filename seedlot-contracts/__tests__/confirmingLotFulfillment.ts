@@ -1,11 +1,24 @@
 import * as anchor from "@coral-xyz/anchor";
 import {
   airdrop,
+  authorizeManager,
+  findAuthorizationPDA,
+  findLotPDA,
+  findManagerPDA,
+  findOfferConfigPDA,
+  findOrderBalancePDA,
+  findReceiptPDA,
   initialize,
   LOT_PRICE_IN_USDC,
   MintMetadata,
   PRICE_PER_TREE,
   program,
+  findCertificationPDA,
+  EVIDENCE_HASH,
+  CERTIFICATION_VALID_FOR,
+  findBondPDA,
+  postBond,
+  findLotEscrowPDA,
 } from "../client/utils";
 import {
   Account,
@@ -13,7 +26,6 @@ import {
   getAssociatedTokenAddressSync,
   getMint,
   getOrCreateAssociatedTokenAccount,
-  getTokenMetadata,
   mintTo,
   TOKEN_2022_PROGRAM_ID,
   TOKEN_PROGRAM_ID,
@@ -23,14 +35,14 @@ import { SendTransactionError } from "@solana/web3.js";
 describe("confirmingLots", () => {
   let admin: anchor.web3.Keypair;
   let contractPK: anchor.web3.PublicKey;
-  let offersAccount: anchor.web3.Keypair;
+  let offersGroup: anchor.web3.Keypair;
+  let lotsGroup: anchor.web3.Keypair;
   let usdc: { mint: anchor.web3.PublicKey; authority: anchor.web3.Keypair };
   let contractUsdcTokenAccount: anchor.web3.PublicKey;
   let user: anchor.web3.Keypair;
   let orderMint: anchor.web3.Keypair;
   let userAta: Account;
   let userOrderTokenAccount: anchor.web3.PublicKey;
-  let lotsAccount: anchor.web3.Keypair;
   let manager: anchor.web3.Keypair;
   let lotMint: anchor.web3.Keypair;
   let userLotTokenAccount: anchor.web3.PublicKey;
@@ -45,10 +57,10 @@ describe("confirmingLots", () => {
     ({
       admin,
       contractPK,
-      offersAccount,
+      offersGroup,
+      lotsGroup,
       usdc,
       contractUsdcTokenAccount,
-      lotsAccount,
       certificationMint,
     } = await initialize());
     user = anchor.web3.Keypair.generate();
@@ -83,14 +95,15 @@ describe("confirmingLots", () => {
     const addOfferAccounts = {
       admin: admin.publicKey,
       contract: contractPK,
-      offersAccount: offersAccount.publicKey,
+      offersGroup: offersGroup.publicKey,
       orderMint: orderMint.publicKey,
+      offerConfig: findOfferConfigPDA(contractPK, orderMint.publicKey),
       systemProgram: anchor.web3.SystemProgram.programId,
       rent: anchor.web3.SYSVAR_RENT_PUBKEY,
       tokenProgram: TOKEN_2022_PROGRAM_ID,
     };
     await program.methods
-      .addOffer(orderMintMetadata)
+      .addOffer(orderMintMetadata, null)
       .accounts(addOfferAccounts)
       .signers([admin, orderMint])
       .rpc();
@@ -103,10 +116,22 @@ describe("confirmingLots", () => {
     );
     const placeOrderAccounts = {
       user: user.publicKey,
+      signer: user.publicKey,
+      sessionToken: null,
       contract: contractPK,
-      offersAccount: offersAccount.publicKey,
       offerMint: orderMint.publicKey,
       userTokenAccount: userOrderTokenAccount,
+      orderBalance: findOrderBalancePDA(
+        contractPK,
+        orderMint.publicKey,
+        user.publicKey
+      ),
+      receipt: findReceiptPDA(
+        contractPK,
+        orderMint.publicKey,
+        user.publicKey,
+        0
+      ),
       associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
       tokenProgram: TOKEN_2022_PROGRAM_ID,
       systemProgram: anchor.web3.SystemProgram.programId,
@@ -118,7 +143,11 @@ describe("confirmingLots", () => {
     };
 
     await program.methods
-      .placeOrder(new anchor.BN(0), new anchor.BN(numOrders))
+      .placeOrder(
+        new anchor.BN(numOrders),
+        new anchor.BN(numOrders * LOT_PRICE_IN_USDC),
+        null
+      )
       .accounts(placeOrderAccounts)
       .signers([user])
       .rpc();
@@ -153,25 +182,59 @@ describe("confirmingLots", () => {
       manager: manager.publicKey,
       contract: contractPK,
       certificationMint: certificationMint.publicKey,
+      certification: findCertificationPDA(contractPK, manager.publicKey),
       tokenProgram: TOKEN_2022_PROGRAM_ID,
       associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
       systemProgram: anchor.web3.SystemProgram.programId,
     };
     await program.methods
-      .certify({ tier1: {} })
+      .certify({ tier1: {} }, EVIDENCE_HASH, CERTIFICATION_VALID_FOR)
       .accounts(certifyAccounts)
       .signers([admin])
       .rpc();
 
+    await postBond({ contractPK, manager, usdc });
+    await authorizeManager({
+      contractPK,
+      user,
+      manager: manager.publicKey,
+      orderMint: orderMint.publicKey,
+      lots: numLotsPrepared,
+    });
+
     const prepareLotsAccounts = {
       user: user.publicKey,
       manager: manager.publicKey,
+      signer: manager.publicKey,
+      sessionToken: null,
       contract: contractPK,
-      offersAccount: offersAccount.publicKey,
-      lotsAccount: lotsAccount.publicKey,
       orderMint: orderMint.publicKey,
       userOrderTokenAccount: userOrderTokenAccount,
+      orderBalance: findOrderBalancePDA(
+        contractPK,
+        orderMint.publicKey,
+        user.publicKey
+      ),
+      receipt: findReceiptPDA(
+        contractPK,
+        orderMint.publicKey,
+        user.publicKey,
+        0
+      ),
       lotMint: lotMint.publicKey,
+      lotsGroup: lotsGroup.publicKey,
+      lot: findLotPDA(contractPK, lotMint.publicKey),
+      lotEscrow: findLotEscrowPDA(contractPK, lotMint.publicKey),
+      certification: findCertificationPDA(contractPK, manager.publicKey),
+      managerRecord: findManagerPDA(contractPK, manager.publicKey),
+      bond: findBondPDA(contractPK, manager.publicKey),
+      offerConfig: findOfferConfigPDA(contractPK, orderMint.publicKey),
+      authorization: findAuthorizationPDA(
+        contractPK,
+        orderMint.publicKey,
+        user.publicKey,
+        manager.publicKey
+      ),
       userLotTokenAccount,
       usdcMint: usdc.mint,
       contractUsdcTokenAccount: contractUsdcTokenAccount,
@@ -184,7 +247,6 @@ describe("confirmingLots", () => {
 
     await program.methods
       .prepareLots(
-        new anchor.BN(0),
         new anchor.BN(numLotsPrepared),
         manager.publicKey.toBase58()
       )
@@ -219,27 +281,31 @@ describe("confirmingLots", () => {
   };
 
   describe("confirmed", () => {
+    const confirmAccounts = () => ({
+      admin: admin.publicKey,
+      contract: contractPK,
+      manager: manager.publicKey,
+      certificationMint: certificationMint.publicKey,
+      certification: findCertificationPDA(contractPK, manager.publicKey),
+      usdcMint: usdc.mint,
+      tokenProgramStandard: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      lotMint: lotMint.publicKey,
+      lot: findLotPDA(contractPK, lotMint.publicKey),
+      lotEscrow: findLotEscrowPDA(contractPK, lotMint.publicKey),
+      dispute: null,
+      managerRecord: findManagerPDA(contractPK, manager.publicKey),
+      bond: findBondPDA(contractPK, manager.publicKey),
+      tokenProgram: TOKEN_2022_PROGRAM_ID,
+      user: user.publicKey,
+      systemProgram: anchor.web3.SystemProgram.programId,
+      orderMint: orderMint.publicKey,
+    });
     beforeAll(async () => {
       await setup();
-      const confirmAccounts = {
-        admin: admin.publicKey,
-        contract: contractPK,
-        manager: manager.publicKey,
-        certificationMint: certificationMint.publicKey,
-        usdcMint: usdc.mint,
-        tokenProgramStandard: TOKEN_PROGRAM_ID,
-        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
-        lotsAccount: lotsAccount.publicKey,
-        lotMint: lotMint.publicKey,
-        tokenProgram: TOKEN_2022_PROGRAM_ID,
-        user: user.publicKey,
-        systemProgram: anchor.web3.SystemProgram.programId,
-        offersAccount: offersAccount.publicKey,
-        orderMint: orderMint.publicKey,
-      };
       await program.methods
-        .confirmLots(true, new anchor.BN(0), new anchor.BN(0))
-        .accounts(confirmAccounts)
+        .confirmLots(true, null)
+        .accounts(confirmAccounts())
         .signers([admin])
         .rpc();
     }, 15000);
@@ -261,14 +327,20 @@ describe("confirmingLots", () => {
         new anchor.BN(LOT_PRICE_IN_USDC * numLotsPrepared).toString()
       );
     });
-    it('changes the lot\'s status to "1"', async () => {
-      const lotMintMetadata = await getTokenMetadata(
-        program.provider.connection,
-        lotMint.publicKey,
-        undefined,
-        TOKEN_2022_PROGRAM_ID
+    it("moves the lot into the growing state", async () => {
+      const lot = await program.account.lot.fetch(
+        findLotPDA(contractPK, lotMint.publicKey)
       );
-      expect(lotMintMetadata.additionalMetadata).toContainEqual(["state", "1"]);
+      expect(lot.state).toEqual({ growing: {} });
+    });
+    it("cannot confirm the same lot twice", async () => {
+      await expect(
+        program.methods
+          .confirmLots(true, null)
+          .accounts(confirmAccounts())
+          .signers([admin])
+          .rpc()
+      ).rejects.toThrow("Error Code: InvalidLotState");
     });
     it("thaws the user's lot tokens and removes permanent delegate", async () => {
       const lotTokenAccount = await getOrCreateAssociatedTokenAccount(
@@ -291,6 +363,7 @@ describe("confirmingLots", () => {
     test.todo("Revokes close authority from the lot mint");
   });
   describe("denied", () => {
+    const denialReason = 3;
     beforeAll(async () => {
       await setup();
       const confirmAccounts = {
@@ -298,21 +371,36 @@ describe("confirmingLots", () => {
         contract: contractPK,
         manager: manager.publicKey,
         certificationMint: certificationMint.publicKey,
+        certification: findCertificationPDA(contractPK, manager.publicKey),
         usdcMint: usdc.mint,
         tokenProgramStandard: TOKEN_PROGRAM_ID,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
-        lotsAccount: lotsAccount.publicKey,
         lotMint: lotMint.publicKey,
+        lot: findLotPDA(contractPK, lotMint.publicKey),
+        lotEscrow: findLotEscrowPDA(contractPK, lotMint.publicKey),
+        dispute: null,
+        managerRecord: findManagerPDA(contractPK, manager.publicKey),
+        bond: findBondPDA(contractPK, manager.publicKey),
         tokenProgram: TOKEN_2022_PROGRAM_ID,
         user: user.publicKey,
         systemProgram: anchor.web3.SystemProgram.programId,
-        offersAccount: offersAccount.publicKey,
         orderMint: orderMint.publicKey,
+        orderBalance: findOrderBalancePDA(
+          contractPK,
+          orderMint.publicKey,
+          user.publicKey
+        ),
+        receipt: findReceiptPDA(
+          contractPK,
+          orderMint.publicKey,
+          user.publicKey,
+          0
+        ),
       };
       await airdrop(program.provider.publicKey);
       await airdrop(contractPK);
       await program.methods
-        .confirmLots(false, new anchor.BN(0), new anchor.BN(0))
+        .confirmLots(false, denialReason)
         .accounts(confirmAccounts)
         .signers([admin])
         .rpc();
@@ -331,9 +419,7 @@ describe("confirmingLots", () => {
           managerUsdcTokenAccount
         );
 
-      expect(managerUsdcTokenAccountBalance.value.amount).toEqual(
-        new anchor.BN(LOT_PRICE_IN_USDC * numLotsPrepared * 0.1).toString()
-      );
+      expect(managerUsdcTokenAccountBalance.value.amount).toEqual("0");
     });
     it("replenishes the 10% of the lot's original price to the contract", async () => {
       const contractUsdcTokenAccountBalance =
@@ -355,6 +441,16 @@ describe("confirmingLots", () => {
         new anchor.BN(numOrders).toString()
       );
     });
+    it("puts the order tokens back on their receipt", async () => {
+      const receipt = await program.account.orderReceipt.fetch(
+        findReceiptPDA(contractPK, orderMint.publicKey, user.publicKey, 0)
+      );
+      expect(receipt.quantityRemaining.toNumber()).toEqual(numOrders);
+      const orderBalance = await program.account.orderBalance.fetch(
+        findOrderBalancePDA(contractPK, orderMint.publicKey, user.publicKey)
+      );
+      expect(orderBalance.quantity.toNumber()).toEqual(numOrders);
+    });
     it("closes the lot mint", async () => {
       await expect(
         getMint(
@@ -365,6 +461,26 @@ describe("confirmingLots", () => {
         )
       ).rejects.toThrow();
     });
+    it("closes the lot's escrow", async () => {
+      expect(
+        await program.provider.connection.getAccountInfo(
+          findLotEscrowPDA(contractPK, lotMint.publicKey)
+        )
+      ).toBeNull();
+    });
+    it("closes the lot record", async () => {
+      expect(
+        await program.provider.connection.getAccountInfo(
+          findLotPDA(contractPK, lotMint.publicKey)
+        )
+      ).toBeNull();
+    });
+    it("no longer counts the lots against the manager", async () => {
+      const managerRecord = await program.account.managerRecord.fetch(
+        findManagerPDA(contractPK, manager.publicKey)
+      );
+      expect(managerRecord.activeLots.toNumber()).toEqual(0);
+    });
     it("decertifies the manager", async () => {
       const managerCertificationTokenAccount =
         await getOrCreateAssociatedTokenAccount(
@@ -380,5 +496,12 @@ describe("confirmingLots", () => {
         );
       expect(managerCertificationTokenAccount.amount).toEqual(5n);
     });
+    it("records why the lot was denied", async () => {
+      const certification = await program.account.certification.fetch(
+        findCertificationPDA(contractPK, manager.publicKey)
+      );
+      expect(certification.lastDenial.lotMint).toEqual(lotMint.publicKey);
+      expect(certification.lastDenial.reason).toEqual(denialReason);
+    });
   });
 });
@@ -0,0 +1,261 @@
+import * as anchor from "@coral-xyz/anchor";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  TOKEN_2022_PROGRAM_ID,
+  TOKEN_PROGRAM_ID,
+  getAccount,
+  getAssociatedTokenAddressSync,
+} from "@solana/spl-token";
+import {
+  EVIDENCE_HASH,
+  LOT_PRICE_IN_USDC,
+  PreparedLot,
+  airdrop,
+  confirmLot,
+  findDisputePDA,
+  prepareAnotherLot,
+  program,
+  setupPreparedLot,
+} from "../client/utils";
+
+describe("disputes", () => {
+  const denialReason = 7;
+  const managerEvidence = Array.from({ length: 32 }, () => 9);
+  const arbitrator = anchor.web3.Keypair.generate();
+  let lot: PreparedLot;
+  let managerUsdcAccount: anchor.web3.PublicKey;
+  const balance = async (account: anchor.web3.PublicKey) =>
+    Number(
+      (
+        await getAccount(
+          program.provider.connection,
+          account,
+          undefined,
+          TOKEN_PROGRAM_ID
+        )
+      ).amount
+    );
+  const setArbitrator = (
+    arbitrator: anchor.web3.PublicKey,
+    contestWindow: number
+  ) =>
+    program.methods
+      .setArbitrator(arbitrator, new anchor.BN(contestWindow))
+      .accounts({ admin: lot.admin.publicKey, contract: lot.contractPK })
+      .signers([lot.admin])
+      .rpc();
+  const openDenial = (lot: PreparedLot) =>
+    program.methods
+      .openDenial(denialReason, EVIDENCE_HASH)
+      .accounts({
+        admin: lot.admin.publicKey,
+        contract: lot.contractPK,
+        manager: lot.manager.publicKey,
+        lotMint: lot.lotMint.publicKey,
+        lot: lot.lot,
+        dispute: findDisputePDA(lot.contractPK, lot.lotMint.publicKey),
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([lot.admin])
+      .rpc();
+  const contestDenial = (lot: PreparedLot) =>
+    program.methods
+      .contestDenial(managerEvidence)
+      .accounts({
+        manager: lot.manager.publicKey,
+        contract: lot.contractPK,
+        dispute: findDisputePDA(lot.contractPK, lot.lotMint.publicKey),
+      })
+      .signers([lot.manager])
+      .rpc();
+  const resolveDispute = (
+    lot: PreparedLot,
+    signer: anchor.web3.Keypair,
+    upheld: boolean
+  ) =>
+    program.methods
+      .resolveDispute(upheld)
+      .accounts({
+        admin: signer.publicKey,
+        adminUsdcTokenAccount: null,
+        contract: lot.contractPK,
+        manager: lot.manager.publicKey,
+        certificationMint: lot.certificationMint.publicKey,
+        certification: lot.certification,
+        usdcMint: lot.usdc.mint,
+        tokenProgramStandard: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        lotMint: lot.lotMint.publicKey,
+        lot: lot.lot,
+        lotEscrow: lot.lotEscrow,
+        dispute: findDisputePDA(lot.contractPK, lot.lotMint.publicKey),
+        managerRecord: lot.managerRecord,
+        bond: lot.bond,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        user: lot.user.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        orderMint: lot.orderMint.publicKey,
+        orderBalance: lot.orderBalance,
+        receipt: lot.receipt,
+        userUsdcTokenAccount: null,
+      })
+      .signers([signer])
+      .rpc();
+
+  beforeAll(async () => {
+    lot = await setupPreparedLot({ numOrders: 5, numLotsPrepared: 3 });
+    managerUsdcAccount = getAssociatedTokenAddressSync(
+      lot.usdc.mint,
+      lot.manager.publicKey,
+      false,
+      TOKEN_PROGRAM_ID,
+      ASSOCIATED_TOKEN_PROGRAM_ID
+    );
+    await airdrop(arbitrator.publicKey);
+  }, 15000);
+
+  it("needs an arbitrator to open a denial", async () => {
+    await expect(openDenial(lot)).rejects.toThrow(
+      "Error Code: ArbitratorNotSet"
+    );
+  });
+
+  it("doesn't let the admin arbitrate", async () => {
+    await expect(setArbitrator(lot.admin.publicKey, 60)).rejects.toThrow(
+      "Error Code: ArbitratorCannotBeAdmin"
+    );
+  });
+
+  it("sends denials through disputes with an arbitrator", async () => {
+    await setArbitrator(arbitrator.publicKey, 24 * 60 * 60);
+    await expect(confirmLot(lot, denialReason)).rejects.toThrow(
+      "Error Code: DenialMustBeDisputed"
+    );
+  });
+
+  describe("overturned", () => {
+    beforeAll(async () => {
+      await openDenial(lot);
+    });
+    it("holds the lot in dispute with its fee escrowed", async () => {
+      const disputedLot = await program.account.lot.fetch(lot.lot);
+      expect(disputedLot.state).toEqual({ disputed: {} });
+      expect(await balance(lot.lotEscrow)).toEqual(
+        (LOT_PRICE_IN_USDC * 3) / 10
+      );
+      const dispute = await program.account.dispute.fetch(
+        findDisputePDA(lot.contractPK, lot.lotMint.publicKey)
+      );
+      expect(dispute.reason).toEqual(denialReason);
+      expect(dispute.managerEvidenceHash).toBeNull();
+    });
+    it("only lets the arbitrator resolve it", async () => {
+      await expect(resolveDispute(lot, lot.admin, false)).rejects.toThrow(
+        "Error Code: NotArbitrator"
+      );
+    });
+    it("waits out the contest window of uncontested denials", async () => {
+      await expect(resolveDispute(lot, arbitrator, false)).rejects.toThrow(
+        "Error Code: ContestWindowOpen"
+      );
+    });
+    it("lets the manager contest it", async () => {
+      await contestDenial(lot);
+      const dispute = await program.account.dispute.fetch(
+        findDisputePDA(lot.contractPK, lot.lotMint.publicKey)
+      );
+      expect(dispute.managerEvidenceHash).toEqual(managerEvidence);
+    });
+    it("confirms the lot when the arbitrator overturns it", async () => {
+      await resolveDispute(lot, arbitrator, false);
+      const confirmedLot = await program.account.lot.fetch(lot.lot);
+      expect(confirmedLot.state).toEqual({ growing: {} });
+      expect(await balance(managerUsdcAccount)).toEqual(LOT_PRICE_IN_USDC * 3);
+      const dispute = await program.account.dispute.fetchNullable(
+        findDisputePDA(lot.contractPK, lot.lotMint.publicKey)
+      );
+      expect(dispute).toBeNull();
+    });
+  });
+
+  describe("upheld", () => {
+    let secondLot: PreparedLot;
+    beforeAll(async () => {
+      secondLot = { ...lot, ...(await prepareAnotherLot(lot, 2)) };
+      await setArbitrator(arbitrator.publicKey, 1);
+      await openDenial(secondLot);
+    }, 15000);
+    it("stops taking contests once the window closes", async () => {
+      await new Promise((resolve) => setTimeout(resolve, 2000));
+      await expect(contestDenial(secondLot)).rejects.toThrow(
+        "Error Code: ContestWindowClosed"
+      );
+    });
+    it("denies the lot when the arbitrator upholds it", async () => {
+      await resolveDispute(secondLot, arbitrator, true);
+      expect(await program.account.lot.fetchNullable(secondLot.lot)).toBeNull();
+      const userOrders = await getAccount(
+        program.provider.connection,
+        lot.userOrderTokenAccount,
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      );
+      expect(Number(userOrders.amount)).toEqual(2);
+      const certification = await program.account.certification.fetch(
+        lot.certification
+      );
+      expect(certification.lastDenial.reason).toEqual(denialReason);
+      expect(certification.status).toEqual({ decertified: {} });
+    });
+  });
+
+  describe("while a dispute is open", () => {
+    let thirdLot: PreparedLot;
+    const setArbitrator = (pubkey: anchor.web3.PublicKey | null) =>
+      program.methods
+        .setArbitrator(pubkey, new anchor.BN(1))
+        .accounts({
+          admin: thirdLot.admin.publicKey,
+          contract: thirdLot.contractPK,
+        })
+        .signers([thirdLot.admin])
+        .rpc();
+    beforeAll(async () => {
+      thirdLot = await setupPreparedLot({ numOrders: 1, numLotsPrepared: 1 });
+      await setArbitrator(arbitrator.publicKey);
+      await openDenial(thirdLot);
+      await new Promise((resolve) => setTimeout(resolve, 2000));
+    }, 15000);
+    it("records the arbitrator on the dispute", async () => {
+      const dispute = await program.account.dispute.fetch(
+        findDisputePDA(thirdLot.contractPK, thirdLot.lotMint.publicKey)
+      );
+      expect(dispute.arbitrator).toEqual(arbitrator.publicKey);
+    });
+    it("doesn't let the admin unset or replace the arbitrator", async () => {
+      await expect(setArbitrator(null)).rejects.toThrow(
+        "Error Code: DisputesOpen"
+      );
+      await expect(
+        setArbitrator(anchor.web3.Keypair.generate().publicKey)
+      ).rejects.toThrow("Error Code: DisputesOpen");
+    });
+    it("doesn't let the admin resolve it", async () => {
+      await expect(
+        resolveDispute(thirdLot, thirdLot.admin, false)
+      ).rejects.toThrow("Error Code: NotArbitrator");
+    });
+    it("lets the arbitrator change once it's resolved", async () => {
+      await resolveDispute(thirdLot, arbitrator, false);
+      const confirmedLot = await program.account.lot.fetch(thirdLot.lot);
+      expect(confirmedLot.state).toEqual({ growing: {} });
+      await setArbitrator(null);
+      const contract = await program.account.contract.fetch(
+        thirdLot.contractPK
+      );
+      expect(contract.arbitrator).toBeNull();
+      expect(contract.openDisputes.toNumber()).toEqual(0);
+    });
+  });
+});
@@ -0,0 +1,112 @@
+import * as anchor from "@coral-xyz/anchor";
+import { TOKEN_2022_PROGRAM_ID } from "@solana/spl-token";
+import {
+  DEFAULT_FEE_CONFIG,
+  FeeConfig,
+  findOfferConfigPDA,
+  LOT_PRICE_IN_USDC,
+  PreparedLot,
+  program,
+  setupPreparedLot,
+} from "../client/utils";
+
+describe("fees", () => {
+  const OFFER_FEE_CONFIG: FeeConfig = {
+    upfrontFeeBps: 2000,
+    userProfitBps: 6000,
+    adminProfitBps: 1000,
+    managerProfitBps: 3000,
+  };
+  const numLotsPrepared = 3;
+  let lot: PreparedLot;
+  beforeAll(async () => {
+    lot = await setupPreparedLot({
+      numLotsPrepared,
+      offerFeeConfig: OFFER_FEE_CONFIG,
+    });
+  }, 15000);
+
+  describe("contract", () => {
+    it("starts with the fees it was initialized with", async () => {
+      const contract = await program.account.contract.fetch(lot.contractPK);
+      expect(contract.feeConfig).toEqual(DEFAULT_FEE_CONFIG);
+    });
+    it("lets the admin update the fees", async () => {
+      const feeConfig = { ...DEFAULT_FEE_CONFIG, upfrontFeeBps: 500 };
+      await program.methods
+        .updateConfig(feeConfig)
+        .accounts({ admin: lot.admin.publicKey, contract: lot.contractPK })
+        .signers([lot.admin])
+        .rpc();
+      const contract = await program.account.contract.fetch(lot.contractPK);
+      expect(contract.feeConfig).toEqual(feeConfig);
+    });
+    it("rejects profit splits that don't add up to 100%", async () => {
+      await expect(
+        program.methods
+          .updateConfig({ ...DEFAULT_FEE_CONFIG, userProfitBps: 6000 })
+          .accounts({ admin: lot.admin.publicKey, contract: lot.contractPK })
+          .signers([lot.admin])
+          .rpc()
+      ).rejects.toThrow("Error Code: InvalidFeeConfig");
+    });
+    it("only lets the admin update the fees", async () => {
+      const notAdmin = anchor.web3.Keypair.generate();
+      await expect(
+        program.methods
+          .updateConfig(DEFAULT_FEE_CONFIG)
+          .accounts({ admin: notAdmin.publicKey, contract: lot.contractPK })
+          .signers([notAdmin])
+          .rpc()
+      ).rejects.toThrow();
+    });
+  });
+
+  describe("offer override", () => {
+    const offerConfigAccounts = () => ({
+      admin: lot.admin.publicKey,
+      contract: lot.contractPK,
+      orderMint: lot.orderMint.publicKey,
+      offerConfig: findOfferConfigPDA(lot.contractPK, lot.orderMint.publicKey),
+      tokenProgram: TOKEN_2022_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    });
+    it("stores the offer's fees", async () => {
+      const offerConfig = await program.account.offerConfig.fetch(
+        findOfferConfigPDA(lot.contractPK, lot.orderMint.publicKey)
+      );
+      expect(offerConfig.orderMint).toEqual(lot.orderMint.publicKey);
+      expect(offerConfig.feeConfig).toEqual(OFFER_FEE_CONFIG);
+    });
+    it("escrows the offer's upfront fee", async () => {
+      const balance = await program.provider.connection.getTokenAccountBalance(
+        lot.lotEscrow
+      );
+      expect(balance.value.amount).toEqual(
+        ((LOT_PRICE_IN_USDC * numLotsPrepared * 2000) / 10000).toString()
+      );
+    });
+    it("keeps the fees the lot was prepared under", async () => {
+      await program.methods
+        .updateOfferConfig(null)
+        .accounts(offerConfigAccounts())
+        .signers([lot.admin])
+        .rpc();
+      const offerConfig = await program.account.offerConfig.fetch(
+        findOfferConfigPDA(lot.contractPK, lot.orderMint.publicKey)
+      );
+      expect(offerConfig.feeConfig).toBeNull();
+      const preparedLot = await program.account.lot.fetch(lot.lot);
+      expect(preparedLot.feeConfig).toEqual(OFFER_FEE_CONFIG);
+    });
+    it("rejects invalid offer fees", async () => {
+      await expect(
+        program.methods
+          .updateOfferConfig({ ...OFFER_FEE_CONFIG, upfrontFeeBps: 10001 })
+          .accounts(offerConfigAccounts())
+          .signers([lot.admin])
+          .rpc()
+      ).rejects.toThrow("Error Code: InvalidFeeConfig");
+    });
+  });
+});
@@ -0,0 +1,225 @@
+import * as anchor from "@coral-xyz/anchor";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  TOKEN_2022_PROGRAM_ID,
+  TOKEN_PROGRAM_ID,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import {
+  EVIDENCE_HASH,
+  PreparedLot,
+  addBuyer,
+  airdrop,
+  confirmLot,
+  findBuyerPDA,
+  findHarvestExpensePDA,
+  findHarvestRecordPDA,
+  findHarvestVaultPDA,
+  findHoldingPDA,
+  program,
+  setupPreparedLot,
+} from "../client/utils";
+
+describe("harvest losses", () => {
+  const expenses = 30000; // 300 dollars
+  const lossSeasonProceeds = 10000; // 100 dollars
+  const loss = (expenses - lossSeasonProceeds) * 10 ** 4;
+  const buyer = anchor.web3.Keypair.generate();
+  let lot: PreparedLot;
+  const fund = async (owner: anchor.web3.Keypair, amount: number) => {
+    const usdcAccount = await getOrCreateAssociatedTokenAccount(
+      program.provider.connection,
+      owner,
+      lot.usdc.mint,
+      owner.publicKey,
+      false,
+      undefined,
+      undefined,
+      TOKEN_PROGRAM_ID,
+      ASSOCIATED_TOKEN_PROGRAM_ID
+    );
+    await mintTo(
+      program.provider.connection,
+      owner,
+      lot.usdc.mint,
+      usdcAccount.address,
+      lot.usdc.authority,
+      amount * 10 ** 4
+    );
+  };
+  const depositHarvest = (
+    payer: anchor.web3.Keypair,
+    harvest: number,
+    grossProceeds: number,
+    buyerRecord: anchor.web3.PublicKey | null = payer === lot.admin
+      ? null
+      : findBuyerPDA(lot.contractPK, payer.publicKey)
+  ) =>
+    program.methods
+      .depositHarvest(2026 + harvest, new anchor.BN(grossProceeds))
+      .accounts({
+        contract: lot.contractPK,
+        payer: payer.publicKey,
+        buyerRecord,
+        usdcMint: lot.usdc.mint,
+        tokenProgramStandard: TOKEN_PROGRAM_ID,
+        lotMint: lot.lotMint.publicKey,
+        lot: lot.lot,
+        harvestVault: findHarvestVaultPDA(
+          lot.contractPK,
+          lot.lotMint.publicKey
+        ),
+        harvestRecord: findHarvestRecordPDA(
+          lot.contractPK,
+          lot.lotMint.publicKey,
+          harvest
+        ),
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([payer])
+      .rpc();
+  const approveExpenseOverrun = (admin: anchor.web3.Keypair) =>
+    program.methods
+      .approveExpenseOverrun(new anchor.BN(expenses))
+      .accounts({
+        admin: admin.publicKey,
+        contract: lot.contractPK,
+        lotMint: lot.lotMint.publicKey,
+        lot: lot.lot,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([admin])
+      .rpc();
+
+  beforeAll(async () => {
+    lot = await setupPreparedLot({ numLotsPrepared: 1 });
+    await confirmLot(lot);
+    await program.methods
+      .openHolding()
+      .accounts({
+        holder: lot.user.publicKey,
+        contract: lot.contractPK,
+        lotMint: lot.lotMint.publicKey,
+        lot: lot.lot,
+        holding: findHoldingPDA(
+          lot.contractPK,
+          lot.lotMint.publicKey,
+          lot.user.publicKey
+        ),
+        holderLotTokenAccount: lot.userLotTokenAccount,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([lot.user])
+      .rpc();
+    await program.methods
+      .markReady()
+      .accounts({
+        manager: lot.manager.publicKey,
+        signer: lot.manager.publicKey,
+        sessionToken: null,
+        contract: lot.contractPK,
+        lotMint: lot.lotMint.publicKey,
+        lot: lot.lot,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([lot.manager])
+      .rpc();
+    await program.methods
+      .reportHarvestExpense(new anchor.BN(expenses), EVIDENCE_HASH)
+      .accounts({
+        manager: lot.manager.publicKey,
+        contract: lot.contractPK,
+        lotMint: lot.lotMint.publicKey,
+        lot: lot.lot,
+        expense: findHarvestExpensePDA(
+          lot.contractPK,
+          lot.lotMint.publicKey,
+          0,
+          0
+        ),
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([lot.manager])
+      .rpc();
+    await airdrop(buyer.publicKey);
+    await addBuyer({
+      contractPK: lot.contractPK,
+      admin: lot.admin,
+      buyer: buyer.publicKey,
+    });
+    await fund(buyer, 100000);
+    await fund(lot.admin, lossSeasonProceeds);
+  }, 15000);
+
+  it("only takes deposits from the admin and the buyers it added", async () => {
+    const stranger = anchor.web3.Keypair.generate();
+    await airdrop(stranger.publicKey);
+    await fund(stranger, lossSeasonProceeds);
+    await expect(
+      depositHarvest(stranger, 0, lossSeasonProceeds, null)
+    ).rejects.toThrow("Error Code: NotABuyer");
+    await expect(
+      depositHarvest(stranger, 0, lossSeasonProceeds)
+    ).rejects.toThrow("Error Code: AccountNotInitialized");
+  });
+
+  it("keeps expenses under the cap for the admin too", async () => {
+    await expect(
+      depositHarvest(buyer, 0, lossSeasonProceeds)
+    ).rejects.toThrow("Error Code: ExpensesOverCap");
+    await expect(
+      depositHarvest(lot.admin, 0, lossSeasonProceeds)
+    ).rejects.toThrow("Error Code: ExpensesOverCap");
+  });
+
+  it("only lets the admin approve expenses over the cap", async () => {
+    await expect(approveExpenseOverrun(lot.manager)).rejects.toThrow(
+      "Error Code: ConstraintHasOne"
+    );
+    await approveExpenseOverrun(lot.admin);
+    const lotAccount = await program.account.lot.fetch(lot.lot);
+    expect(lotAccount.approvedExpenses.toNumber()).toEqual(expenses * 10 ** 4);
+  });
+
+  it("carries an approved loss forward on the lot", async () => {
+    await depositHarvest(buyer, 0, lossSeasonProceeds);
+    const lotAccount = await program.account.lot.fetch(lot.lot);
+    expect(lotAccount.approvedExpenses.toNumber()).toEqual(0);
+    expect(lotAccount.carriedLoss.toNumber()).toEqual(loss);
+    expect(lotAccount.holdersUnclaimed.toNumber()).toEqual(0);
+    // The proceeds all go towards the manager's expenses
+    expect(lotAccount.managerUnclaimed.toNumber()).toEqual(
+      lossSeasonProceeds * 10 ** 4
+    );
+    const record = await program.account.harvestRecord.fetch(
+      findHarvestRecordPDA(lot.contractPK, lot.lotMint.publicKey, 0)
+    );
+    expect(record.profit.toNumber()).toEqual(-loss);
+    // The default cap is half the gross revenue
+    expect(record.overCap.toNumber()).toEqual(
+      (expenses - lossSeasonProceeds / 2) * 10 ** 4
+    );
+    expect(record.carriedLoss.toNumber()).toEqual(loss);
+  });
+
+  it("pays off the loss before splitting later profit", async () => {
+    const grossProceeds = 30000;
+    await depositHarvest(buyer, 1, grossProceeds);
+    const lotAccount = await program.account.lot.fetch(lot.lot);
+    expect(lotAccount.carriedLoss.toNumber()).toEqual(0);
+    const splitProfit = grossProceeds * 10 ** 4 - loss;
+    expect(lotAccount.holdersUnclaimed.toNumber()).toEqual(splitProfit / 2);
+    const record = await program.account.harvestRecord.fetch(
+      findHarvestRecordPDA(lot.contractPK, lot.lotMint.publicKey, 1)
+    );
+    expect(record.lossRecovered.toNumber()).toEqual(loss);
+    expect(record.holdersAmount.toNumber()).toEqual(splitProfit / 2);
+    expect(lotAccount.harvestTotals.profit.toNumber()).toEqual(
+      grossProceeds * 10 ** 4 - loss
+    );
+  });
+});
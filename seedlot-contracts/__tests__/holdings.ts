@@ -0,0 +1,326 @@
+import * as anchor from "@coral-xyz/anchor";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  TOKEN_2022_PROGRAM_ID,
+  TOKEN_PROGRAM_ID,
+  getAccount,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+  transferChecked,
+} from "@solana/spl-token";
+import {
+  PreparedLot,
+  UNITS_PER_LOT,
+  addBuyer,
+  airdrop,
+  confirmLot,
+  findBuyerPDA,
+  findHarvestRecordPDA,
+  findHarvestVaultPDA,
+  findHoldingPDA,
+  program,
+  setupPreparedLot,
+} from "../client/utils";
+
+describe("holdings", () => {
+  const profit = 20000; // 200 dollars
+  const holdersShareInUsdc = (profit * 10 ** 4) / 2;
+  const investor = anchor.web3.Keypair.generate();
+  let lot: PreparedLot;
+  let investorLotTokenAccount: anchor.web3.PublicKey;
+  const holdingAccounts = (
+    holder: anchor.web3.PublicKey,
+    holderLotTokenAccount: anchor.web3.PublicKey
+  ) => ({
+    holder,
+    contract: lot.contractPK,
+    lotMint: lot.lotMint.publicKey,
+    lot: lot.lot,
+    holding: findHoldingPDA(lot.contractPK, lot.lotMint.publicKey, holder),
+    holderLotTokenAccount,
+    tokenProgram: TOKEN_2022_PROGRAM_ID,
+    systemProgram: anchor.web3.SystemProgram.programId,
+  });
+  const openHolding = (
+    holder: anchor.web3.Keypair,
+    holderLotTokenAccount: anchor.web3.PublicKey
+  ) =>
+    program.methods
+      .openHolding()
+      .accounts(holdingAccounts(holder.publicKey, holderLotTokenAccount))
+      .signers([holder])
+      .rpc();
+  const claimAccounts = (
+    holder: anchor.web3.Keypair,
+    holderLotTokenAccount: anchor.web3.PublicKey
+  ) => ({
+    ...holdingAccounts(holder.publicKey, holderLotTokenAccount),
+    usdcMint: lot.usdc.mint,
+    harvestVault: findHarvestVaultPDA(lot.contractPK, lot.lotMint.publicKey),
+    tokenProgramStandard: TOKEN_PROGRAM_ID,
+    associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+  });
+  const usdcBalance = async (owner: anchor.web3.Keypair) =>
+    Number(
+      (
+        await getOrCreateAssociatedTokenAccount(
+          program.provider.connection,
+          owner,
+          lot.usdc.mint,
+          owner.publicKey,
+          false,
+          undefined,
+          undefined,
+          TOKEN_PROGRAM_ID,
+          ASSOCIATED_TOKEN_PROGRAM_ID
+        )
+      ).amount
+    );
+  const depositHarvest = async (index: number) => {
+    const payer = anchor.web3.Keypair.generate();
+    await airdrop(payer.publicKey);
+    await addBuyer({
+      contractPK: lot.contractPK,
+      admin: lot.admin,
+      buyer: payer.publicKey,
+    });
+    const payerUsdcAccount = await getOrCreateAssociatedTokenAccount(
+      program.provider.connection,
+      payer,
+      lot.usdc.mint,
+      payer.publicKey,
+      false,
+      undefined,
+      undefined,
+      TOKEN_PROGRAM_ID,
+      ASSOCIATED_TOKEN_PROGRAM_ID
+    );
+    await mintTo(
+      program.provider.connection,
+      payer,
+      lot.usdc.mint,
+      payerUsdcAccount.address,
+      lot.usdc.authority,
+      profit * 10 ** 4
+    );
+    await program.methods
+      .depositHarvest(2026 + index, new anchor.BN(profit))
+      .accounts({
+        contract: lot.contractPK,
+        payer: payer.publicKey,
+        buyerRecord: findBuyerPDA(lot.contractPK, payer.publicKey),
+        usdcMint: lot.usdc.mint,
+        tokenProgramStandard: TOKEN_PROGRAM_ID,
+        lotMint: lot.lotMint.publicKey,
+        lot: lot.lot,
+        harvestVault: findHarvestVaultPDA(
+          lot.contractPK,
+          lot.lotMint.publicKey
+        ),
+        harvestRecord: findHarvestRecordPDA(
+          lot.contractPK,
+          lot.lotMint.publicKey,
+          index
+        ),
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([payer])
+      .rpc();
+  };
+  const claim = (
+    holder: anchor.web3.Keypair,
+    holderLotTokenAccount: anchor.web3.PublicKey
+  ) =>
+    program.methods
+      .claimHarvestShare()
+      .accounts(claimAccounts(holder, holderLotTokenAccount))
+      .signers([holder])
+      .rpc();
+  const closeHolding = (
+    holder: anchor.web3.Keypair,
+    holderLotTokenAccount: anchor.web3.PublicKey
+  ) =>
+    program.methods
+      .closeHolding()
+      .accounts(claimAccounts(holder, holderLotTokenAccount))
+      .signers([holder])
+      .rpc();
+
+  beforeAll(async () => {
+    lot = await setupPreparedLot({ numLotsPrepared: 2 });
+    await airdrop(investor.publicKey);
+    investorLotTokenAccount = (
+      await getOrCreateAssociatedTokenAccount(
+        program.provider.connection,
+        investor,
+        lot.lotMint.publicKey,
+        investor.publicKey,
+        false,
+        undefined,
+        undefined,
+        TOKEN_2022_PROGRAM_ID,
+        ASSOCIATED_TOKEN_PROGRAM_ID
+      )
+    ).address;
+  }, 15000);
+
+  it("only registers holdings in confirmed lots", async () => {
+    await expect(
+      openHolding(lot.user, lot.userLotTokenAccount)
+    ).rejects.toThrow("Error Code: InvalidLotState");
+  });
+
+  it("lets a quarter of the lot change hands", async () => {
+    await confirmLot(lot);
+    await program.methods
+      .thawLotAccount()
+      .accounts({
+        holder: investor.publicKey,
+        contract: lot.contractPK,
+        lotMint: lot.lotMint.publicKey,
+        lot: lot.lot,
+        holding: findHoldingPDA(
+          lot.contractPK,
+          lot.lotMint.publicKey,
+          investor.publicKey
+        ),
+        holderLotTokenAccount: investorLotTokenAccount,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([investor])
+      .rpc();
+    await transferChecked(
+      program.provider.connection,
+      lot.user,
+      lot.userLotTokenAccount,
+      lot.lotMint.publicKey,
+      investorLotTokenAccount,
+      lot.user,
+      UNITS_PER_LOT / 2,
+      6,
+      undefined,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    const investorLots = await getAccount(
+      program.provider.connection,
+      investorLotTokenAccount,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    expect(Number(investorLots.amount)).toEqual(UNITS_PER_LOT / 2);
+  });
+
+  it("freezes the lot tokens of open holdings", async () => {
+    await openHolding(lot.user, lot.userLotTokenAccount);
+    await openHolding(investor, investorLotTokenAccount);
+    const lotAccount = await program.account.lot.fetch(lot.lot);
+    expect(lotAccount.heldUnits.toNumber()).toEqual(2 * UNITS_PER_LOT);
+    const userLots = await getAccount(
+      program.provider.connection,
+      lot.userLotTokenAccount,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    expect(userLots.isFrozen).toBe(true);
+  });
+
+  it("splits the holders' share of a harvest pro rata", async () => {
+    await program.methods
+      .markReady()
+      .accounts({
+        manager: lot.manager.publicKey,
+        signer: lot.manager.publicKey,
+        sessionToken: null,
+        contract: lot.contractPK,
+        lotMint: lot.lotMint.publicKey,
+        lot: lot.lot,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([lot.manager])
+      .rpc();
+    await depositHarvest(0);
+
+    const userBefore = await usdcBalance(lot.user);
+    await claim(lot.user, lot.userLotTokenAccount);
+    expect((await usdcBalance(lot.user)) - userBefore).toEqual(
+      (holdersShareInUsdc * 3) / 4
+    );
+
+    await closeHolding(investor, investorLotTokenAccount);
+    expect(await usdcBalance(investor)).toEqual(holdersShareInUsdc / 4);
+  });
+
+  it("frees the lot tokens of closed holdings", async () => {
+    const investorLots = await getAccount(
+      program.provider.connection,
+      investorLotTokenAccount,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    expect(investorLots.isFrozen).toBe(false);
+    const lotAccount = await program.account.lot.fetch(lot.lot);
+    expect(lotAccount.heldUnits.toNumber()).toEqual((3 * UNITS_PER_LOT) / 2);
+    expect(lotAccount.holdersUnclaimed.toNumber()).toEqual(0);
+    const holding = await program.account.holding.fetch(
+      findHoldingPDA(lot.contractPK, lot.lotMint.publicKey, investor.publicKey)
+    );
+    expect(holding.units.toNumber()).toEqual(0);
+    expect(holding.paidThrough.eq(lotAccount.profitPerUnit)).toBe(true);
+  });
+
+  it("doesn't pay the same harvest twice", async () => {
+    const userBefore = await usdcBalance(lot.user);
+    await claim(lot.user, lot.userLotTokenAccount);
+    expect(await usdcBalance(lot.user)).toEqual(userBefore);
+  });
+
+  it("holds the share of units outside holdings until they're registered", async () => {
+    await depositHarvest(1);
+    let lotAccount = await program.account.lot.fetch(lot.lot);
+    expect(lotAccount.unregisteredProfit.toNumber()).toEqual(
+      holdersShareInUsdc / 4
+    );
+
+    const userBefore = await usdcBalance(lot.user);
+    await claim(lot.user, lot.userLotTokenAccount);
+    expect((await usdcBalance(lot.user)) - userBefore).toEqual(
+      (holdersShareInUsdc * 3) / 4
+    );
+
+    await openHolding(investor, investorLotTokenAccount);
+    lotAccount = await program.account.lot.fetch(lot.lot);
+    expect(lotAccount.unregisteredProfit.toNumber()).toEqual(0);
+    const investorBefore = await usdcBalance(investor);
+    await claim(investor, investorLotTokenAccount);
+    expect((await usdcBalance(investor)) - investorBefore).toEqual(
+      holdersShareInUsdc / 4
+    );
+  });
+
+  it("doesn't pay units what they earned outside holdings twice", async () => {
+    await closeHolding(lot.user, lot.userLotTokenAccount);
+    await depositHarvest(2);
+
+    // The investor's share is paid on closing and not again on reopening
+    const investorBefore = await usdcBalance(investor);
+    await closeHolding(investor, investorLotTokenAccount);
+    await openHolding(investor, investorLotTokenAccount);
+    await claim(investor, investorLotTokenAccount);
+    expect((await usdcBalance(investor)) - investorBefore).toEqual(
+      holdersShareInUsdc / 4
+    );
+
+    // So the user still gets all their units earned outside their holding
+    const userBefore = await usdcBalance(lot.user);
+    await openHolding(lot.user, lot.userLotTokenAccount);
+    await claim(lot.user, lot.userLotTokenAccount);
+    expect((await usdcBalance(lot.user)) - userBefore).toEqual(
+      (holdersShareInUsdc * 3) / 4
+    );
+    const lotAccount = await program.account.lot.fetch(lot.lot);
+    expect(lotAccount.unregisteredProfit.toNumber()).toEqual(0);
+    expect(lotAccount.holdersUnclaimed.toNumber()).toEqual(0);
+  });
+});
@@ -0,0 +1,110 @@
+import * as anchor from "@coral-xyz/anchor";
+import { TOKEN_2022_PROGRAM_ID } from "@solana/spl-token";
+import {
+  confirmLot,
+  PreparedLot,
+  prepareAnotherLot,
+  program,
+  setupPreparedLot,
+} from "../client/utils";
+
+describe("lotLimits", () => {
+  let lot: PreparedLot;
+  const activeLots = async () =>
+    (
+      await program.account.managerRecord.fetch(lot.managerRecord)
+    ).activeLots.toNumber();
+  const setLotLimits = (lotLimits: number[]) =>
+    program.methods
+      .setLotLimits(lotLimits.map((l) => new anchor.BN(l)))
+      .accounts({ admin: lot.admin.publicKey, contract: lot.contractPK })
+      .signers([lot.admin])
+      .rpc();
+
+  beforeAll(async () => {
+    lot = await setupPreparedLot({ numOrders: 8, numLotsPrepared: 3 });
+  }, 15000);
+
+  it("starts contracts with the default tier limits", async () => {
+    const contract = await program.account.contract.fetch(lot.contractPK);
+    expect(contract.lotLimits.map((l) => l.toString())).toEqual([
+      "10",
+      "1000",
+      "10000",
+      "18446744073709551615",
+    ]);
+  });
+  it("counts the lots a manager prepares", async () => {
+    expect(await activeLots()).toEqual(3);
+  });
+  it("only lets the admin change the limits", async () => {
+    const notAdmin = anchor.web3.Keypair.generate();
+    await expect(
+      program.methods
+        .setLotLimits([1, 1, 1, 1].map((l) => new anchor.BN(l)))
+        .accounts({ admin: notAdmin.publicKey, contract: lot.contractPK })
+        .signers([notAdmin])
+        .rpc()
+    ).rejects.toThrow();
+  });
+  it("rejects lots over the manager's tier limit", async () => {
+    await setLotLimits([4, 1000, 10000, 100000]);
+    await expect(prepareAnotherLot(lot, 2)).rejects.toThrow(
+      "Error Code: LotLimitExceeded"
+    );
+    expect(await activeLots()).toEqual(3);
+
+    await prepareAnotherLot(lot, 1);
+    expect(await activeLots()).toEqual(4);
+  });
+  it("frees up room when a lot is retired", async () => {
+    await confirmLot(lot);
+    const managerAccounts = {
+      manager: lot.manager.publicKey,
+      signer: lot.manager.publicKey,
+      sessionToken: null,
+      contract: lot.contractPK,
+      lotMint: lot.lotMint.publicKey,
+      lot: lot.lot,
+      tokenProgram: TOKEN_2022_PROGRAM_ID,
+    };
+    await program.methods
+      .markReady()
+      .accounts(managerAccounts)
+      .signers([lot.manager])
+      .rpc();
+    await program.methods
+      .markShipped()
+      .accounts({
+        admin: lot.admin.publicKey,
+        signer: lot.admin.publicKey,
+        sessionToken: null,
+        contract: lot.contractPK,
+        lotMint: lot.lotMint.publicKey,
+        lot: lot.lot,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([lot.admin])
+      .rpc();
+    await program.methods
+      .retireLot()
+      .accounts({
+        admin: lot.admin.publicKey,
+        contract: lot.contractPK,
+        manager: lot.manager.publicKey,
+        managerRecord: lot.managerRecord,
+        lotMint: lot.lotMint.publicKey,
+        lot: lot.lot,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([lot.admin])
+      .rpc();
+    expect((await program.account.lot.fetch(lot.lot)).state).toEqual({
+      retired: {},
+    });
+    expect(await activeLots()).toEqual(1);
+
+    await prepareAnotherLot(lot, 3);
+    expect(await activeLots()).toEqual(4);
+  });
+});
@@ -0,0 +1,179 @@
+import * as anchor from "@coral-xyz/anchor";
+import { TOKEN_2022_PROGRAM_ID } from "@solana/spl-token";
+import {
+  confirmLot,
+  createSession,
+  LotState,
+  PreparedLot,
+  program,
+  Session,
+  setupPreparedLot,
+} from "../client/utils";
+
+describe("lotProgress", () => {
+  let lot: PreparedLot;
+  const managerAccounts = () => ({
+    manager: lot.manager.publicKey,
+    signer: lot.manager.publicKey,
+    sessionToken: null,
+    contract: lot.contractPK,
+    lotMint: lot.lotMint.publicKey,
+    lot: lot.lot,
+    tokenProgram: TOKEN_2022_PROGRAM_ID,
+  });
+  const adminAccounts = () => ({
+    admin: lot.admin.publicKey,
+    signer: lot.admin.publicKey,
+    sessionToken: null,
+    contract: lot.contractPK,
+    lotMint: lot.lotMint.publicKey,
+    lot: lot.lot,
+    tokenProgram: TOKEN_2022_PROGRAM_ID,
+  });
+  const lotState = async (): Promise<LotState> =>
+    (await program.account.lot.fetch(lot.lot)).state;
+
+  beforeAll(async () => {
+    lot = await setupPreparedLot();
+  }, 15000);
+
+  it("does not let the manager skip confirmation", async () => {
+    await expect(
+      program.methods
+        .markReady()
+        .accounts(managerAccounts())
+        .signers([lot.manager])
+        .rpc()
+    ).rejects.toThrow("Error Code: InvalidLotStateTransition");
+    await expect(
+      program.methods
+        .markGrowing()
+        .accounts(managerAccounts())
+        .signers([lot.manager])
+        .rpc()
+    ).rejects.toThrow("Error Code: InvalidLotStateTransition");
+    expect(await lotState()).toEqual({ preparation: {} });
+  });
+  it("walks a confirmed lot through a full season", async () => {
+    await confirmLot(lot);
+    expect(await lotState()).toEqual({ growing: {} });
+
+    await program.methods
+      .markReady()
+      .accounts(managerAccounts())
+      .signers([lot.manager])
+      .rpc();
+    expect(await lotState()).toEqual({ ready: {} });
+
+    await program.methods
+      .markShipped()
+      .accounts(adminAccounts())
+      .signers([lot.admin])
+      .rpc();
+    expect(await lotState()).toEqual({ shipped: {} });
+
+    await program.methods
+      .markGrowing()
+      .accounts(managerAccounts())
+      .signers([lot.manager])
+      .rpc();
+    expect(await lotState()).toEqual({ growing: {} });
+  });
+  it("only lets the lot's manager mark progress", async () => {
+    const otherManager = anchor.web3.Keypair.generate();
+    await expect(
+      program.methods
+        .markReady()
+        .accounts({
+          ...managerAccounts(),
+          manager: otherManager.publicKey,
+          signer: otherManager.publicKey,
+        })
+        .signers([otherManager])
+        .rpc()
+    ).rejects.toThrow("Error Code: ManagerMismatch");
+  });
+  it("doesn't let someone else sign for the manager without a session", async () => {
+    const someoneElse = anchor.web3.Keypair.generate();
+    await expect(
+      program.methods
+        .markReady()
+        .accounts({ ...managerAccounts(), signer: someoneElse.publicKey })
+        .signers([someoneElse])
+        .rpc()
+    ).rejects.toThrow("Error Code: ManagerMismatch");
+  });
+  it("only lets the admin mark a lot shipped", async () => {
+    await program.methods
+      .markReady()
+      .accounts(managerAccounts())
+      .signers([lot.manager])
+      .rpc();
+    await expect(
+      program.methods
+        .markShipped()
+        .accounts({
+          ...adminAccounts(),
+          admin: lot.manager.publicKey,
+          signer: lot.manager.publicKey,
+        })
+        .signers([lot.manager])
+        .rpc()
+    ).rejects.toThrow();
+    expect(await lotState()).toEqual({ ready: {} });
+  });
+
+  describe("with session keys", () => {
+    let managerSession: Session;
+    let adminSession: Session;
+    const signedBy = (session: Session) => ({
+      signer: session.signer.publicKey,
+      sessionToken: session.token,
+    });
+
+    beforeAll(async () => {
+      managerSession = await createSession(lot.manager);
+      adminSession = await createSession(lot.admin);
+    }, 15000);
+
+    it("lets session keys mark progress for whoever approved them", async () => {
+      await program.methods
+        .markShipped()
+        .accounts({ ...adminAccounts(), ...signedBy(adminSession) })
+        .signers([adminSession.signer])
+        .rpc();
+      expect(await lotState()).toEqual({ shipped: {} });
+
+      await program.methods
+        .markGrowing()
+        .accounts({ ...managerAccounts(), ...signedBy(managerSession) })
+        .signers([managerSession.signer])
+        .rpc();
+      expect(await lotState()).toEqual({ growing: {} });
+
+      await program.methods
+        .markReady()
+        .accounts({ ...managerAccounts(), ...signedBy(managerSession) })
+        .signers([managerSession.signer])
+        .rpc();
+      expect(await lotState()).toEqual({ ready: {} });
+    });
+    it("rejects sessions approved by someone else", async () => {
+      await expect(
+        program.methods
+          .markShipped()
+          .accounts({ ...adminAccounts(), ...signedBy(managerSession) })
+          .signers([managerSession.signer])
+          .rpc()
+      ).rejects.toThrow("Error Code: InvalidToken");
+      await expect(
+        program.methods
+          .markReady()
+          .accounts({ ...managerAccounts(), ...signedBy(adminSession) })
+          .signers([adminSession.signer])
+          .rpc()
+      ).rejects.toThrow("Error Code: InvalidToken");
+      expect(await lotState()).toEqual({ ready: {} });
+    });
+  });
+});
@@ -0,0 +1,204 @@
+import * as anchor from "@coral-xyz/anchor";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  TOKEN_2022_PROGRAM_ID,
+  TOKEN_PROGRAM_ID,
+  getAccount,
+  getAssociatedTokenAddressSync,
+} from "@solana/spl-token";
+import {
+  EVIDENCE_HASH,
+  LOT_PRICE_IN_USDC,
+  PreparedLot,
+  airdrop,
+  confirmLot,
+  findDisputePDA,
+  findInspectorPDA,
+  findOfferConfigPDA,
+  prepareAnotherLot,
+  program,
+  setupPreparedLot,
+} from "../client/utils";
+
+describe("milestones", () => {
+  const numLotsPrepared = 3;
+  const totalPrice = LOT_PRICE_IN_USDC * numLotsPrepared;
+  const upfrontFee = totalPrice / 10;
+  // Half of the price after the upfront fee, then a quarter.
+  const payoutSchedule = [5000, 2500];
+  const firstMilestone = (totalPrice - upfrontFee) / 2;
+  const secondMilestone = (totalPrice - upfrontFee) / 4;
+  let lot: PreparedLot;
+  let managerUsdcAccount: anchor.web3.PublicKey;
+  const inspector = anchor.web3.Keypair.generate();
+  const balance = async (account: anchor.web3.PublicKey) =>
+    Number(
+      (
+        await getAccount(
+          program.provider.connection,
+          account,
+          undefined,
+          TOKEN_PROGRAM_ID
+        )
+      ).amount
+    );
+  const releaseMilestone = (
+    milestone: number,
+    attester: anchor.web3.Keypair,
+    inspectorRecord: anchor.web3.PublicKey | null = null,
+    target: PreparedLot = lot
+  ) =>
+    program.methods
+      .releaseMilestone(milestone, EVIDENCE_HASH)
+      .accounts({
+        attester: attester.publicKey,
+        contract: lot.contractPK,
+        inspectorRecord,
+        manager: lot.manager.publicKey,
+        lotMint: target.lotMint.publicKey,
+        lot: target.lot,
+        usdcMint: lot.usdc.mint,
+        contractUsdcTokenAccount: lot.contractUsdcTokenAccount,
+        managerUsdcTokenAccount: managerUsdcAccount,
+        tokenProgramStandard: TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([attester])
+      .rpc();
+
+  beforeAll(async () => {
+    lot = await setupPreparedLot({ numLotsPrepared, payoutSchedule });
+    managerUsdcAccount = getAssociatedTokenAddressSync(
+      lot.usdc.mint,
+      lot.manager.publicKey,
+      false,
+      TOKEN_PROGRAM_ID,
+      ASSOCIATED_TOKEN_PROGRAM_ID
+    );
+    await airdrop(inspector.publicKey);
+  }, 15000);
+
+  it("copies the offer's payout schedule onto the lot", async () => {
+    const preparedLot = await program.account.lot.fetch(lot.lot);
+    expect(preparedLot.payoutSchedule).toEqual(payoutSchedule);
+  });
+
+  it("rejects schedules paying out more than the price", async () => {
+    await expect(
+      program.methods
+        .setPayoutSchedule([5000, 5001])
+        .accounts({
+          admin: lot.admin.publicKey,
+          contract: lot.contractPK,
+          orderMint: lot.orderMint.publicKey,
+          offerConfig: findOfferConfigPDA(
+            lot.contractPK,
+            lot.orderMint.publicKey
+          ),
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([lot.admin])
+        .rpc()
+    ).rejects.toThrow("Error Code: InvalidPayoutSchedule");
+  });
+
+  it("doesn't release milestones before the lot is confirmed", async () => {
+    await expect(releaseMilestone(0, lot.admin)).rejects.toThrow(
+      "Error Code: InvalidLotState"
+    );
+  });
+
+  it("holds back the scheduled payouts at confirmation", async () => {
+    await confirmLot(lot);
+    expect(await balance(managerUsdcAccount)).toEqual(
+      totalPrice - firstMilestone - secondMilestone
+    );
+    const confirmedLot = await program.account.lot.fetch(lot.lot);
+    expect(confirmedLot.released.toNumber()).toEqual(
+      totalPrice - firstMilestone - secondMilestone
+    );
+    expect(confirmedLot.pending.toNumber()).toEqual(
+      firstMilestone + secondMilestone
+    );
+  });
+
+  it("only lets the admin or an inspector attest", async () => {
+    await expect(releaseMilestone(0, inspector)).rejects.toThrow(
+      "Error Code: NotAnAttester"
+    );
+  });
+
+  it("releases a milestone on the admin's attestation", async () => {
+    await releaseMilestone(0, lot.admin);
+    expect(await balance(managerUsdcAccount)).toEqual(
+      totalPrice - secondMilestone
+    );
+    const lotAccount = await program.account.lot.fetch(lot.lot);
+    expect(lotAccount.milestonesReleased).toEqual(1);
+    expect(lotAccount.pending.toNumber()).toEqual(secondMilestone);
+  });
+
+  it("releases milestones in order", async () => {
+    await expect(releaseMilestone(0, lot.admin)).rejects.toThrow(
+      "Error Code: MilestoneOutOfOrder"
+    );
+  });
+
+  it("releases a milestone on an inspector's attestation", async () => {
+    const inspectorRecord = findInspectorPDA(
+      lot.contractPK,
+      inspector.publicKey
+    );
+    await program.methods
+      .addInspector()
+      .accounts({
+        admin: lot.admin.publicKey,
+        contract: lot.contractPK,
+        inspector: inspector.publicKey,
+        inspectorRecord,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([lot.admin])
+      .rpc();
+    await releaseMilestone(1, inspector, inspectorRecord);
+    expect(await balance(managerUsdcAccount)).toEqual(totalPrice);
+    const lotAccount = await program.account.lot.fetch(lot.lot);
+    expect(lotAccount.released.toNumber()).toEqual(totalPrice);
+    expect(lotAccount.pending.toNumber()).toEqual(0);
+  });
+
+  it("has nothing left to release after the last milestone", async () => {
+    await expect(releaseMilestone(2, lot.admin)).rejects.toThrow(
+      "Error Code: NoPendingPayout"
+    );
+  });
+
+  it("doesn't release milestones of a disputed lot", async () => {
+    const disputedLot = { ...lot, ...(await prepareAnotherLot(lot, 1)) };
+    await program.methods
+      .setArbitrator(inspector.publicKey, new anchor.BN(60))
+      .accounts({ admin: lot.admin.publicKey, contract: lot.contractPK })
+      .signers([lot.admin])
+      .rpc();
+    await program.methods
+      .openDenial(1, EVIDENCE_HASH)
+      .accounts({
+        admin: lot.admin.publicKey,
+        contract: lot.contractPK,
+        manager: lot.manager.publicKey,
+        lotMint: disputedLot.lotMint.publicKey,
+        lot: disputedLot.lot,
+        dispute: findDisputePDA(lot.contractPK, disputedLot.lotMint.publicKey),
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([lot.admin])
+      .rpc();
+    await expect(
+      releaseMilestone(0, lot.admin, null, disputedLot)
+    ).rejects.toThrow("Error Code: InvalidLotState");
+  }, 15000);
+});
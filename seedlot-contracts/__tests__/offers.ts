@@ -1,18 +1,24 @@
 import { web3 } from "@coral-xyz/anchor";
 import {
+  findOfferConfigPDA,
   initialize,
   MintMetadata,
   program,
-  TOTAL_OFFERS,
 } from "../client/utils";
-import { getTokenMetadata, TOKEN_2022_PROGRAM_ID } from "@solana/spl-token";
+import {
+  getMint,
+  getTokenGroupMemberState,
+  getTokenGroupState,
+  getTokenMetadata,
+  TOKEN_2022_PROGRAM_ID,
+} from "@solana/spl-token";
 
 describe("Offers", () => {
   let admin: web3.Keypair;
   let contractPK: web3.PublicKey;
-  let offersAccount: web3.Keypair;
+  let offersGroup: web3.Keypair;
   beforeAll(async () => {
-    ({ admin, contractPK, offersAccount } = await initialize());
+    ({ admin, contractPK, offersGroup } = await initialize());
   });
   it("Works with a single offer", async () => {
     const orderMint = web3.Keypair.generate();
@@ -26,14 +32,15 @@ describe("Offers", () => {
     const accounts = {
       admin: admin.publicKey,
       contract: contractPK,
-      offersAccount: offersAccount.publicKey,
+      offersGroup: offersGroup.publicKey,
       orderMint: orderMint.publicKey,
+      offerConfig: findOfferConfigPDA(contractPK, orderMint.publicKey),
       systemProgram: web3.SystemProgram.programId,
       rent: web3.SYSVAR_RENT_PUBKEY,
       tokenProgram: TOKEN_2022_PROGRAM_ID,
     };
     await program.methods
-      .addOffer(orderMintMetadata)
+      .addOffer(orderMintMetadata, null)
       .accounts(accounts)
       .signers([admin, orderMint])
       .rpc();
@@ -51,87 +58,69 @@ describe("Offers", () => {
       ["variety", `variety`],
       ["price", `price`],
     ]);
-    const offers = await program.account.offers.fetch(offersAccount.publicKey);
-    expect(offers.offers[0].mint.equals(orderMint.publicKey)).toBe(true);
-    expect(offers.tail.eqn(1)).toBe(true);
+    const member = getTokenGroupMemberState(
+      await getMint(
+        program.provider.connection,
+        orderMint.publicKey,
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      )
+    );
+    expect(member?.group).toEqual(offersGroup.publicKey);
   });
-  // TODO(techiejd): https://github.com/kevinheavey/solana-bankrun/issues/28
-  /*
-  it("Allows the admin to add an offer until the limit is reached", async () => {
-    const orderMints = [];
-    await Promise.all(
-      [...Array(TOTAL_OFFERS)].map(async (_, i) => {
-        const orderMint = web3.Keypair.generate();
-        orderMints.push(orderMint.publicKey);
-        const orderMintMetadata: MintMetadata = {
-          name: `Offer Mint ${i}`,
-          symbol: `OFFER${i}`,
-          uri: `https://example.com/offer/${i}`,
-          locationVarietyPrice: [`location${i}`, `variety${i}`, `price${i}`],
-        };
-        const accounts = {
+  it("Makes each offer a member of the contract's offers group", async () => {
+    const orderMints = [web3.Keypair.generate(), web3.Keypair.generate()];
+    for (const orderMint of orderMints) {
+      await program.methods
+        .addOffer(
+          {
+            name: `Offer Mint`,
+            symbol: `OFFER`,
+            uri: `https://example.com/offer/`,
+            locationVarietyPrice: [`location`, `variety`, `price`],
+            managerForLot: null,
+          },
+          null
+        )
+        .accounts({
           admin: admin.publicKey,
           contract: contractPK,
-          offersAccount: offersAccount.publicKey,
+          offersGroup: offersGroup.publicKey,
           orderMint: orderMint.publicKey,
+          offerConfig: findOfferConfigPDA(contractPK, orderMint.publicKey),
           systemProgram: web3.SystemProgram.programId,
           rent: web3.SYSVAR_RENT_PUBKEY,
           tokenProgram: TOKEN_2022_PROGRAM_ID,
-        };
-        await program.methods
-          .addOffer(orderMintMetadata)
-          .accounts(accounts)
-          .signers([admin, orderMint])
-          .rpc();
-        const metadata = await getTokenMetadata(
-          program.provider.connection,
-          orderMint.publicKey,
-          undefined,
-          TOKEN_2022_PROGRAM_ID
-        );
-        expect(metadata.name).toEqual(orderMintMetadata.name);
-        expect(metadata.symbol).toEqual(orderMintMetadata.symbol);
-        expect(metadata.uri).toEqual(orderMintMetadata.uri);
-        expect(metadata.additionalMetadata).toEqual([
-          ["location", `location${i}`],
-          ["variety", `variety${i}`],
-        ]);
-        return "done";
-      })
+        })
+        .signers([admin, orderMint])
+        .rpc();
+    }
+    const members = await Promise.all(
+      orderMints.map(async (orderMint) =>
+        getTokenGroupMemberState(
+          await getMint(
+            program.provider.connection,
+            orderMint.publicKey,
+            undefined,
+            TOKEN_2022_PROGRAM_ID
+          )
+        )
+      )
     );
-    const offers = await program.account.offers.fetch(offersAccount.publicKey);
-    orderMints.map((o) =>
-      expect(
-        offers.offers.find((offer) => offer.mint.equals(o))
-      ).not.toBeUndefined()
+    members.forEach((member, i) => {
+      expect(member?.mint).toEqual(orderMints[i].publicKey);
+      expect(member?.group).toEqual(offersGroup.publicKey);
+    });
+    // The first test added the first member.
+    expect(members.map((m) => m?.memberNumber)).toEqual([BigInt(2), BigInt(3)]);
+    const group = getTokenGroupState(
+      await getMint(
+        program.provider.connection,
+        offersGroup.publicKey,
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      )
     );
-    expect(offers.tail.eqn(TOTAL_OFFERS)).toBe(true);
-
-    const overFlowOffer = web3.Keypair.generate();
-    const accounts = {
-      admin: admin.publicKey,
-      contract: contractPK,
-      offersAccount: offersAccount.publicKey,
-      orderMint: overFlowOffer.publicKey,
-      systemProgram: web3.SystemProgram.programId,
-      rent: web3.SYSVAR_RENT_PUBKEY,
-      tokenProgram: TOKEN_2022_PROGRAM_ID,
-    };
-    await expect(
-      program.methods
-        .addOffer({
-          name: `Offer Mint ${TOTAL_OFFERS}`,
-          symbol: `OFFER${TOTAL_OFFERS}`,
-          uri: `https://example.com/offer/${TOTAL_OFFERS}`,
-          locationVariety: [
-            `location${TOTAL_OFFERS}`,
-            `variety${TOTAL_OFFERS}`,
-            `price${TOTAL_OFFERS}`,
-          ],
-        })
-        .accounts(accounts)
-        .signers([admin, overFlowOffer])
-        .rpc()
-    ).rejects.toThrow("OffersFull");
-  }, 15000); */
+    expect(group?.size).toEqual(BigInt(3));
+  });
 });
@@ -1,11 +1,30 @@
 import * as anchor from "@coral-xyz/anchor";
 import {
   airdrop,
+  authorizeManager,
+  findAuthorizationPDA,
+  findLotPDA,
+  findManagerPDA,
+  findOfferConfigPDA,
+  findOrderBalancePDA,
+  findReceiptPDA,
   initialize,
   LOT_PRICE_IN_USDC,
   MintMetadata,
   PRICE_PER_TREE,
   program,
+  findCertificationPDA,
+  EVIDENCE_HASH,
+  CERTIFICATION_VALID_FOR,
+  findBondPDA,
+  postBond,
+  findLotEscrowPDA,
+  findHarvestVaultPDA,
+  findHoldingPDA,
+  findHarvestRecordPDA,
+  findHarvestExpensePDA,
+  addBuyer,
+  findBuyerPDA,
 } from "../client/utils";
 import {
   Account,
@@ -23,14 +42,14 @@ import { SendTransactionError } from "@solana/web3.js";
 describe("payingHarvest", () => {
   let admin: anchor.web3.Keypair;
   let contractPK: anchor.web3.PublicKey;
-  let offersAccount: anchor.web3.Keypair;
+  let offersGroup: anchor.web3.Keypair;
+  let lotsGroup: anchor.web3.Keypair;
   let usdc: { mint: anchor.web3.PublicKey; authority: anchor.web3.Keypair };
   let contractUsdcTokenAccount: anchor.web3.PublicKey;
   let user: anchor.web3.Keypair;
   let orderMint: anchor.web3.Keypair;
   let userAta: Account;
   let userOrderTokenAccount: anchor.web3.PublicKey;
-  let lotsAccount: anchor.web3.Keypair;
   let manager: anchor.web3.Keypair;
   let lotMint: anchor.web3.Keypair;
   let userLotTokenAccount: anchor.web3.PublicKey;
@@ -45,10 +64,10 @@ describe("payingHarvest", () => {
     ({
       admin,
       contractPK,
-      offersAccount,
+      offersGroup,
+      lotsGroup,
       usdc,
       contractUsdcTokenAccount,
-      lotsAccount,
       certificationMint,
     } = await initialize());
     user = anchor.web3.Keypair.generate();
@@ -83,14 +102,15 @@ describe("payingHarvest", () => {
     const addOfferAccounts = {
       admin: admin.publicKey,
       contract: contractPK,
-      offersAccount: offersAccount.publicKey,
+      offersGroup: offersGroup.publicKey,
       orderMint: orderMint.publicKey,
+      offerConfig: findOfferConfigPDA(contractPK, orderMint.publicKey),
       systemProgram: anchor.web3.SystemProgram.programId,
       rent: anchor.web3.SYSVAR_RENT_PUBKEY,
       tokenProgram: TOKEN_2022_PROGRAM_ID,
     };
     await program.methods
-      .addOffer(orderMintMetadata)
+      .addOffer(orderMintMetadata, null)
       .accounts(addOfferAccounts)
       .signers([admin, orderMint])
       .rpc();
@@ -103,10 +123,22 @@ describe("payingHarvest", () => {
     );
     const placeOrderAccounts = {
       user: user.publicKey,
+      signer: user.publicKey,
+      sessionToken: null,
       contract: contractPK,
-      offersAccount: offersAccount.publicKey,
       offerMint: orderMint.publicKey,
       userTokenAccount: userOrderTokenAccount,
+      orderBalance: findOrderBalancePDA(
+        contractPK,
+        orderMint.publicKey,
+        user.publicKey
+      ),
+      receipt: findReceiptPDA(
+        contractPK,
+        orderMint.publicKey,
+        user.publicKey,
+        0
+      ),
       associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
       tokenProgram: TOKEN_2022_PROGRAM_ID,
       systemProgram: anchor.web3.SystemProgram.programId,
@@ -118,7 +150,11 @@ describe("payingHarvest", () => {
     };
 
     await program.methods
-      .placeOrder(new anchor.BN(0), new anchor.BN(numOrders))
+      .placeOrder(
+        new anchor.BN(numOrders),
+        new anchor.BN(numOrders * LOT_PRICE_IN_USDC),
+        null
+      )
       .accounts(placeOrderAccounts)
       .signers([user])
       .rpc();
@@ -153,25 +189,59 @@ describe("payingHarvest", () => {
       manager: manager.publicKey,
       contract: contractPK,
       certificationMint: certificationMint.publicKey,
+      certification: findCertificationPDA(contractPK, manager.publicKey),
       tokenProgram: TOKEN_2022_PROGRAM_ID,
       associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
       systemProgram: anchor.web3.SystemProgram.programId,
     };
     await program.methods
-      .certify({ tier1: {} })
+      .certify({ tier1: {} }, EVIDENCE_HASH, CERTIFICATION_VALID_FOR)
       .accounts(certifyAccounts)
       .signers([admin])
       .rpc();
 
+    await postBond({ contractPK, manager, usdc });
+    await authorizeManager({
+      contractPK,
+      user,
+      manager: manager.publicKey,
+      orderMint: orderMint.publicKey,
+      lots: numLotsPrepared,
+    });
+
     const prepareLotsAccounts = {
       user: user.publicKey,
       manager: manager.publicKey,
+      signer: manager.publicKey,
+      sessionToken: null,
       contract: contractPK,
-      offersAccount: offersAccount.publicKey,
-      lotsAccount: lotsAccount.publicKey,
       orderMint: orderMint.publicKey,
       userOrderTokenAccount: userOrderTokenAccount,
+      orderBalance: findOrderBalancePDA(
+        contractPK,
+        orderMint.publicKey,
+        user.publicKey
+      ),
+      receipt: findReceiptPDA(
+        contractPK,
+        orderMint.publicKey,
+        user.publicKey,
+        0
+      ),
       lotMint: lotMint.publicKey,
+      lotsGroup: lotsGroup.publicKey,
+      lot: findLotPDA(contractPK, lotMint.publicKey),
+      lotEscrow: findLotEscrowPDA(contractPK, lotMint.publicKey),
+      certification: findCertificationPDA(contractPK, manager.publicKey),
+      managerRecord: findManagerPDA(contractPK, manager.publicKey),
+      bond: findBondPDA(contractPK, manager.publicKey),
+      offerConfig: findOfferConfigPDA(contractPK, orderMint.publicKey),
+      authorization: findAuthorizationPDA(
+        contractPK,
+        orderMint.publicKey,
+        user.publicKey,
+        manager.publicKey
+      ),
       userLotTokenAccount,
       usdcMint: usdc.mint,
       contractUsdcTokenAccount: contractUsdcTokenAccount,
@@ -184,7 +254,6 @@ describe("payingHarvest", () => {
 
     await program.methods
       .prepareLots(
-        new anchor.BN(0),
         new anchor.BN(numLotsPrepared),
         manager.publicKey.toBase58()
       )
@@ -214,22 +283,40 @@ describe("payingHarvest", () => {
       contract: contractPK,
       manager: manager.publicKey,
       certificationMint: certificationMint.publicKey,
+      certification: findCertificationPDA(contractPK, manager.publicKey),
       usdcMint: usdc.mint,
       tokenProgramStandard: TOKEN_PROGRAM_ID,
       associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
-      lotsAccount: lotsAccount.publicKey,
       lotMint: lotMint.publicKey,
+      lot: findLotPDA(contractPK, lotMint.publicKey),
+      lotEscrow: findLotEscrowPDA(contractPK, lotMint.publicKey),
+      dispute: null,
+      managerRecord: findManagerPDA(contractPK, manager.publicKey),
+      bond: findBondPDA(contractPK, manager.publicKey),
       tokenProgram: TOKEN_2022_PROGRAM_ID,
       user: user.publicKey,
       systemProgram: anchor.web3.SystemProgram.programId,
-      offersAccount: offersAccount.publicKey,
       orderMint: orderMint.publicKey,
     };
     await program.methods
-      .confirmLots(true, new anchor.BN(0), new anchor.BN(0))
+      .confirmLots(true, null)
       .accounts(confirmAccounts)
       .signers([admin])
       .rpc();
+
+    await program.methods
+      .markReady()
+      .accounts({
+        manager: manager.publicKey,
+        signer: manager.publicKey,
+        sessionToken: null,
+        contract: contractPK,
+        lotMint: lotMint.publicKey,
+        lot: findLotPDA(contractPK, lotMint.publicKey),
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([manager])
+      .rpc();
   }, 15000);
 
   it("Should pay the manager for the harvest and split the profit 25/25/50 between manager, admin, and user", async () => {
@@ -239,6 +326,7 @@ describe("payingHarvest", () => {
     const profitInUsdc = 20000 * 10 ** 4; // 200 dollars
     const payer = anchor.web3.Keypair.generate();
     await airdrop(payer.publicKey);
+    await addBuyer({ contractPK, admin, buyer: payer.publicKey });
 
     const [
       userUsdcTokenAccountBefore,
@@ -304,25 +392,123 @@ describe("payingHarvest", () => {
     const harvestAccounts = {
       contract: contractPK,
       payer: payer.publicKey,
-      user: user.publicKey,
-      manager: manager.publicKey,
-      admin: admin.publicKey,
-      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      buyerRecord: findBuyerPDA(contractPK, payer.publicKey),
       usdcMint: usdc.mint,
       tokenProgramStandard: TOKEN_PROGRAM_ID,
-      lotsAccount: lotsAccount.publicKey,
       lotMint: lotMint.publicKey,
+      lot: findLotPDA(contractPK, lotMint.publicKey),
+      harvestVault: findHarvestVaultPDA(contractPK, lotMint.publicKey),
+      harvestRecord: findHarvestRecordPDA(contractPK, lotMint.publicKey, 0),
       tokenProgram: TOKEN_2022_PROGRAM_ID,
       systemProgram: anchor.web3.SystemProgram.programId,
     };
+    const holdingAccounts = {
+      holder: user.publicKey,
+      contract: contractPK,
+      lotMint: lotMint.publicKey,
+      lot: findLotPDA(contractPK, lotMint.publicKey),
+      holding: findHoldingPDA(contractPK, lotMint.publicKey, user.publicKey),
+      holderLotTokenAccount: userLotTokenAccount,
+      tokenProgram: TOKEN_2022_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    };
+    // The user's share of the profit goes to the lot's holders
     await program.methods
-      .payHarvest(
-        new anchor.BN(0) /** lotIndex */,
-        new anchor.BN(costOfHarvest) /** harvestAmount */,
-        new anchor.BN(profit) /** profit */
-      )
-      .accounts(harvestAccounts)
-      .signers([payer])
+      .openHolding()
+      .accounts(holdingAccounts)
+      .signers([user])
+      .rpc();
+    const expenseAccounts = (index: number) => ({
+      manager: manager.publicKey,
+      contract: contractPK,
+      lotMint: lotMint.publicKey,
+      lot: harvestAccounts.lot,
+      expense: findHarvestExpensePDA(contractPK, lotMint.publicKey, 0, index),
+      tokenProgram: TOKEN_2022_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    });
+    const reportExpense = (index: number, amount: number) =>
+      program.methods
+        .reportHarvestExpense(new anchor.BN(amount), EVIDENCE_HASH)
+        .accounts(expenseAccounts(index))
+        .signers([manager])
+        .rpc();
+    const depositHarvest = () =>
+      program.methods
+        .depositHarvest(
+          2026 /** season */,
+          new anchor.BN(costOfHarvest + profit) /** grossProceeds */
+        )
+        .accounts(harvestAccounts)
+        .signers([payer])
+        .rpc();
+    await reportExpense(0, costOfHarvest);
+    // Expenses can't take more than half of the proceeds by default
+    await reportExpense(1, 5001);
+    await expect(depositHarvest()).rejects.toThrow(
+      "Error Code: ExpensesOverCap"
+    );
+    await program.methods
+      .retractHarvestExpense()
+      .accounts(expenseAccounts(1))
+      .signers([manager])
+      .rpc();
+    await depositHarvest();
+    const lotAccount = await program.account.lot.fetch(harvestAccounts.lot);
+    expect(lotAccount.holdersUnclaimed.toNumber()).toEqual(profitInUsdc / 2);
+    expect(lotAccount.adminUnclaimed.toNumber()).toEqual(profitInUsdc / 4);
+    expect(lotAccount.managerUnclaimed.toNumber()).toEqual(
+      profitInUsdc / 4 + costOfHarvestInUsdc
+    );
+    const harvestRecord = await program.account.harvestRecord.fetch(
+      harvestAccounts.harvestRecord
+    );
+    expect(harvestRecord.season).toEqual(2026);
+    expect(harvestRecord.grossRevenue.toNumber()).toEqual(
+      costOfHarvestInUsdc + profitInUsdc
+    );
+    expect(harvestRecord.holdersAmount.toNumber()).toEqual(profitInUsdc / 2);
+    expect(harvestRecord.managerCost.toNumber()).toEqual(costOfHarvestInUsdc);
+    expect(harvestRecord.depositor.toBase58()).toEqual(
+      payer.publicKey.toBase58()
+    );
+    expect(lotAccount.harvestTotals.harvests).toEqual(1);
+    expect(lotAccount.harvestTotals.profit.toNumber()).toEqual(profitInUsdc);
+    expect(lotAccount.pendingExpenses.toNumber()).toEqual(0);
+
+    const claimHarvest = (beneficiary: anchor.web3.Keypair) =>
+      program.methods
+        .claimHarvest()
+        .accounts({
+          beneficiary: beneficiary.publicKey,
+          contract: contractPK,
+          lotMint: lotMint.publicKey,
+          lot: harvestAccounts.lot,
+          usdcMint: usdc.mint,
+          harvestVault: harvestAccounts.harvestVault,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          tokenProgramStandard: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([beneficiary])
+        .rpc();
+    await expect(claimHarvest(payer)).rejects.toThrow(
+      "Error Code: ManagerMismatch"
+    );
+    await claimHarvest(admin);
+    await claimHarvest(manager);
+    await program.methods
+      .claimHarvestShare()
+      .accounts({
+        ...holdingAccounts,
+        usdcMint: usdc.mint,
+        harvestVault: findHarvestVaultPDA(contractPK, lotMint.publicKey),
+        holderUsdcTokenAccount: userUsdcTokenAccountBefore.address,
+        tokenProgramStandard: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      })
+      .signers([user])
       .rpc();
 
     const [
@@ -1,6 +1,9 @@
 import * as anchor from "@coral-xyz/anchor";
 import {
   airdrop,
+  findOfferConfigPDA,
+  findOrderBalancePDA,
+  findReceiptPDA,
   initialize,
   TREES_PER_LOT,
   MintMetadata,
@@ -21,14 +24,14 @@ import {
 describe("Offers", () => {
   let admin: anchor.web3.Keypair;
   let contractPK: anchor.web3.PublicKey;
-  let offersAccount: anchor.web3.Keypair;
+  let offersGroup: anchor.web3.Keypair;
   let usdc: { mint: anchor.web3.PublicKey; authority: anchor.web3.Keypair };
   let contractUsdcTokenAccount: anchor.web3.PublicKey;
   let user: anchor.web3.Keypair;
   let orderMint: anchor.web3.Keypair;
   let userAta: Account;
   beforeAll(async () => {
-    ({ admin, contractPK, offersAccount, usdc, contractUsdcTokenAccount } =
+    ({ admin, contractPK, offersGroup, usdc, contractUsdcTokenAccount } =
       await initialize());
     user = anchor.web3.Keypair.generate();
     await airdrop(user.publicKey);
@@ -63,14 +66,15 @@ describe("Offers", () => {
     const accounts = {
       admin: admin.publicKey,
       contract: contractPK,
-      offersAccount: offersAccount.publicKey,
+      offersGroup: offersGroup.publicKey,
       orderMint: orderMint.publicKey,
+      offerConfig: findOfferConfigPDA(contractPK, orderMint.publicKey),
       systemProgram: anchor.web3.SystemProgram.programId,
       rent: anchor.web3.SYSVAR_RENT_PUBKEY,
       tokenProgram: TOKEN_2022_PROGRAM_ID,
     };
     await program.methods
-      .addOffer(orderMintMetadata)
+      .addOffer(orderMintMetadata, null)
       .accounts(accounts)
       .signers([admin, orderMint])
       .rpc();
@@ -86,10 +90,22 @@ describe("Offers", () => {
     );
     const accounts = {
       user: user.publicKey,
+      signer: user.publicKey,
+      sessionToken: null,
       contract: contractPK,
-      offersAccount: offersAccount.publicKey,
       offerMint: orderMint.publicKey,
       userTokenAccount: userTokenAccount,
+      orderBalance: findOrderBalancePDA(
+        contractPK,
+        orderMint.publicKey,
+        user.publicKey
+      ),
+      receipt: findReceiptPDA(
+        contractPK,
+        orderMint.publicKey,
+        user.publicKey,
+        0
+      ),
       associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
       tokenProgram: TOKEN_2022_PROGRAM_ID,
       systemProgram: anchor.web3.SystemProgram.programId,
@@ -108,7 +124,11 @@ describe("Offers", () => {
     );
 
     await program.methods
-      .placeOrder(new anchor.BN(0), new anchor.BN(numOrders))
+      .placeOrder(
+        new anchor.BN(numOrders),
+        new anchor.BN(numOrders * LOT_PRICE_IN_USDC),
+        null
+      )
       .accounts(accounts)
       .signers([user])
       .rpc();
@@ -137,5 +157,81 @@ describe("Offers", () => {
     expect(userUsdcAccountAfter.amount).toBe(
       userUsdcAccountBefore.amount - BigInt(numOrders * LOT_PRICE_IN_USDC)
     );
+
+    const receipt = await program.account.orderReceipt.fetch(accounts.receipt);
+    expect(receipt.buyer).toEqual(user.publicKey);
+    expect(receipt.orderMint).toEqual(orderMint.publicKey);
+    expect(receipt.orderId.toNumber()).toEqual(0);
+    expect(receipt.quantity.toNumber()).toEqual(numOrders);
+    expect(receipt.quantityRemaining.toNumber()).toEqual(numOrders);
+    expect(receipt.pricePerTree.toNumber()).toEqual(Number(PRICE_PER_TREE));
+    expect(receipt.treesPerLot.eq(TREES_PER_LOT)).toBe(true);
+    expect(receipt.placedAt.toNumber()).toBeGreaterThan(0);
+    const orderBalance = await program.account.orderBalance.fetch(
+      accounts.orderBalance
+    );
+    expect(orderBalance.ordersPlaced.toNumber()).toEqual(1);
+  });
+  describe("price protection", () => {
+    // The user already placed one order above, so this would be their second
+    const placeSecondOrder = (
+      maxTotalPrice: number,
+      expiresAt: anchor.BN | null
+    ) =>
+      program.methods
+        .placeOrder(new anchor.BN(1), new anchor.BN(maxTotalPrice), expiresAt)
+        .accounts({
+          user: user.publicKey,
+          signer: user.publicKey,
+          sessionToken: null,
+          contract: contractPK,
+          offerMint: orderMint.publicKey,
+          userTokenAccount: getAssociatedTokenAddressSync(
+            orderMint.publicKey,
+            user.publicKey,
+            false,
+            TOKEN_2022_PROGRAM_ID,
+            ASSOCIATED_TOKEN_PROGRAM_ID
+          ),
+          orderBalance: findOrderBalancePDA(
+            contractPK,
+            orderMint.publicKey,
+            user.publicKey
+          ),
+          receipt: findReceiptPDA(
+            contractPK,
+            orderMint.publicKey,
+            user.publicKey,
+            1
+          ),
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          usdcMint: usdc.mint,
+          usdcFrom: userAta.address,
+          contractUsdcTokenAccount,
+          tokenProgramStandard: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+    const now = () => new anchor.BN(Math.floor(Date.now() / 1000));
+
+    it("rejects orders that cost more than the user allowed", async () => {
+      await expect(
+        placeSecondOrder(LOT_PRICE_IN_USDC - 1, null)
+      ).rejects.toThrow("Error Code: PriceAboveMaximum");
+    });
+    it("rejects orders past their expiry", async () => {
+      await expect(
+        placeSecondOrder(LOT_PRICE_IN_USDC, now().subn(60))
+      ).rejects.toThrow("Error Code: OrderExpired");
+    });
+    it("places orders within the price and before the expiry", async () => {
+      await placeSecondOrder(LOT_PRICE_IN_USDC, now().addn(60));
+      const orderBalance = await program.account.orderBalance.fetch(
+        findOrderBalancePDA(contractPK, orderMint.publicKey, user.publicKey)
+      );
+      expect(orderBalance.quantity.toNumber()).toEqual(6);
+    });
   });
 });
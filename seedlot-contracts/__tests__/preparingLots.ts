@@ -1,20 +1,35 @@
 import * as anchor from "@coral-xyz/anchor";
 import {
   airdrop,
+  authorizeManager,
+  findAuthorizationPDA,
+  findLotPDA,
+  findManagerPDA,
+  findOfferConfigPDA,
+  findOrderBalancePDA,
+  findReceiptPDA,
+  TREES_PER_LOT,
   initialize,
   LOT_PRICE_IN_USDC,
   MintMetadata,
   PRICE_PER_TREE,
   program,
+  findCertificationPDA,
+  EVIDENCE_HASH,
+  CERTIFICATION_VALID_FOR,
+  findLotEscrowPDA,
+  UNITS_PER_LOT,
 } from "../client/utils";
 import {
   Account,
   ASSOCIATED_TOKEN_PROGRAM_ID,
   ExtensionType,
+  getAccount,
   getAssociatedTokenAddressSync,
   getExtensionTypes,
   getMint,
   getOrCreateAssociatedTokenAccount,
+  getTokenGroupMemberState,
   getTokenMetadata,
   mintTo,
   TOKEN_2022_PROGRAM_ID,
@@ -27,23 +42,23 @@ describe("preparingLots", () => {
   let admin: anchor.web3.Keypair;
   let contractPK: anchor.web3.PublicKey;
   let certificationMint: anchor.web3.Keypair;
-  let offersAccount: anchor.web3.Keypair;
+  let offersGroup: anchor.web3.Keypair;
+  let lotsGroup: anchor.web3.Keypair;
   let usdc: { mint: anchor.web3.PublicKey; authority: anchor.web3.Keypair };
   let contractUsdcTokenAccount: anchor.web3.PublicKey;
   let user: anchor.web3.Keypair;
   let orderMint: anchor.web3.Keypair;
   let userAta: Account;
   let userOrderTokenAccount: anchor.web3.PublicKey;
-  let lotsAccount: anchor.web3.Keypair;
   const numOrders = 5;
   beforeAll(async () => {
     ({
       admin,
       contractPK,
-      offersAccount,
+      offersGroup,
+      lotsGroup,
       usdc,
       contractUsdcTokenAccount,
-      lotsAccount,
       certificationMint,
     } = await initialize());
     user = anchor.web3.Keypair.generate();
@@ -79,14 +94,15 @@ describe("preparingLots", () => {
     const addOfferAccounts = {
       admin: admin.publicKey,
       contract: contractPK,
-      offersAccount: offersAccount.publicKey,
+      offersGroup: offersGroup.publicKey,
       orderMint: orderMint.publicKey,
+      offerConfig: findOfferConfigPDA(contractPK, orderMint.publicKey),
       systemProgram: anchor.web3.SystemProgram.programId,
       rent: anchor.web3.SYSVAR_RENT_PUBKEY,
       tokenProgram: TOKEN_2022_PROGRAM_ID,
     };
     await program.methods
-      .addOffer(orderMintMetadata)
+      .addOffer(orderMintMetadata, null)
       .accounts(addOfferAccounts)
       .signers([admin, orderMint])
       .rpc();
@@ -99,10 +115,22 @@ describe("preparingLots", () => {
     );
     const placeOrderAccounts = {
       user: user.publicKey,
+      signer: user.publicKey,
+      sessionToken: null,
       contract: contractPK,
-      offersAccount: offersAccount.publicKey,
       offerMint: orderMint.publicKey,
       userTokenAccount: userOrderTokenAccount,
+      orderBalance: findOrderBalancePDA(
+        contractPK,
+        orderMint.publicKey,
+        user.publicKey
+      ),
+      receipt: findReceiptPDA(
+        contractPK,
+        orderMint.publicKey,
+        user.publicKey,
+        0
+      ),
       associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
       tokenProgram: TOKEN_2022_PROGRAM_ID,
       systemProgram: anchor.web3.SystemProgram.programId,
@@ -113,7 +141,11 @@ describe("preparingLots", () => {
     };
 
     await program.methods
-      .placeOrder(new anchor.BN(0), new anchor.BN(numOrders))
+      .placeOrder(
+        new anchor.BN(numOrders),
+        new anchor.BN(numOrders * LOT_PRICE_IN_USDC),
+        null
+      )
       .accounts(placeOrderAccounts)
       .signers([user])
       .rpc();
@@ -131,12 +163,13 @@ describe("preparingLots", () => {
       manager: manager.publicKey,
       contract: contractPK,
       certificationMint: certificationMint.publicKey,
+      certification: findCertificationPDA(contractPK, manager.publicKey),
       tokenProgram: TOKEN_2022_PROGRAM_ID,
       associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
       systemProgram: anchor.web3.SystemProgram.programId,
     };
     await program.methods
-      .certify({ tier1: {} })
+      .certify({ tier1: {} }, EVIDENCE_HASH, CERTIFICATION_VALID_FOR)
       .accounts(certifyAccounts)
       .signers([admin])
       .rpc();
@@ -166,18 +199,50 @@ describe("preparingLots", () => {
       ASSOCIATED_TOKEN_PROGRAM_ID
     );
 
+    // Managers don't need a bond while the contract doesn't ask for one
+    await authorizeManager({
+      contractPK,
+      user,
+      manager: manager.publicKey,
+      orderMint: orderMint.publicKey,
+      lots: numLotsToPrepare,
+    });
+
     const prepareLotsAccounts = {
       user: user.publicKey,
       manager: manager.publicKey,
+      signer: manager.publicKey,
+      sessionToken: null,
       contract: contractPK,
-      offersAccount: offersAccount.publicKey,
-      lotsAccount: lotsAccount.publicKey,
       orderMint: orderMint.publicKey,
       userOrderTokenAccount: userOrderTokenAccount,
+      orderBalance: findOrderBalancePDA(
+        contractPK,
+        orderMint.publicKey,
+        user.publicKey
+      ),
+      receipt: findReceiptPDA(
+        contractPK,
+        orderMint.publicKey,
+        user.publicKey,
+        0
+      ),
       lotMint: lotMint.publicKey,
+      lotsGroup: lotsGroup.publicKey,
+      lot: findLotPDA(contractPK, lotMint.publicKey),
+      lotEscrow: findLotEscrowPDA(contractPK, lotMint.publicKey),
+      managerRecord: findManagerPDA(contractPK, manager.publicKey),
+      bond: null,
+      offerConfig: findOfferConfigPDA(contractPK, orderMint.publicKey),
+      authorization: findAuthorizationPDA(
+        contractPK,
+        orderMint.publicKey,
+        user.publicKey,
+        manager.publicKey
+      ),
       userLotTokenAccount,
       usdcMint: usdc.mint,
-      certificationMint: certificationMint.publicKey,
+      certification: findCertificationPDA(contractPK, manager.publicKey),
       associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
       tokenProgram: TOKEN_2022_PROGRAM_ID,
       tokenProgramStandard: TOKEN_PROGRAM_ID,
@@ -187,7 +252,6 @@ describe("preparingLots", () => {
 
     await program.methods
       .prepareLots(
-        new anchor.BN(0),
         new anchor.BN(numLotsToPrepare),
         manager.publicKey.toBase58()
       )
@@ -228,10 +292,12 @@ describe("preparingLots", () => {
       TOKEN_2022_PROGRAM_ID,
       ASSOCIATED_TOKEN_PROGRAM_ID
     );
-    expect(lotMintTokenAccount.amount).toEqual(BigInt(numLotsToPrepare));
+    expect(lotMintTokenAccount.amount).toEqual(
+      BigInt(numLotsToPrepare * UNITS_PER_LOT)
+    );
     expect(lotMintTokenAccount.isFrozen).toBe(true);
 
-    // We want to check that the state of the additonal mint is correct. Name, symbol, uri, and additonal metadata is (location, variety, manager pub key, "0")
+    // We want to check that the state of the additonal mint is correct. Name, symbol, uri, and additonal metadata is (location, variety, manager pub key)
     const lotMintInfo = await getMint(
       program.provider.connection,
       lotMint.publicKey,
@@ -241,11 +307,16 @@ describe("preparingLots", () => {
 
     expect(lotMintInfo.freezeAuthority).toEqual(contractPK);
     expect(lotMintInfo.mintAuthority).toEqual(contractPK);
-    expect(lotMintInfo.supply).toEqual(BigInt(numLotsToPrepare));
+    expect(lotMintInfo.supply).toEqual(
+      BigInt(numLotsToPrepare * UNITS_PER_LOT)
+    );
     expect(lotMintInfo.isInitialized).toBe(true);
-    expect(lotMintInfo.decimals).toEqual(0);
+    expect(lotMintInfo.decimals).toEqual(6);
     const extensionTypes = getExtensionTypes(lotMintInfo.tlvData);
     expect(extensionTypes).toContain(ExtensionType.DefaultAccountState);
+    expect(getTokenGroupMemberState(lotMintInfo)?.group).toEqual(
+      lotsGroup.publicKey
+    );
 
     const lotMintMetadata = await getTokenMetadata(
       program.provider.connection,
@@ -264,16 +335,26 @@ describe("preparingLots", () => {
       ["location", "location"],
       ["variety", "variety"],
       ["manager", manager.publicKey.toBase58()],
-      ["state", "0"],
     ]);
 
-    const lots = await program.account.lots.fetch(lotsAccount.publicKey);
-    expect(lots.lots[0].originalPricePerTree.toString()).toEqual(
-      PRICE_PER_TREE
+    const lot = await program.account.lot.fetch(
+      findLotPDA(contractPK, lotMint.publicKey)
     );
-    expect(lots.lots[0].mint).toEqual(lotMint.publicKey);
+    expect(lot.originalPricePerTree.toString()).toEqual(PRICE_PER_TREE);
+    expect(lot.treesPerLot.eq(TREES_PER_LOT)).toBe(true);
+    expect(lot.mint).toEqual(lotMint.publicKey);
+    expect(lot.state).toEqual({ preparation: {} });
 
-    // We want to check that the manager has received his payment for preparing the lot, which is 10% of LOT_PRICE_IN_USDC *  numOrders
+    // The manager's upfront fee waits in the lot's escrow until confirmation
+    const lotEscrow = await getAccount(
+      program.provider.connection,
+      findLotEscrowPDA(contractPK, lotMint.publicKey),
+      undefined,
+      TOKEN_PROGRAM_ID
+    );
+    expect(lotEscrow.amount).toEqual(
+      BigInt(LOT_PRICE_IN_USDC * numLotsToPrepare * 0.1)
+    );
     const managerUsdcTokenAccount = await getOrCreateAssociatedTokenAccount(
       program.provider.connection,
       manager,
@@ -285,8 +366,6 @@ describe("preparingLots", () => {
       TOKEN_PROGRAM_ID,
       ASSOCIATED_TOKEN_PROGRAM_ID
     );
-    expect(managerUsdcTokenAccount.amount).toEqual(
-      BigInt(LOT_PRICE_IN_USDC * numLotsToPrepare * 0.1)
-    );
+    expect(managerUsdcTokenAccount.amount).toEqual(0n);
   });
 });
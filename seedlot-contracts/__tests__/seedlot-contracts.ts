@@ -10,6 +10,7 @@ import {
   ASSOCIATED_TOKEN_PROGRAM_ID,
   getAccount,
   getAssociatedTokenAddressSync,
+  getTokenGroupState,
 } from "@solana/spl-token";
 import {
   Contract,
@@ -18,21 +19,27 @@ import {
   TREES_PER_LOT,
   confirmTx,
   CERTIFICATION_MINT_METADATA,
-  initializeZeroAccount,
-  TOTAL_OFFERS,
+  DEFAULT_FEE_CONFIG,
   initializeUSDC,
-  TOTAL_LOTS,
+  findBondPDA,
+  findLotPDA,
+  findManagerPDA,
+  migrateLegacyContract,
+  postBond,
+  PRICE_PER_TREE,
+  setupLegacyContract,
 } from "../client/utils";
 
 describe("initializing", () => {
   let admin: web3.Keypair;
+  let contractId: web3.PublicKey;
   let contractPK: web3.PublicKey;
   let contract: Contract;
   let certificationMint: web3.Keypair;
-  let offersAccount: web3.Keypair;
+  let offersGroup: web3.Keypair;
+  let lotsGroup: web3.Keypair;
   let usdcMint: web3.PublicKey;
   let contractUsdcTokenAccount: web3.PublicKey;
-  let lotsAccount: web3.Keypair;
   beforeAll(async () => {
     admin = web3.Keypair.generate();
     await airdrop(admin.publicKey);
@@ -40,16 +47,15 @@ describe("initializing", () => {
 
   describe("success", () => {
     beforeAll(async () => {
+      contractId = web3.Keypair.generate().publicKey;
       [contractPK] = web3.PublicKey.findProgramAddressSync(
-        [Buffer.from("contract"), admin.publicKey.toBuffer()],
+        [Buffer.from("contract"), contractId.toBuffer()],
         program.programId
       );
       certificationMint = web3.Keypair.generate();
-      [offersAccount, { mint: usdcMint }, lotsAccount] = await Promise.all([
-        initializeZeroAccount(admin, program.account.offers.size),
-        initializeUSDC(),
-        initializeZeroAccount(admin, program.account.lots.size),
-      ]);
+      offersGroup = web3.Keypair.generate();
+      lotsGroup = web3.Keypair.generate();
+      ({ mint: usdcMint } = await initializeUSDC());
       contractUsdcTokenAccount = getAssociatedTokenAddressSync(
         usdcMint,
         contractPK,
@@ -58,8 +64,8 @@ describe("initializing", () => {
       const accounts = {
         admin: admin.publicKey,
         contract: contractPK,
-        offersAccount: offersAccount.publicKey,
-        lotsAccount: lotsAccount.publicKey,
+        offersGroup: offersGroup.publicKey,
+        lotsGroup: lotsGroup.publicKey,
         systemProgram: web3.SystemProgram.programId,
         tokenProgram: TOKEN_2022_PROGRAM_ID,
         certificationMint: certificationMint.publicKey,
@@ -69,9 +75,14 @@ describe("initializing", () => {
         tokenProgramStandard: TOKEN_PROGRAM_ID,
       };
       const txHash = await program.methods
-        .initialize(TREES_PER_LOT, CERTIFICATION_MINT_METADATA)
+        .initialize(
+          contractId,
+          TREES_PER_LOT,
+          CERTIFICATION_MINT_METADATA,
+          DEFAULT_FEE_CONFIG
+        )
         .accounts(accounts)
-        .signers([admin, certificationMint])
+        .signers([admin, certificationMint, offersGroup, lotsGroup])
         .preInstructions([
           anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({
             units: 800_000,
@@ -85,6 +96,10 @@ describe("initializing", () => {
 
     it("Sets admin as the owner", async () => {
       expect(contract.admin).toStrictEqual(admin.publicKey);
+      expect(contract.pendingAdmin).toBeNull();
+    });
+    it("Seeds the contract by its id rather than its admin", () => {
+      expect(contract.contractId).toEqual(contractId);
     });
     it("Sets the number of minimum trees in a lot", async () => {
       expect(contract.treesPerLot.eq(TREES_PER_LOT)).toBe(true);
@@ -124,17 +139,26 @@ describe("initializing", () => {
         expect(metadata.additionalMetadata).toEqual([]);
       });
     });
-    it("Sets the offers account", async () => {
-      expect(contract.offersAccount).toEqual(offersAccount.publicKey);
-      const offers = await program.account.offers.fetch(
-        offersAccount.publicKey
-      );
-      expect(offers.owner).toEqual(contractPK);
-      expect(offers.tail.eqn(0)).toBe(true);
-      expect(offers.offers).toHaveLength(TOTAL_OFFERS);
-      expect(
-        offers.offers.every((o) => o.mint.equals(web3.SystemProgram.programId))
-      ).toBe(true);
+    describe("groups", () => {
+      it("Sets the offers and lots groups", () => {
+        expect(contract.offersGroup).toEqual(offersGroup.publicKey);
+        expect(contract.lotsGroup).toEqual(lotsGroup.publicKey);
+      });
+      it("Creates empty groups that the contract manages", async () => {
+        for (const groupPK of [contract.offersGroup, contract.lotsGroup]) {
+          const mintInfo = await getMint(
+            program.provider.connection,
+            groupPK,
+            undefined,
+            TOKEN_2022_PROGRAM_ID
+          );
+          expect(mintInfo.mintAuthority).toEqual(contractPK);
+          const group = getTokenGroupState(mintInfo);
+          expect(group?.updateAuthority).toEqual(contractPK);
+          expect(group?.mint).toEqual(groupPK);
+          expect(group?.size).toEqual(BigInt(0));
+        }
+      });
     });
     test.todo(
       "Sets the percentage that the managers will receive in taking on an order"
@@ -153,15 +177,136 @@ describe("initializing", () => {
     it("Sets the USDC mint", async () => {
       expect(contract.usdcMint).toEqual(usdcMint);
     });
-    it("Sets the Lots account", async () => {
-      expect(contract.lotsAccount).toEqual(lotsAccount.publicKey);
-      const lots = await program.account.lots.fetch(lotsAccount.publicKey);
-      expect(lots.owner).toEqual(contractPK);
-      expect(lots.tail.eqn(0)).toBe(true);
-      expect(lots.lots).toHaveLength(TOTAL_LOTS);
-      expect(
-        lots.lots.every((l) => l.mint.equals(web3.SystemProgram.programId))
-      ).toBe(true);
+    it("Does not set a legacy Lots account", async () => {
+      expect(contract.legacyLotsAccount).toEqual(web3.PublicKey.default);
+    });
+    it("Migrates lots out of a legacy Lots account", async () => {
+      const user = web3.Keypair.generate().publicKey;
+      const legacy = await setupLegacyContract({
+        name: "legacy-lots",
+        lotStates: ["1", "0"],
+        lotHolders: [[user, 2]],
+      });
+      const migrated = await migrateLegacyContract(legacy);
+      // The legacy lots were all prepared by the admin
+      const managerRecord = findManagerPDA(
+        migrated.contractPK,
+        legacy.admin.publicKey
+      );
+      const { legacyLotsAccount } = await program.account.contract.fetch(
+        migrated.contractPK
+      );
+      const lotPDAs = legacy.lotMints.map((lotMint) =>
+        findLotPDA(migrated.contractPK, lotMint.publicKey)
+      );
+      for (const [i, lotMint] of legacy.lotMints.entries()) {
+        await program.methods
+          .migrateLot()
+          .accounts({
+            admin: legacy.admin.publicKey,
+            contract: migrated.contractPK,
+            legacyLotsAccount,
+            lotMint: lotMint.publicKey,
+            lot: lotPDAs[i],
+            manager: legacy.admin.publicKey,
+            managerRecord,
+            tokenProgram: TOKEN_2022_PROGRAM_ID,
+            systemProgram: web3.SystemProgram.programId,
+          })
+          .signers([legacy.admin])
+          .rpc();
+      }
+
+      // The first lot was confirmed before the migration and the second wasn't
+      const [confirmed, preparing] = await Promise.all(
+        lotPDAs.map((lot) => program.account.lot.fetch(lot))
+      );
+      expect(confirmed.state).toEqual({ growing: {} });
+      expect(preparing.state).toEqual({ preparation: {} });
+      for (const lot of [confirmed, preparing]) {
+        expect(lot.originalPricePerTree.toString()).toEqual(PRICE_PER_TREE);
+        expect(lot.treesPerLot.eq(TREES_PER_LOT)).toBe(true);
+        expect(lot.feeEscrowed).toBe(false);
+      }
+      const legacyLots = await program.account.lots.fetch(legacyLotsAccount);
+      expect(legacyLots.tail.toNumber()).toEqual(0);
+
+      // The unconfirmed lot could still be denied and slash the manager's bond
+      const { activeLots } = await program.account.managerRecord.fetch(
+        managerRecord
+      );
+      expect(activeLots.toNumber()).toEqual(2);
+      await postBond({
+        contractPK: migrated.contractPK,
+        manager: legacy.admin,
+        usdc: legacy.usdc,
+      });
+      await expect(
+        program.methods
+          .withdrawBond(new anchor.BN(0))
+          .accounts({
+            manager: legacy.admin.publicKey,
+            contract: migrated.contractPK,
+            managerRecord,
+            usdcMint: legacy.usdc.mint,
+            bond: findBondPDA(migrated.contractPK, legacy.admin.publicKey),
+            managerUsdcTokenAccount: getAssociatedTokenAddressSync(
+              legacy.usdc.mint,
+              legacy.admin.publicKey
+            ),
+            tokenProgramStandard: TOKEN_PROGRAM_ID,
+          })
+          .signers([legacy.admin])
+          .rpc()
+      ).rejects.toThrow("Error Code: ManagerHasOpenLots");
+    }, 30000);
+  });
+
+  describe("failure", () => {
+    it("Rejects a number of trees that doesn't split a lot's units", async () => {
+      const contractId = web3.Keypair.generate().publicKey;
+      const [contractPK] = web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("contract"), contractId.toBuffer()],
+        program.programId
+      );
+      const certificationMint = web3.Keypair.generate();
+      const offersGroup = web3.Keypair.generate();
+      const lotsGroup = web3.Keypair.generate();
+      const { mint: usdcMint } = await initializeUSDC();
+      await expect(
+        program.methods
+          .initialize(
+            contractId,
+            // 1_000_000 units don't split into 3 trees
+            new anchor.BN(3),
+            CERTIFICATION_MINT_METADATA,
+            DEFAULT_FEE_CONFIG
+          )
+          .accounts({
+            admin: admin.publicKey,
+            contract: contractPK,
+            offersGroup: offersGroup.publicKey,
+            lotsGroup: lotsGroup.publicKey,
+            systemProgram: web3.SystemProgram.programId,
+            tokenProgram: TOKEN_2022_PROGRAM_ID,
+            certificationMint: certificationMint.publicKey,
+            usdcMint,
+            contractUsdcTokenAccount: getAssociatedTokenAddressSync(
+              usdcMint,
+              contractPK,
+              true
+            ),
+            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+            tokenProgramStandard: TOKEN_PROGRAM_ID,
+          })
+          .signers([admin, certificationMint, offersGroup, lotsGroup])
+          .preInstructions([
+            anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({
+              units: 800_000,
+            }),
+          ])
+          .rpc()
+      ).rejects.toThrow("Error Code: InvalidTreesPerLot");
     });
   });
 });
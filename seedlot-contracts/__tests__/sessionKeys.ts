@@ -0,0 +1,132 @@
+import * as anchor from "@coral-xyz/anchor";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  getAccount,
+  mintTo,
+  TOKEN_2022_PROGRAM_ID,
+  TOKEN_PROGRAM_ID,
+} from "@solana/spl-token";
+import {
+  createSession,
+  findOrderBalancePDA,
+  findReceiptPDA,
+  LOT_PRICE_IN_USDC,
+  PreparedLot,
+  prepareAnotherLot,
+  program,
+  Session,
+  setupPreparedLot,
+  UNITS_PER_LOT,
+} from "../client/utils";
+
+describe("session keys", () => {
+  let lot: PreparedLot;
+  let userSession: Session;
+  let unfundedSession: Session;
+  let managerSession: Session;
+  const placeOrder = (session: Session, orderId: number) =>
+    program.methods
+      .placeOrder(new anchor.BN(1), new anchor.BN(LOT_PRICE_IN_USDC), null)
+      .accounts({
+        user: lot.user.publicKey,
+        signer: session.signer.publicKey,
+        sessionToken: session.token,
+        contract: lot.contractPK,
+        offerMint: lot.orderMint.publicKey,
+        userTokenAccount: lot.userOrderTokenAccount,
+        orderBalance: findOrderBalancePDA(
+          lot.contractPK,
+          lot.orderMint.publicKey,
+          lot.user.publicKey
+        ),
+        receipt: findReceiptPDA(
+          lot.contractPK,
+          lot.orderMint.publicKey,
+          lot.user.publicKey,
+          orderId
+        ),
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        usdcMint: lot.usdc.mint,
+        usdcFrom: lot.userUsdcAccount,
+        contractUsdcTokenAccount: lot.contractUsdcTokenAccount,
+        tokenProgramStandard: TOKEN_PROGRAM_ID,
+      })
+      .signers([session.signer])
+      .rpc();
+  const orderTokens = async () =>
+    Number(
+      (
+        await getAccount(
+          program.provider.connection,
+          lot.userOrderTokenAccount,
+          undefined,
+          TOKEN_2022_PROGRAM_ID
+        )
+      ).amount
+    );
+
+  beforeAll(async () => {
+    lot = await setupPreparedLot({ numOrders: 3, numLotsPrepared: 1 });
+    await mintTo(
+      program.provider.connection,
+      lot.user,
+      lot.usdc.mint,
+      lot.userUsdcAccount,
+      lot.usdc.authority,
+      LOT_PRICE_IN_USDC
+    );
+    // The session key pays for the order out of USDC the user let it spend
+    userSession = await createSession(lot.user, {
+      account: lot.userUsdcAccount,
+      amount: LOT_PRICE_IN_USDC,
+    });
+    unfundedSession = await createSession(lot.user);
+    managerSession = await createSession(lot.manager);
+  }, 30000);
+
+  it("rejects orders from a session the user didn't approve", async () => {
+    await expect(placeOrder(managerSession, 1)).rejects.toThrow(
+      "Error Code: InvalidToken"
+    );
+  });
+  it("rejects orders from a session the user didn't let spend USDC", async () => {
+    // The token program's OwnerMismatch
+    await expect(placeOrder(unfundedSession, 1)).rejects.toThrow(
+      "custom program error: 0x4"
+    );
+  });
+  it("places orders for the user with their session key", async () => {
+    const before = await orderTokens();
+    await placeOrder(userSession, 1);
+    expect(await orderTokens()).toEqual(before + 1);
+    const receipt = await program.account.orderReceipt.fetch(
+      findReceiptPDA(
+        lot.contractPK,
+        lot.orderMint.publicKey,
+        lot.user.publicKey,
+        1
+      )
+    );
+    expect(receipt.buyer).toEqual(lot.user.publicKey);
+    expect(receipt.quantity.toNumber()).toEqual(1);
+  });
+  it("rejects lots prepared with a session the manager didn't approve", async () => {
+    await expect(prepareAnotherLot(lot, 1, userSession)).rejects.toThrow(
+      "Error Code: InvalidToken"
+    );
+  });
+  it("prepares lots for the manager with their session key", async () => {
+    const prepared = await prepareAnotherLot(lot, 1, managerSession);
+    const userLots = await getAccount(
+      program.provider.connection,
+      prepared.userLotTokenAccount,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    expect(Number(userLots.amount)).toEqual(UNITS_PER_LOT);
+    const { state } = await program.account.lot.fetch(prepared.lot);
+    expect(state).toEqual({ preparation: {} });
+  });
+});
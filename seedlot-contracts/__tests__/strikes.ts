@@ -0,0 +1,129 @@
+import * as anchor from "@coral-xyz/anchor";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  TOKEN_2022_PROGRAM_ID,
+  TOKEN_PROGRAM_ID,
+  getAssociatedTokenAddressSync,
+} from "@solana/spl-token";
+import {
+  CERTIFICATION_VALID_FOR,
+  EVIDENCE_HASH,
+  PreparedLot,
+  confirmLot,
+  prepareAnotherLot,
+  program,
+  setupPreparedLot,
+} from "../client/utils";
+
+describe("strikes", () => {
+  let lot: PreparedLot;
+  const certification = () =>
+    program.account.certification.fetch(lot.certification);
+  const setStrikePolicy = (strikePolicy: {
+    strikesPerPenalty: number;
+    penalty: { dropTier: {} } | { decertify: {} };
+  }) =>
+    program.methods
+      .setStrikePolicy(strikePolicy)
+      .accounts({ admin: lot.admin.publicKey, contract: lot.contractPK })
+      .signers([lot.admin])
+      .rpc();
+  const denyAnotherLot = async (reason: number) =>
+    confirmLot({ ...lot, ...(await prepareAnotherLot(lot, 1)) }, reason);
+
+  beforeAll(async () => {
+    lot = await setupPreparedLot({ numOrders: 8, numLotsPrepared: 1 });
+    await program.methods
+      .certify({ tier2: {} }, EVIDENCE_HASH, CERTIFICATION_VALID_FOR)
+      .accounts({
+        admin: lot.admin.publicKey,
+        manager: lot.manager.publicKey,
+        contract: lot.contractPK,
+        certification: lot.certification,
+        certificationMint: lot.certificationMint.publicKey,
+        managerTo: getAssociatedTokenAddressSync(
+          lot.certificationMint.publicKey,
+          lot.manager.publicKey,
+          false,
+          TOKEN_2022_PROGRAM_ID,
+          ASSOCIATED_TOKEN_PROGRAM_ID
+        ),
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([lot.admin])
+      .rpc();
+  }, 15000);
+
+  it("decertifies on the first denied lot by default", async () => {
+    const contract = await program.account.contract.fetch(lot.contractPK);
+    expect(contract.strikePolicy).toEqual({
+      strikesPerPenalty: 1,
+      penalty: { decertify: {} },
+    });
+  });
+  it("rejects policies without strikes", async () => {
+    await expect(
+      setStrikePolicy({ strikesPerPenalty: 0, penalty: { decertify: {} } })
+    ).rejects.toThrow("Error Code: InvalidStrikePolicy");
+  });
+  it("needs a reason to deny a lot", async () => {
+    await expect(
+      program.methods
+        .confirmLots(false, null)
+        .accounts({
+          admin: lot.admin.publicKey,
+          adminUsdcTokenAccount: null,
+          contract: lot.contractPK,
+          manager: lot.manager.publicKey,
+          certificationMint: lot.certificationMint.publicKey,
+          certification: lot.certification,
+          usdcMint: lot.usdc.mint,
+          tokenProgramStandard: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          lotMint: lot.lotMint.publicKey,
+          lot: lot.lot,
+          lotEscrow: lot.lotEscrow,
+          dispute: null,
+          managerRecord: lot.managerRecord,
+          bond: lot.bond,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          user: lot.user.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          orderMint: lot.orderMint.publicKey,
+          orderBalance: lot.orderBalance,
+          receipt: lot.receipt,
+        })
+        .signers([lot.admin])
+        .rpc()
+    ).rejects.toThrow("Error Code: MissingDenialReason");
+  });
+  it("adds a strike and records why the lot was denied", async () => {
+    await setStrikePolicy({ strikesPerPenalty: 2, penalty: { dropTier: {} } });
+    await confirmLot(lot, 4);
+
+    const afterDenial = await certification();
+    expect(afterDenial.strikes).toEqual(1);
+    expect(afterDenial.tier).toEqual({ tier2: {} });
+    expect(afterDenial.lastDenial.lotMint).toEqual(lot.lotMint.publicKey);
+    expect(afterDenial.lastDenial.reason).toEqual(4);
+  });
+  it("drops a tier once the manager has enough strikes", async () => {
+    await denyAnotherLot(5);
+
+    const afterPenalty = await certification();
+    expect(afterPenalty.strikes).toEqual(0);
+    expect(afterPenalty.tier).toEqual({ tier1: {} });
+    expect(afterPenalty.status).toEqual({ active: {} });
+    expect(afterPenalty.lastDenial.reason).toEqual(5);
+  }, 15000);
+  it("decertifies instead of dropping below tier 1", async () => {
+    await denyAnotherLot(6);
+    await denyAnotherLot(6);
+
+    const afterPenalty = await certification();
+    expect(afterPenalty.status).toEqual({ decertified: {} });
+    expect(afterPenalty.decertifications).toEqual(1);
+  }, 15000);
+});
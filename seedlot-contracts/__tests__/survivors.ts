@@ -0,0 +1,150 @@
+import * as anchor from "@coral-xyz/anchor";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  TOKEN_2022_PROGRAM_ID,
+  TOKEN_PROGRAM_ID,
+  getAccount,
+  getAssociatedTokenAddressSync,
+} from "@solana/spl-token";
+import {
+  LOT_PRICE_IN_USDC,
+  PreparedLot,
+  TREES_PER_LOT,
+  UNITS_PER_LOT,
+  findCertificationPDA,
+  prepareAnotherLot,
+  program,
+  setupPreparedLot,
+} from "../client/utils";
+
+describe("confirming survivors", () => {
+  const treesPerLot = TREES_PER_LOT.toNumber();
+  let lot: PreparedLot;
+  let managerUsdcAccount: anchor.web3.PublicKey;
+  const account = (
+    address: anchor.web3.PublicKey,
+    programId: anchor.web3.PublicKey
+  ) => getAccount(program.provider.connection, address, undefined, programId);
+  const confirmSurvivors = (
+    lot: PreparedLot,
+    survivingTrees: number,
+    refundUsdc: boolean
+  ) =>
+    program.methods
+      .confirmSurvivors(new anchor.BN(survivingTrees), refundUsdc)
+      .accounts({
+        admin: lot.admin.publicKey,
+        adminUsdcTokenAccount: null,
+        contract: lot.contractPK,
+        manager: lot.manager.publicKey,
+        certificationMint: lot.certificationMint.publicKey,
+        certification: findCertificationPDA(
+          lot.contractPK,
+          lot.manager.publicKey
+        ),
+        usdcMint: lot.usdc.mint,
+        tokenProgramStandard: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        lotMint: lot.lotMint.publicKey,
+        lot: lot.lot,
+        lotEscrow: lot.lotEscrow,
+        dispute: null,
+        managerRecord: lot.managerRecord,
+        bond: lot.bond,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        user: lot.user.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        orderMint: lot.orderMint.publicKey,
+        orderBalance: lot.orderBalance,
+        receipt: lot.receipt,
+        userUsdcTokenAccount: lot.userUsdcAccount,
+      })
+      .signers([lot.admin])
+      .rpc();
+
+  beforeAll(async () => {
+    lot = await setupPreparedLot({ numOrders: 5, numLotsPrepared: 3 });
+    managerUsdcAccount = getAssociatedTokenAddressSync(
+      lot.usdc.mint,
+      lot.manager.publicKey,
+      false,
+      TOKEN_PROGRAM_ID,
+      ASSOCIATED_TOKEN_PROGRAM_ID
+    );
+  }, 15000);
+
+  it("needs survivors, and no more than were prepared", async () => {
+    await expect(
+      confirmSurvivors(lot, 3 * treesPerLot + 1, false)
+    ).rejects.toThrow("Error Code: InvalidSurvivorCount");
+    await expect(confirmSurvivors(lot, 0, false)).rejects.toThrow(
+      "Error Code: InvalidSurvivorCount"
+    );
+  });
+
+  // A lot and a half survive out of three
+  describe("refunding in order tokens", () => {
+    beforeAll(async () => {
+      await confirmSurvivors(lot, treesPerLot + treesPerLot / 2, false);
+    });
+    it("pays the manager for the surviving trees", async () => {
+      const managerUsdc = await account(managerUsdcAccount, TOKEN_PROGRAM_ID);
+      expect(Number(managerUsdc.amount)).toEqual(1.5 * LOT_PRICE_IN_USDC);
+    });
+    it("burns the lost trees' units and thaws the rest", async () => {
+      const userLots = await account(
+        lot.userLotTokenAccount,
+        TOKEN_2022_PROGRAM_ID
+      );
+      expect(Number(userLots.amount)).toEqual(1.5 * UNITS_PER_LOT);
+      expect(userLots.isFrozen).toBe(false);
+      const growingLot = await program.account.lot.fetch(lot.lot);
+      expect(growingLot.state).toEqual({ growing: {} });
+      // The half lot still counts against the manager
+      const managerRecord = await program.account.managerRecord.fetch(
+        lot.managerRecord
+      );
+      expect(managerRecord.activeLots.toNumber()).toEqual(2);
+    });
+    it("refunds the trees short of a whole lot in USDC", async () => {
+      const userUsdc = await account(lot.userUsdcAccount, TOKEN_PROGRAM_ID);
+      expect(Number(userUsdc.amount)).toEqual(LOT_PRICE_IN_USDC / 2);
+    });
+    it("re-mints order tokens for the lost lot", async () => {
+      const userOrders = await account(
+        lot.userOrderTokenAccount,
+        TOKEN_2022_PROGRAM_ID
+      );
+      expect(Number(userOrders.amount)).toEqual(3);
+    });
+    it("puts the lost lot's order tokens back on their receipt", async () => {
+      const receipt = await program.account.orderReceipt.fetch(lot.receipt);
+      expect(receipt.quantityRemaining.toNumber()).toEqual(3);
+      const orderBalance = await program.account.orderBalance.fetch(
+        lot.orderBalance
+      );
+      expect(orderBalance.quantity.toNumber()).toEqual(3);
+    });
+  });
+
+  describe("refunding in USDC", () => {
+    let secondLot: PreparedLot;
+    beforeAll(async () => {
+      secondLot = { ...lot, ...(await prepareAnotherLot(lot, 2)) };
+      await confirmSurvivors(secondLot, treesPerLot, true);
+    }, 15000);
+    it("refunds the lost lot to the user", async () => {
+      const userUsdc = await account(lot.userUsdcAccount, TOKEN_PROGRAM_ID);
+      expect(Number(userUsdc.amount)).toEqual(1.5 * LOT_PRICE_IN_USDC);
+      const userLots = await account(
+        secondLot.userLotTokenAccount,
+        TOKEN_2022_PROGRAM_ID
+      );
+      expect(Number(userLots.amount)).toEqual(UNITS_PER_LOT);
+    });
+    it("pays the manager for the surviving lot", async () => {
+      const managerUsdc = await account(managerUsdcAccount, TOKEN_PROGRAM_ID);
+      expect(Number(managerUsdc.amount)).toEqual(2.5 * LOT_PRICE_IN_USDC);
+    });
+  });
+});
@@ -3,11 +3,35 @@ import * as anchor from "@coral-xyz/anchor";
 import { SeedlotContracts } from "../target/types/seedlot_contracts";
 import {
   ASSOCIATED_TOKEN_PROGRAM_ID,
+  AuthorityType,
+  createApproveInstruction,
+  createInitializeMetadataPointerInstruction,
+  createInitializeMintCloseAuthorityInstruction,
+  createInitializeMintInstruction,
+  createInitializePermanentDelegateInstruction,
   createMint,
+  createSetAuthorityInstruction,
+  ExtensionType,
+  freezeAccount,
   getAssociatedTokenAddressSync,
+  getMintLen,
+  getOrCreateAssociatedTokenAccount,
+  LENGTH_SIZE,
+  mintTo,
   TOKEN_2022_PROGRAM_ID,
   TOKEN_PROGRAM_ID,
+  TYPE_SIZE,
 } from "@solana/spl-token";
+import {
+  createInitializeInstruction,
+  createUpdateAuthorityInstruction,
+  createUpdateFieldInstruction,
+  pack,
+  TokenMetadata,
+} from "@solana/spl-token-metadata";
+import { createHash } from "crypto";
+import * as fs from "fs";
+import * as path from "path";
 
 const _airdrop = async <T extends anchor.Idl>(
   addy: web3.PublicKey,
@@ -39,19 +63,13 @@ export type Contract = anchor.IdlAccounts<SeedlotContracts>["contract"];
 export type CertificationTier =
   anchor.IdlTypes<SeedlotContracts>["certificationTier"];
 export type MintMetadata = anchor.IdlTypes<SeedlotContracts>["mintMetadata"];
+export type LotState = anchor.IdlTypes<SeedlotContracts>["lotState"];
+export type FeeConfig = anchor.IdlTypes<SeedlotContracts>["feeConfig"];
 export const program = anchor.workspace
   .SeedlotContracts as Program<SeedlotContracts>;
 export const confirmTx = (txHash: string) => _confirmTx(txHash, program);
 export const airdrop = (addy: web3.PublicKey) => _airdrop(addy, program);
 
-export const TOTAL_OFFERS = Number(
-  program.idl.constants.find((c) => c.name === "totalOffers")?.value
-);
-
-export const TOTAL_LOTS = Number(
-  program.idl.constants.find((c) => c.name === "totalLots")?.value
-);
-
 export const CERTIFICATION_MINT_METADATA: MintMetadata = {
   name: "Seedlot Manager Certification",
   symbol: "SEEDLOT-MCERT",
@@ -59,32 +77,88 @@ export const CERTIFICATION_MINT_METADATA: MintMetadata = {
   locationVarietyPrice: null,
   managerForLot: null,
 };
+// 10% to the manager up front, and harvest profit split 50/25/25 between user, admin and manager.
+export const DEFAULT_FEE_CONFIG: FeeConfig = {
+  upfrontFeeBps: 1000,
+  userProfitBps: 5000,
+  adminProfitBps: 2500,
+  managerProfitBps: 2500,
+};
+// Stands in for the hash of an inspection report.
+export const EVIDENCE_HASH = Array.from({ length: 32 }, (_, i) => i);
+// Agronomists re-inspect every year.
+export const CERTIFICATION_VALID_FOR = new anchor.BN(365 * 24 * 60 * 60);
 export const TREES_PER_LOT = new anchor.BN(10);
+// Lot tokens have 6 decimals so a lot can be split between holders.
+export const UNITS_PER_LOT = 1_000_000;
 export const PRICE_PER_TREE = "1500";
 export const LOT_PRICE_IN_USDC =
   TREES_PER_LOT.toNumber() * Number(PRICE_PER_TREE) * 10 ** 4;
 
-export const initializeZeroAccount = async (
-  admin: web3.Keypair,
-  space: number
-) => {
-  const newAccount = web3.Keypair.generate();
-  const lamportsForRentExemption =
-    await program.provider.connection.getMinimumBalanceForRentExemption(space);
-  const createAccountInstruction = web3.SystemProgram.createAccount({
-    fromPubkey: admin.publicKey,
-    newAccountPubkey: newAccount.publicKey,
-    lamports: lamportsForRentExemption,
-    space,
-    programId: program.programId,
+export const SESSION_KEYS_PROGRAM_ID = new web3.PublicKey(
+  "KeyspM2ssCJbqUhQ4k7sveSiY4WjnYsrXkC8oDbwde5"
+);
+
+export type Session = { signer: web3.Keypair; token: web3.PublicKey };
+
+// Has `authority` approve a new session key for this program through the
+// session keys program, which the validator clones from devnet. place_order
+// pays with the session key, so users who want it to place orders also let it
+// spend up to `usdcAllowance` of their USDC in the same transaction.
+export const createSession = async (
+  authority: web3.Keypair,
+  usdcAllowance?: { account: web3.PublicKey; amount: number }
+): Promise<Session> => {
+  const signer = web3.Keypair.generate();
+  await airdrop(signer.publicKey);
+  const [token] = web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("session_token"),
+      program.programId.toBuffer(),
+      signer.publicKey.toBuffer(),
+      authority.publicKey.toBuffer(),
+    ],
+    SESSION_KEYS_PROGRAM_ID
+  );
+  const createSessionIx = new web3.TransactionInstruction({
+    programId: SESSION_KEYS_PROGRAM_ID,
+    keys: [
+      { pubkey: token, isSigner: false, isWritable: true },
+      { pubkey: signer.publicKey, isSigner: true, isWritable: true },
+      { pubkey: authority.publicKey, isSigner: true, isWritable: true },
+      { pubkey: program.programId, isSigner: false, isWritable: false },
+      {
+        pubkey: web3.SystemProgram.programId,
+        isSigner: false,
+        isWritable: false,
+      },
+    ],
+    // No top up, and valid for the default hour
+    data: Buffer.concat([
+      createHash("sha256")
+        .update("global:create_session")
+        .digest()
+        .subarray(0, 8),
+      Buffer.from([0, 0]),
+    ]),
   });
-  const transaction = new web3.Transaction().add(createAccountInstruction);
+  const transaction = new web3.Transaction().add(createSessionIx);
+  if (usdcAllowance) {
+    transaction.add(
+      createApproveInstruction(
+        usdcAllowance.account,
+        signer.publicKey,
+        authority.publicKey,
+        usdcAllowance.amount
+      )
+    );
+  }
   await web3.sendAndConfirmTransaction(
     program.provider.connection,
     transaction,
-    [admin, newAccount]
+    [signer, authority]
   );
-  return newAccount;
+  return { signer, token };
 };
 
 export const initializeUSDC = async () => {
@@ -106,18 +180,17 @@ export const initializeUSDC = async () => {
 export const initialize = async () => {
   const admin = web3.Keypair.generate();
   const certificationMint = web3.Keypair.generate();
+  const offersGroup = web3.Keypair.generate();
+  const lotsGroup = web3.Keypair.generate();
+  const contractId = web3.Keypair.generate().publicKey;
   await airdrop(admin.publicKey);
 
   const [contractPK] = web3.PublicKey.findProgramAddressSync(
-    [Buffer.from("contract"), admin.publicKey.toBuffer()],
+    [Buffer.from("contract"), contractId.toBuffer()],
     program.programId
   );
 
-  const [offersAccount, usdc, lotsAccount] = await Promise.all([
-    initializeZeroAccount(admin, program.account.offers.size),
-    initializeUSDC(),
-    initializeZeroAccount(admin, program.account.lots.size),
-  ]);
+  const usdc = await initializeUSDC();
 
   const contractUsdcTokenAccount = getAssociatedTokenAddressSync(
     usdc.mint,
@@ -128,8 +201,8 @@ export const initialize = async () => {
   const accounts = {
     admin: admin.publicKey,
     contract: contractPK,
-    offersAccount: offersAccount.publicKey,
-    lotsAccount: lotsAccount.publicKey,
+    offersGroup: offersGroup.publicKey,
+    lotsGroup: lotsGroup.publicKey,
     systemProgram: web3.SystemProgram.programId,
     tokenProgram: TOKEN_2022_PROGRAM_ID,
     certificationMint: certificationMint.publicKey,
@@ -139,20 +212,999 @@ export const initialize = async () => {
     tokenProgramStandard: TOKEN_PROGRAM_ID,
   };
   const txHash = await program.methods
-    .initialize(TREES_PER_LOT, CERTIFICATION_MINT_METADATA)
+    .initialize(
+      contractId,
+      TREES_PER_LOT,
+      CERTIFICATION_MINT_METADATA,
+      DEFAULT_FEE_CONFIG
+    )
     .accounts(accounts)
-    .signers([admin, certificationMint])
+    .signers([admin, certificationMint, offersGroup, lotsGroup])
+    .preInstructions([
+      web3.ComputeBudgetProgram.setComputeUnitLimit({ units: 800_000 }),
+    ])
     .rpc();
 
   const txConfirmation = await confirmTx(txHash);
   return {
     txConfirmation,
+    contractId,
     contractPK,
     certificationMint,
     admin,
-    offersAccount,
-    lotsAccount,
+    offersGroup,
+    lotsGroup,
     contractUsdcTokenAccount,
     usdc,
   };
 };
+
+export const findLotPDA = (
+  contractPK: web3.PublicKey,
+  lotMint: web3.PublicKey
+) =>
+  web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("lot"), contractPK.toBuffer(), lotMint.toBuffer()],
+    program.programId
+  )[0];
+
+export const findManagerPDA = (
+  contractPK: web3.PublicKey,
+  manager: web3.PublicKey
+) =>
+  web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("manager"), contractPK.toBuffer(), manager.toBuffer()],
+    program.programId
+  )[0];
+
+export const findCertificationPDA = (
+  contractPK: web3.PublicKey,
+  manager: web3.PublicKey
+) =>
+  web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("certification"), contractPK.toBuffer(), manager.toBuffer()],
+    program.programId
+  )[0];
+
+export const findBondPDA = (
+  contractPK: web3.PublicKey,
+  manager: web3.PublicKey
+) =>
+  web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("bond"), contractPK.toBuffer(), manager.toBuffer()],
+    program.programId
+  )[0];
+
+export const findLotEscrowPDA = (
+  contractPK: web3.PublicKey,
+  lotMint: web3.PublicKey
+) =>
+  web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("lot_escrow"), contractPK.toBuffer(), lotMint.toBuffer()],
+    program.programId
+  )[0];
+
+export const findInspectorPDA = (
+  contractPK: web3.PublicKey,
+  inspector: web3.PublicKey
+) =>
+  web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("inspector"), contractPK.toBuffer(), inspector.toBuffer()],
+    program.programId
+  )[0];
+
+export const findBuyerPDA = (
+  contractPK: web3.PublicKey,
+  buyer: web3.PublicKey
+) =>
+  web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("buyer"), contractPK.toBuffer(), buyer.toBuffer()],
+    program.programId
+  )[0];
+
+export const findDisputePDA = (
+  contractPK: web3.PublicKey,
+  lotMint: web3.PublicKey
+) =>
+  web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("dispute"), contractPK.toBuffer(), lotMint.toBuffer()],
+    program.programId
+  )[0];
+
+export const findHoldingPDA = (
+  contractPK: web3.PublicKey,
+  lotMint: web3.PublicKey,
+  holder: web3.PublicKey
+) =>
+  web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("holding"),
+      contractPK.toBuffer(),
+      lotMint.toBuffer(),
+      holder.toBuffer(),
+    ],
+    program.programId
+  )[0];
+
+export const findHarvestVaultPDA = (
+  contractPK: web3.PublicKey,
+  lotMint: web3.PublicKey
+) =>
+  web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("harvest_vault"), contractPK.toBuffer(), lotMint.toBuffer()],
+    program.programId
+  )[0];
+
+export const findHarvestRecordPDA = (
+  contractPK: web3.PublicKey,
+  lotMint: web3.PublicKey,
+  index: number
+) =>
+  web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("harvest"),
+      contractPK.toBuffer(),
+      lotMint.toBuffer(),
+      new anchor.BN(index).toArrayLike(Buffer, "le", 4),
+    ],
+    program.programId
+  )[0];
+
+export const findHarvestExpensePDA = (
+  contractPK: web3.PublicKey,
+  lotMint: web3.PublicKey,
+  harvest: number,
+  index: number
+) =>
+  web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("expense"),
+      contractPK.toBuffer(),
+      lotMint.toBuffer(),
+      new anchor.BN(harvest).toArrayLike(Buffer, "le", 4),
+      new anchor.BN(index).toArrayLike(Buffer, "le", 2),
+    ],
+    program.programId
+  )[0];
+
+export const findOfferConfigPDA = (
+  contractPK: web3.PublicKey,
+  orderMint: web3.PublicKey
+) =>
+  web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("offer_config"), contractPK.toBuffer(), orderMint.toBuffer()],
+    program.programId
+  )[0];
+
+export const findAuthorizationPDA = (
+  contractPK: web3.PublicKey,
+  orderMint: web3.PublicKey,
+  user: web3.PublicKey,
+  manager: web3.PublicKey
+) =>
+  web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("authorization"),
+      contractPK.toBuffer(),
+      orderMint.toBuffer(),
+      user.toBuffer(),
+      manager.toBuffer(),
+    ],
+    program.programId
+  )[0];
+
+export const findOrderBalancePDA = (
+  contractPK: web3.PublicKey,
+  orderMint: web3.PublicKey,
+  user: web3.PublicKey
+) =>
+  web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("order"),
+      contractPK.toBuffer(),
+      orderMint.toBuffer(),
+      user.toBuffer(),
+    ],
+    program.programId
+  )[0];
+
+export const findReceiptPDA = (
+  contractPK: web3.PublicKey,
+  orderMint: web3.PublicKey,
+  user: web3.PublicKey,
+  orderId: number
+) =>
+  web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("receipt"),
+      contractPK.toBuffer(),
+      orderMint.toBuffer(),
+      user.toBuffer(),
+      new anchor.BN(orderId).toArrayLike(Buffer, "le", 8),
+    ],
+    program.programId
+  )[0];
+
+// The user lets `manager` prepare up to `lots` of their `orderMint` orders.
+export const authorizeManager = async ({
+  contractPK,
+  user,
+  manager,
+  orderMint,
+  lots,
+}: {
+  contractPK: web3.PublicKey;
+  user: web3.Keypair;
+  manager: web3.PublicKey;
+  orderMint: web3.PublicKey;
+  lots: number;
+}) => {
+  const txHash = await program.methods
+    .authorizeManager(new anchor.BN(lots))
+    .accounts({
+      user: user.publicKey,
+      contract: contractPK,
+      manager,
+      orderMint,
+      authorization: findAuthorizationPDA(
+        contractPK,
+        orderMint,
+        user.publicKey,
+        manager
+      ),
+      tokenProgram: TOKEN_2022_PROGRAM_ID,
+      systemProgram: web3.SystemProgram.programId,
+    })
+    .signers([user])
+    .rpc();
+  return confirmTx(txHash);
+};
+
+// Mints the manager `amount` USDC and posts it all as their bond.
+export const postBond = async ({
+  contractPK,
+  manager,
+  usdc,
+  amount = 0,
+}: {
+  contractPK: web3.PublicKey;
+  manager: web3.Keypair;
+  usdc: { mint: web3.PublicKey; authority: web3.Keypair };
+  amount?: number;
+}) => {
+  const managerUsdcAccount = await getOrCreateAssociatedTokenAccount(
+    program.provider.connection,
+    manager,
+    usdc.mint,
+    manager.publicKey,
+    false,
+    undefined,
+    undefined,
+    TOKEN_PROGRAM_ID,
+    ASSOCIATED_TOKEN_PROGRAM_ID
+  );
+  if (amount > 0) {
+    await mintTo(
+      program.provider.connection,
+      manager,
+      usdc.mint,
+      managerUsdcAccount.address,
+      usdc.authority,
+      amount
+    );
+  }
+  const txHash = await program.methods
+    .postBond(new anchor.BN(amount))
+    .accounts({
+      manager: manager.publicKey,
+      contract: contractPK,
+      managerRecord: findManagerPDA(contractPK, manager.publicKey),
+      usdcMint: usdc.mint,
+      bond: findBondPDA(contractPK, manager.publicKey),
+      managerUsdcTokenAccount: managerUsdcAccount.address,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      tokenProgramStandard: TOKEN_PROGRAM_ID,
+      systemProgram: web3.SystemProgram.programId,
+    })
+    .signers([manager])
+    .rpc();
+  return confirmTx(txHash);
+};
+
+// Lets `buyer` deposit harvests into the contract's lots.
+export const addBuyer = async ({
+  contractPK,
+  admin,
+  buyer,
+}: {
+  contractPK: web3.PublicKey;
+  admin: web3.Keypair;
+  buyer: web3.PublicKey;
+}) => {
+  const txHash = await program.methods
+    .addBuyer()
+    .accounts({
+      admin: admin.publicKey,
+      contract: contractPK,
+      buyer,
+      buyerRecord: findBuyerPDA(contractPK, buyer),
+      systemProgram: web3.SystemProgram.programId,
+    })
+    .signers([admin])
+    .rpc();
+  return confirmTx(txHash);
+};
+
+// Walks a fresh contract through offer -> order -> certification -> prepared lot.
+export const setupPreparedLot = async ({
+  numOrders = 5,
+  numLotsPrepared = 3,
+  offerFeeConfig = null,
+  payoutSchedule = [],
+}: {
+  numOrders?: number;
+  numLotsPrepared?: number;
+  offerFeeConfig?: FeeConfig | null;
+  payoutSchedule?: number[];
+} = {}) => {
+  const initialized = await initialize();
+  const {
+    admin,
+    contractPK,
+    offersGroup,
+    lotsGroup,
+    usdc,
+    contractUsdcTokenAccount,
+    certificationMint,
+  } = initialized;
+  const user = web3.Keypair.generate();
+  const manager = web3.Keypair.generate();
+  const orderMint = web3.Keypair.generate();
+  const lotMint = web3.Keypair.generate();
+  await Promise.all([airdrop(user.publicKey), airdrop(manager.publicKey)]);
+
+  const userUsdcAccount = await getOrCreateAssociatedTokenAccount(
+    program.provider.connection,
+    user,
+    usdc.mint,
+    user.publicKey,
+    false,
+    undefined,
+    undefined,
+    TOKEN_PROGRAM_ID,
+    ASSOCIATED_TOKEN_PROGRAM_ID
+  );
+  await mintTo(
+    program.provider.connection,
+    user,
+    usdc.mint,
+    userUsdcAccount.address,
+    usdc.authority,
+    numOrders * LOT_PRICE_IN_USDC
+  );
+
+  await program.methods
+    .addOffer(
+      {
+        name: `Seedlot Offer`,
+        symbol: `SO`,
+        uri: `https://example.com/offer/`,
+        locationVarietyPrice: [`location`, `variety`, PRICE_PER_TREE],
+        managerForLot: null,
+      },
+      offerFeeConfig
+    )
+    .accounts({
+      admin: admin.publicKey,
+      contract: contractPK,
+      offersGroup: offersGroup.publicKey,
+      orderMint: orderMint.publicKey,
+      offerConfig: findOfferConfigPDA(contractPK, orderMint.publicKey),
+      systemProgram: web3.SystemProgram.programId,
+      rent: web3.SYSVAR_RENT_PUBKEY,
+      tokenProgram: TOKEN_2022_PROGRAM_ID,
+    })
+    .signers([admin, orderMint])
+    .rpc();
+  if (payoutSchedule.length > 0) {
+    await program.methods
+      .setPayoutSchedule(payoutSchedule)
+      .accounts({
+        admin: admin.publicKey,
+        contract: contractPK,
+        orderMint: orderMint.publicKey,
+        offerConfig: findOfferConfigPDA(contractPK, orderMint.publicKey),
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .signers([admin])
+      .rpc();
+  }
+
+  const userOrderTokenAccount = getAssociatedTokenAddressSync(
+    orderMint.publicKey,
+    user.publicKey,
+    false,
+    TOKEN_2022_PROGRAM_ID,
+    ASSOCIATED_TOKEN_PROGRAM_ID
+  );
+  await program.methods
+    .placeOrder(
+      new anchor.BN(numOrders),
+      new anchor.BN(numOrders * LOT_PRICE_IN_USDC),
+      null
+    )
+    .accounts({
+      user: user.publicKey,
+      signer: user.publicKey,
+      sessionToken: null,
+      contract: contractPK,
+      offerMint: orderMint.publicKey,
+      userTokenAccount: userOrderTokenAccount,
+      orderBalance: findOrderBalancePDA(
+        contractPK,
+        orderMint.publicKey,
+        user.publicKey
+      ),
+      receipt: findReceiptPDA(
+        contractPK,
+        orderMint.publicKey,
+        user.publicKey,
+        0
+      ),
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      tokenProgram: TOKEN_2022_PROGRAM_ID,
+      systemProgram: web3.SystemProgram.programId,
+      usdcMint: usdc.mint,
+      usdcFrom: userUsdcAccount.address,
+      contractUsdcTokenAccount,
+      tokenProgramStandard: TOKEN_PROGRAM_ID,
+    })
+    .signers([user])
+    .rpc();
+
+  await program.methods
+    .certify({ tier1: {} }, EVIDENCE_HASH, CERTIFICATION_VALID_FOR)
+    .accounts({
+      admin: admin.publicKey,
+      manager: manager.publicKey,
+      contract: contractPK,
+      certificationMint: certificationMint.publicKey,
+      certification: findCertificationPDA(contractPK, manager.publicKey),
+      tokenProgram: TOKEN_2022_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      systemProgram: web3.SystemProgram.programId,
+    })
+    .signers([admin])
+    .rpc();
+
+  await postBond({ contractPK, manager, usdc });
+  await authorizeManager({
+    contractPK,
+    user,
+    manager: manager.publicKey,
+    orderMint: orderMint.publicKey,
+    lots: numOrders,
+  });
+
+  const userLotTokenAccount = getAssociatedTokenAddressSync(
+    lotMint.publicKey,
+    user.publicKey,
+    false,
+    TOKEN_2022_PROGRAM_ID,
+    ASSOCIATED_TOKEN_PROGRAM_ID
+  );
+  await program.methods
+    .prepareLots(new anchor.BN(numLotsPrepared), manager.publicKey.toBase58())
+    .accounts({
+      user: user.publicKey,
+      manager: manager.publicKey,
+      signer: manager.publicKey,
+      sessionToken: null,
+      contract: contractPK,
+      orderMint: orderMint.publicKey,
+      userOrderTokenAccount,
+      orderBalance: findOrderBalancePDA(
+        contractPK,
+        orderMint.publicKey,
+        user.publicKey
+      ),
+      receipt: findReceiptPDA(
+        contractPK,
+        orderMint.publicKey,
+        user.publicKey,
+        0
+      ),
+      lotMint: lotMint.publicKey,
+      lotsGroup: lotsGroup.publicKey,
+      lot: findLotPDA(contractPK, lotMint.publicKey),
+      lotEscrow: findLotEscrowPDA(contractPK, lotMint.publicKey),
+      managerRecord: findManagerPDA(contractPK, manager.publicKey),
+      bond: findBondPDA(contractPK, manager.publicKey),
+      offerConfig: findOfferConfigPDA(contractPK, orderMint.publicKey),
+      authorization: findAuthorizationPDA(
+        contractPK,
+        orderMint.publicKey,
+        user.publicKey,
+        manager.publicKey
+      ),
+      userLotTokenAccount,
+      usdcMint: usdc.mint,
+      certification: findCertificationPDA(contractPK, manager.publicKey),
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      tokenProgram: TOKEN_2022_PROGRAM_ID,
+      tokenProgramStandard: TOKEN_PROGRAM_ID,
+      systemProgram: web3.SystemProgram.programId,
+      rent: web3.SYSVAR_RENT_PUBKEY,
+    })
+    .preInstructions([
+      web3.ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 }),
+    ])
+    .signers([manager, lotMint])
+    .rpc();
+
+  return {
+    ...initialized,
+    user,
+    manager,
+    orderMint,
+    lotMint,
+    userUsdcAccount: userUsdcAccount.address,
+    userOrderTokenAccount,
+    userLotTokenAccount,
+    lot: findLotPDA(contractPK, lotMint.publicKey),
+    lotEscrow: findLotEscrowPDA(contractPK, lotMint.publicKey),
+    managerRecord: findManagerPDA(contractPK, manager.publicKey),
+    bond: findBondPDA(contractPK, manager.publicKey),
+    certification: findCertificationPDA(contractPK, manager.publicKey),
+    orderBalance: findOrderBalancePDA(
+      contractPK,
+      orderMint.publicKey,
+      user.publicKey
+    ),
+    receipt: findReceiptPDA(
+      contractPK,
+      orderMint.publicKey,
+      user.publicKey,
+      0
+    ),
+    offerConfig: findOfferConfigPDA(contractPK, orderMint.publicKey),
+    authorization: findAuthorizationPDA(
+      contractPK,
+      orderMint.publicKey,
+      user.publicKey,
+      manager.publicKey
+    ),
+  };
+};
+
+export type PreparedLot = Awaited<ReturnType<typeof setupPreparedLot>>;
+
+// Prepares more of the user's orders into a new lot with the same manager, or
+// with a session key they approved.
+export const prepareAnotherLot = async (
+  lot: PreparedLot,
+  numLots: number,
+  session: Session | null = null
+) => {
+  const lotMint = web3.Keypair.generate();
+  const userLotTokenAccount = getAssociatedTokenAddressSync(
+    lotMint.publicKey,
+    lot.user.publicKey,
+    false,
+    TOKEN_2022_PROGRAM_ID,
+    ASSOCIATED_TOKEN_PROGRAM_ID
+  );
+  await program.methods
+    .prepareLots(new anchor.BN(numLots), lot.manager.publicKey.toBase58())
+    .accounts({
+      user: lot.user.publicKey,
+      manager: lot.manager.publicKey,
+      signer: session ? session.signer.publicKey : lot.manager.publicKey,
+      sessionToken: session ? session.token : null,
+      contract: lot.contractPK,
+      orderMint: lot.orderMint.publicKey,
+      userOrderTokenAccount: lot.userOrderTokenAccount,
+      orderBalance: lot.orderBalance,
+      receipt: lot.receipt,
+      lotMint: lotMint.publicKey,
+      lotsGroup: lot.lotsGroup.publicKey,
+      lot: findLotPDA(lot.contractPK, lotMint.publicKey),
+      lotEscrow: findLotEscrowPDA(lot.contractPK, lotMint.publicKey),
+      managerRecord: lot.managerRecord,
+      bond: lot.bond,
+      offerConfig: lot.offerConfig,
+      authorization: lot.authorization,
+      userLotTokenAccount,
+      usdcMint: lot.usdc.mint,
+      certification: findCertificationPDA(
+        lot.contractPK,
+        lot.manager.publicKey
+      ),
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      tokenProgram: TOKEN_2022_PROGRAM_ID,
+      tokenProgramStandard: TOKEN_PROGRAM_ID,
+      systemProgram: web3.SystemProgram.programId,
+      rent: web3.SYSVAR_RENT_PUBKEY,
+    })
+    .preInstructions([
+      web3.ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 }),
+    ])
+    .signers([session ? session.signer : lot.manager, lotMint])
+    .rpc();
+  return {
+    lotMint,
+    userLotTokenAccount,
+    lot: findLotPDA(lot.contractPK, lotMint.publicKey),
+    lotEscrow: findLotEscrowPDA(lot.contractPK, lotMint.publicKey),
+  };
+};
+
+// Denies the lot instead when given a reason for it.
+export const confirmLot = async (
+  lot: PreparedLot,
+  denialReason: number | null = null
+) => {
+  const txHash = await program.methods
+    .confirmLots(denialReason === null, denialReason)
+    .accounts({
+      admin: lot.admin.publicKey,
+      adminUsdcTokenAccount: null,
+      contract: lot.contractPK,
+      manager: lot.manager.publicKey,
+      certificationMint: lot.certificationMint.publicKey,
+      certification: findCertificationPDA(
+        lot.contractPK,
+        lot.manager.publicKey
+      ),
+      usdcMint: lot.usdc.mint,
+      tokenProgramStandard: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      lotMint: lot.lotMint.publicKey,
+      lot: lot.lot,
+      lotEscrow: lot.lotEscrow,
+      dispute: null,
+      managerRecord: lot.managerRecord,
+      bond: lot.bond,
+      tokenProgram: TOKEN_2022_PROGRAM_ID,
+      user: lot.user.publicKey,
+      systemProgram: web3.SystemProgram.programId,
+      orderMint: lot.orderMint.publicKey,
+      orderBalance: lot.orderBalance,
+      receipt: lot.receipt,
+      userUsdcTokenAccount: null,
+    })
+    .signers([lot.admin])
+    .rpc();
+  return confirmTx(txHash);
+};
+
+const loadKeypair = (file: string) =>
+  web3.Keypair.fromSecretKey(
+    Uint8Array.from(JSON.parse(fs.readFileSync(file, "utf8")))
+  );
+
+// Creates a Token-2022 mint with every authority the old contract PDA used to
+// hold over its mints. `authority` sets the mint up, mints each holder their
+// frozen tokens and then hands its remaining authorities to `legacyContract`.
+const createLegacyMint = async ({
+  authority,
+  mint,
+  legacyContract,
+  additionalMetadata,
+  holders = [],
+}: {
+  authority: web3.Keypair;
+  mint: web3.Keypair;
+  legacyContract: web3.PublicKey;
+  additionalMetadata: [string, string][];
+  holders?: [web3.PublicKey, number][];
+}) => {
+  const connection = program.provider.connection;
+  const metadata: TokenMetadata = {
+    mint: mint.publicKey,
+    name: "Seedlot",
+    symbol: "SL",
+    uri: "https://app.seedlot.io",
+    additionalMetadata,
+  };
+  const mintLen = getMintLen([
+    ExtensionType.MetadataPointer,
+    ExtensionType.PermanentDelegate,
+    ExtensionType.MintCloseAuthority,
+  ]);
+  const metadataLen = TYPE_SIZE + LENGTH_SIZE + pack(metadata).length;
+  const lamports = await connection.getMinimumBalanceForRentExemption(
+    mintLen + metadataLen
+  );
+  const setUp = new web3.Transaction().add(
+    web3.SystemProgram.createAccount({
+      fromPubkey: authority.publicKey,
+      newAccountPubkey: mint.publicKey,
+      space: mintLen,
+      lamports,
+      programId: TOKEN_2022_PROGRAM_ID,
+    }),
+    createInitializeMetadataPointerInstruction(
+      mint.publicKey,
+      legacyContract,
+      mint.publicKey,
+      TOKEN_2022_PROGRAM_ID
+    ),
+    createInitializePermanentDelegateInstruction(
+      mint.publicKey,
+      legacyContract,
+      TOKEN_2022_PROGRAM_ID
+    ),
+    createInitializeMintCloseAuthorityInstruction(
+      mint.publicKey,
+      legacyContract,
+      TOKEN_2022_PROGRAM_ID
+    ),
+    createInitializeMintInstruction(
+      mint.publicKey,
+      0,
+      authority.publicKey,
+      authority.publicKey,
+      TOKEN_2022_PROGRAM_ID
+    ),
+    createInitializeInstruction({
+      programId: TOKEN_2022_PROGRAM_ID,
+      metadata: mint.publicKey,
+      updateAuthority: authority.publicKey,
+      mint: mint.publicKey,
+      mintAuthority: authority.publicKey,
+      name: metadata.name,
+      symbol: metadata.symbol,
+      uri: metadata.uri,
+    }),
+    ...additionalMetadata.map(([field, value]) =>
+      createUpdateFieldInstruction({
+        programId: TOKEN_2022_PROGRAM_ID,
+        metadata: mint.publicKey,
+        updateAuthority: authority.publicKey,
+        field,
+        value,
+      })
+    )
+  );
+  await web3.sendAndConfirmTransaction(connection, setUp, [authority, mint]);
+
+  for (const [owner, amount] of holders) {
+    const tokenAccount = await getOrCreateAssociatedTokenAccount(
+      connection,
+      authority,
+      mint.publicKey,
+      owner,
+      false,
+      undefined,
+      undefined,
+      TOKEN_2022_PROGRAM_ID,
+      ASSOCIATED_TOKEN_PROGRAM_ID
+    );
+    await mintTo(
+      connection,
+      authority,
+      mint.publicKey,
+      tokenAccount.address,
+      authority,
+      amount,
+      [],
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    await freezeAccount(
+      connection,
+      authority,
+      tokenAccount.address,
+      mint.publicKey,
+      authority,
+      [],
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+  }
+
+  const handOver = new web3.Transaction().add(
+    createUpdateAuthorityInstruction({
+      programId: TOKEN_2022_PROGRAM_ID,
+      metadata: mint.publicKey,
+      oldAuthority: authority.publicKey,
+      newAuthority: legacyContract,
+    }),
+    ...[AuthorityType.MintTokens, AuthorityType.FreezeAccount].map(
+      (authorityType) =>
+        createSetAuthorityInstruction(
+          mint.publicKey,
+          authority.publicKey,
+          authorityType,
+          legacyContract,
+          [],
+          TOKEN_2022_PROGRAM_ID
+        )
+    )
+  );
+  await web3.sendAndConfirmTransaction(connection, handOver, [authority]);
+};
+
+// A contract seeded by its admin, as contracts were before contract ids. The
+// validator loads the contract (and its lots account, if it has one) from
+// __tests__/fixtures/<name>, so this only creates the mints those accounts
+// point at. `lotStates` is the metadata state of each legacy lot, `lotHolders`
+// the lot tokens of each of them, `badges` the certification tokens and
+// `orders` the order tokens of an offer users hold.
+export const setupLegacyContract = async ({
+  name,
+  lotStates = [],
+  lotHolders = [],
+  badges = [],
+  orders = [],
+  usdcAmount = LOT_PRICE_IN_USDC,
+}: {
+  name: string;
+  lotStates?: string[];
+  lotHolders?: [web3.PublicKey, number][];
+  badges?: [web3.PublicKey, number][];
+  orders?: [web3.PublicKey, number][];
+  usdcAmount?: number;
+}) => {
+  const fixtures = path.join(__dirname, "../__tests__/fixtures", name);
+  const admin = loadKeypair(path.join(fixtures, "admin.json"));
+  const usdcMint = loadKeypair(path.join(fixtures, "usdc-mint.json"));
+  const certificationMint = loadKeypair(
+    path.join(fixtures, "certification-mint.json")
+  );
+  const lotMints = lotStates.map((_, i) =>
+    loadKeypair(path.join(fixtures, `lot-mint-${i}.json`))
+  );
+  const orderMint =
+    orders.length > 0
+      ? loadKeypair(path.join(fixtures, "order-mint.json"))
+      : null;
+  const [legacyContractPK] = web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("contract"), admin.publicKey.toBuffer()],
+    program.programId
+  );
+  await airdrop(admin.publicKey);
+
+  await createMint(
+    program.provider.connection,
+    admin,
+    admin.publicKey,
+    null,
+    6,
+    usdcMint,
+    undefined,
+    TOKEN_PROGRAM_ID
+  );
+  const legacyUsdcTokenAccount = await getOrCreateAssociatedTokenAccount(
+    program.provider.connection,
+    admin,
+    usdcMint.publicKey,
+    legacyContractPK,
+    true,
+    undefined,
+    undefined,
+    TOKEN_PROGRAM_ID,
+    ASSOCIATED_TOKEN_PROGRAM_ID
+  );
+  await mintTo(
+    program.provider.connection,
+    admin,
+    usdcMint.publicKey,
+    legacyUsdcTokenAccount.address,
+    admin,
+    usdcAmount
+  );
+
+  await createLegacyMint({
+    authority: admin,
+    mint: certificationMint,
+    legacyContract: legacyContractPK,
+    additionalMetadata: [],
+    holders: badges,
+  });
+  if (orderMint) {
+    await createLegacyMint({
+      authority: admin,
+      mint: orderMint,
+      legacyContract: legacyContractPK,
+      additionalMetadata: [
+        ["location", "location"],
+        ["variety", "variety"],
+        ["price", PRICE_PER_TREE],
+      ],
+      holders: orders,
+    });
+  }
+  for (const [i, state] of lotStates.entries()) {
+    await createLegacyMint({
+      authority: admin,
+      mint: lotMints[i],
+      legacyContract: legacyContractPK,
+      additionalMetadata: [
+        ["location", "location"],
+        ["variety", "variety"],
+        ["manager", admin.publicKey.toBase58()],
+        ["state", state],
+      ],
+      holders: lotHolders,
+    });
+  }
+
+  return {
+    admin,
+    legacyContractPK,
+    legacyUsdcTokenAccount: legacyUsdcTokenAccount.address,
+    usdc: { mint: usdcMint.publicKey, authority: admin },
+    certificationMint,
+    lotMints,
+    orderMint,
+  };
+};
+
+export type LegacyContract = Awaited<ReturnType<typeof setupLegacyContract>>;
+
+// Moves a legacy contract to one seeded by a new contract id, and then every
+// mint it has.
+export const migrateLegacyContract = async (legacy: LegacyContract) => {
+  const contractId = web3.Keypair.generate().publicKey;
+  const offersGroup = web3.Keypair.generate();
+  const lotsGroup = web3.Keypair.generate();
+  const [contractPK] = web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("contract"), contractId.toBuffer()],
+    program.programId
+  );
+  const contractUsdcTokenAccount = getAssociatedTokenAddressSync(
+    legacy.usdc.mint,
+    contractPK,
+    true
+  );
+  await program.methods
+    .migrateContract(contractId)
+    .accounts({
+      admin: legacy.admin.publicKey,
+      legacyContract: legacy.legacyContractPK,
+      contract: contractPK,
+      offersGroup: offersGroup.publicKey,
+      lotsGroup: lotsGroup.publicKey,
+      usdcMint: legacy.usdc.mint,
+      legacyUsdcTokenAccount: legacy.legacyUsdcTokenAccount,
+      contractUsdcTokenAccount,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      tokenProgram: TOKEN_2022_PROGRAM_ID,
+      tokenProgramStandard: TOKEN_PROGRAM_ID,
+      systemProgram: web3.SystemProgram.programId,
+      rent: web3.SYSVAR_RENT_PUBKEY,
+    })
+    .preInstructions([
+      web3.ComputeBudgetProgram.setComputeUnitLimit({ units: 800_000 }),
+    ])
+    .signers([legacy.admin, offersGroup, lotsGroup])
+    .rpc();
+
+  const mints = [legacy.certificationMint, ...legacy.lotMints];
+  if (legacy.orderMint) {
+    mints.push(legacy.orderMint);
+  }
+  for (const mint of mints) {
+    await program.methods
+      .migrateMint()
+      .accounts({
+        admin: legacy.admin.publicKey,
+        contract: contractPK,
+        legacyContract: legacy.legacyContractPK,
+        mint: mint.publicKey,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([legacy.admin])
+      .rpc();
+  }
+  return {
+    contractId,
+    contractPK,
+    contractUsdcTokenAccount,
+    offersGroup,
+    lotsGroup,
+  };
+};
@@ -0,0 +1,53 @@
+use anchor_lang::prelude::*;
+
+use crate::{Contract, SeedlotContractsError};
+
+pub mod instructions {
+    use super::*;
+
+    // The current admin names who should take over. Proposing again replaces the last proposal.
+    pub fn propose_admin(ctx: Context<ProposeAdmin>, new_admin: Pubkey) -> Result<()> {
+        ctx.accounts.contract.pending_admin = Some(new_admin);
+        Ok(())
+    }
+
+    // The proposed admin takes over by signing, so the contract can't be handed to a wrong key.
+    // The arbitrator can't take over, since they rule on the admin's denials.
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        let contract = &mut ctx.accounts.contract;
+        require!(
+            contract.pending_admin == Some(ctx.accounts.new_admin.key()),
+            SeedlotContractsError::NotPendingAdmin
+        );
+        require!(
+            contract.arbitrator != Some(ctx.accounts.new_admin.key()),
+            SeedlotContractsError::ArbitratorCannotBeAdmin
+        );
+        contract.admin = ctx.accounts.new_admin.key();
+        contract.pending_admin = None;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct ProposeAdmin<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"contract", contract.contract_id.as_ref()],
+        bump,
+        has_one = admin,
+    )]
+    pub contract: Account<'info, Contract>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    pub new_admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"contract", contract.contract_id.as_ref()],
+        bump,
+    )]
+    pub contract: Account<'info, Contract>,
+}
@@ -0,0 +1,145 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_interface::{Mint, TokenAccount};
+use solana_program::program_option::COption;
+
+use crate::{
+    Badge, Certification, CertificationStatus, CertificationTier, Contract, Probation,
+    SeedlotContractsError,
+};
+
+pub mod instructions {
+    use super::*;
+
+    // Filing again replaces the evidence of an appeal that is still open.
+    pub fn file_appeal(ctx: Context<FileAppeal>, evidence_hash: [u8; 32]) -> Result<()> {
+        require!(
+            ctx.accounts.certification.status == CertificationStatus::Decertified,
+            SeedlotContractsError::ManagerNotDecertified
+        );
+        let appeal = &mut ctx.accounts.appeal;
+        appeal.manager = ctx.accounts.manager.key();
+        appeal.evidence_hash = evidence_hash;
+        appeal.filed_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    // Grants the manager's appeal, closing it and bringing them back at the given tier.
+    pub fn reinstate(
+        ctx: Context<Reinstate>,
+        tier: CertificationTier,
+        valid_for: i64,
+        probation: Option<Probation>,
+    ) -> Result<()> {
+        ctx.accounts.certification.reinstate(
+            tier,
+            ctx.accounts.admin.key(),
+            ctx.accounts.appeal.evidence_hash,
+            valid_for,
+            probation,
+        )?;
+
+        ctx.accounts
+            .badge(ctx.bumps.contract)
+            .show(ctx.program_id, &ctx.accounts.certification)
+    }
+}
+
+// A decertified manager's request to be reinstated, seeded by [b"appeal", contract, manager].
+#[account]
+pub struct Appeal {
+    pub manager: Pubkey,
+    pub evidence_hash: [u8; 32],
+    pub filed_at: i64,
+}
+
+impl Appeal {
+    pub const LEN: usize = 8 // Discriminator
+     + 32 // manager
+     + 32 // evidence_hash
+     + 8; // filed_at
+}
+
+impl<'info> Reinstate<'info> {
+    fn badge(&self, contract_bump: u8) -> Badge<'info> {
+        Badge {
+            manager: self.manager.to_account_info(),
+            contract: self.contract.clone(),
+            contract_bump,
+            certification_mint: self.certification_mint.clone(),
+            token_account: self.manager_to.clone(),
+            associated_token_program: self.associated_token_program.clone(),
+            token_program: self.token_program.clone(),
+        }
+    }
+}
+
+#[derive(Accounts)]
+pub struct FileAppeal<'info> {
+    #[account(mut)]
+    pub manager: Signer<'info>,
+    #[account(
+        seeds = [b"contract", contract.contract_id.as_ref()],
+        bump,
+    )]
+    pub contract: Account<'info, Contract>,
+    #[account(
+        seeds = [b"certification", contract.key().as_ref(), manager.key().as_ref()],
+        bump
+    )]
+    pub certification: Account<'info, Certification>,
+    #[account(
+        init_if_needed,
+        payer = manager,
+        space = Appeal::LEN,
+        seeds = [b"appeal", contract.key().as_ref(), manager.key().as_ref()],
+        bump
+    )]
+    pub appeal: Account<'info, Appeal>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Reinstate<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        constraint = admin.key() != manager.key() @ SeedlotContractsError::AdminCannotBeCertified
+    )]
+    pub manager: SystemAccount<'info>,
+    #[account(
+        seeds = [b"contract", contract.contract_id.as_ref()],
+        bump,
+        has_one = admin,
+    )]
+    pub contract: Account<'info, Contract>,
+    #[account(
+        mut,
+        seeds = [b"certification", contract.key().as_ref(), manager.key().as_ref()],
+        bump
+    )]
+    pub certification: Account<'info, Certification>,
+    #[account(
+        mut,
+        close = manager,
+        seeds = [b"appeal", contract.key().as_ref(), manager.key().as_ref()],
+        bump
+    )]
+    pub appeal: Account<'info, Appeal>,
+    #[account(
+        mut,
+        constraint = certification_mint.mint_authority == COption::Some(contract.key())
+    )]
+    pub certification_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = certification_mint,
+        associated_token::authority = manager,
+        associated_token::token_program = token_program,
+    )]
+    pub manager_to: InterfaceAccount<'info, TokenAccount>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Program<'info, Token2022>,
+}
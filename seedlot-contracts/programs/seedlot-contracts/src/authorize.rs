@@ -0,0 +1,105 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_interface::Mint;
+
+use crate::{Contract, LegacyOffer, SeedlotContractsError};
+
+pub mod instructions {
+    use super::*;
+
+    // The user lets one manager prepare up to `lots` of their orders for this offer.
+    // Calling it again replaces the number of lots left.
+    pub fn authorize_manager(ctx: Context<AuthorizeManager>, lots: u64) -> Result<()> {
+        ctx.accounts
+            .contract
+            .verify_offer(&ctx.accounts.order_mint, ctx.accounts.legacy_offer.as_ref())?;
+        ctx.accounts.authorization.set_inner(ManagerAuthorization {
+            user: ctx.accounts.user.key(),
+            manager: ctx.accounts.manager.key(),
+            order_mint: ctx.accounts.order_mint.key(),
+            lots_remaining: lots,
+        });
+        Ok(())
+    }
+
+    // The user takes back whatever is left of an authorization.
+    pub fn revoke_authorization(_ctx: Context<RevokeAuthorization>) -> Result<()> {
+        Ok(())
+    }
+}
+
+// Seeded by [b"authorization", contract, order_mint, user, manager].
+#[account]
+pub struct ManagerAuthorization {
+    pub user: Pubkey,
+    pub manager: Pubkey,
+    pub order_mint: Pubkey,
+    pub lots_remaining: u64,
+}
+
+impl ManagerAuthorization {
+    pub const LEN: usize = 8 // Discriminator
+     + 32 // user
+     + 32 // manager
+     + 32 // order_mint
+     + 8; // lots_remaining
+
+    pub fn use_lots(&mut self, lots: u64) -> Result<()> {
+        self.lots_remaining = self
+            .lots_remaining
+            .checked_sub(lots)
+            .ok_or(SeedlotContractsError::ManagerNotAuthorized)?;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct AuthorizeManager<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        seeds = [b"contract", contract.contract_id.as_ref()],
+        bump,
+    )]
+    pub contract: Account<'info, Contract>,
+    pub manager: SystemAccount<'info>,
+    #[account(
+        mint::authority = contract,
+        mint::token_program = token_program,
+    )]
+    pub order_mint: InterfaceAccount<'info, Mint>,
+    // Only for offers added before groups, see allow_legacy_offer.
+    #[account(
+        seeds = [b"legacy_offer", contract.key().as_ref(), order_mint.key().as_ref()],
+        bump
+    )]
+    pub legacy_offer: Option<Account<'info, LegacyOffer>>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = ManagerAuthorization::LEN,
+        seeds = [
+            b"authorization",
+            contract.key().as_ref(),
+            order_mint.key().as_ref(),
+            user.key().as_ref(),
+            manager.key().as_ref(),
+        ],
+        bump
+    )]
+    pub authorization: Account<'info, ManagerAuthorization>,
+    pub token_program: Program<'info, Token2022>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeAuthorization<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        has_one = user,
+        close = user,
+    )]
+    pub authorization: Account<'info, ManagerAuthorization>,
+}
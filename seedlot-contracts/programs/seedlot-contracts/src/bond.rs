@@ -0,0 +1,128 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::Token;
+use anchor_spl::token_interface::{Mint, TokenAccount};
+
+use crate::{Contract, ManagerRecord, SeedlotContractsError};
+
+pub mod instructions {
+    use super::*;
+    use anchor_spl::token::{transfer, Transfer};
+
+    // Adds to the manager's bond, which has to cover the contract's manager_bond to prepare lots.
+    pub fn post_bond(ctx: Context<PostBond>, amount: u64) -> Result<()> {
+        ctx.accounts.manager_record.manager = ctx.accounts.manager.key();
+        transfer(
+            CpiContext::new(
+                ctx.accounts.token_program_standard.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.manager_usdc_token_account.to_account_info(),
+                    to: ctx.accounts.bond.to_account_info(),
+                    authority: ctx.accounts.manager.to_account_info(),
+                },
+            ),
+            amount,
+        )
+    }
+
+    // The bond stays locked while any of the manager's lots could still be denied or slashed.
+    pub fn withdraw_bond(ctx: Context<WithdrawBond>, amount: u64) -> Result<()> {
+        require_eq!(
+            ctx.accounts.manager_record.active_lots,
+            0,
+            SeedlotContractsError::ManagerHasOpenLots
+        );
+        transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program_standard.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.bond.to_account_info(),
+                    to: ctx.accounts.manager_usdc_token_account.to_account_info(),
+                    authority: ctx.accounts.contract.to_account_info(),
+                },
+                &[&[
+                    b"contract",
+                    ctx.accounts.contract.contract_id.as_ref(),
+                    &[ctx.bumps.contract],
+                ]],
+            ),
+            amount,
+        )
+    }
+}
+
+#[derive(Accounts)]
+pub struct PostBond<'info> {
+    #[account(mut)]
+    pub manager: Signer<'info>,
+    #[account(
+        seeds = [b"contract", contract.contract_id.as_ref()],
+        bump,
+        has_one = usdc_mint,
+    )]
+    pub contract: Account<'info, Contract>,
+    // Created here too so withdraw_bond can always check it for open lots.
+    #[account(
+        init_if_needed,
+        payer = manager,
+        space = ManagerRecord::LEN,
+        seeds = [b"manager", contract.key().as_ref(), manager.key().as_ref()],
+        bump
+    )]
+    pub manager_record: Account<'info, ManagerRecord>,
+    pub usdc_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = manager,
+        seeds = [b"bond", contract.key().as_ref(), manager.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = contract,
+        token::token_program = token_program_standard,
+    )]
+    pub bond: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = manager,
+        associated_token::token_program = token_program_standard,
+    )]
+    pub manager_usdc_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program_standard: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawBond<'info> {
+    pub manager: Signer<'info>,
+    #[account(
+        seeds = [b"contract", contract.contract_id.as_ref()],
+        bump,
+        has_one = usdc_mint,
+    )]
+    pub contract: Account<'info, Contract>,
+    #[account(
+        seeds = [b"manager", contract.key().as_ref(), manager.key().as_ref()],
+        bump
+    )]
+    pub manager_record: Account<'info, ManagerRecord>,
+    pub usdc_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [b"bond", contract.key().as_ref(), manager.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = contract,
+        token::token_program = token_program_standard,
+    )]
+    pub bond: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = manager,
+        associated_token::token_program = token_program_standard,
+    )]
+    pub manager_usdc_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program_standard: Program<'info, Token>,
+}
@@ -5,109 +5,448 @@ use anchor_spl::token_interface::{Mint, TokenAccount};
 use core::fmt;
 use solana_program::program_option::COption;
 
-use crate::{Contract, SeedlotContractsError};
+use crate::utils::{
+    burn_frozen_tokens_from, mint_frozen_tokens_to, BurnFrozenTokensFrom,
+    BurnFrozenTokensFromBumps, MintFrozenTokensTo, MintFrozenTokensToBumps,
+};
+use crate::{Contract, SeedlotContractsError, StrikePenalty, StrikePolicy};
 pub mod instructions {
-    use crate::utils::{mint_frozen_tokens_to, MintFrozenTokensTo, MintFrozenTokensToBumps};
-
     use super::*;
 
-    pub fn certify(ctx: Context<Certify>, new_tier: CertificationTier) -> Result<()> {
-        require_neq!(
-            new_tier,
-            CertificationTier::Undefined,
-            SeedlotContractsError::NoCertificationTierZero
+    pub fn certify(
+        ctx: Context<Certify>,
+        new_tier: CertificationTier,
+        evidence_hash: [u8; 32],
+        valid_for: i64,
+    ) -> Result<()> {
+        new_tier.require_certifiable()?;
+        let certification = &mut ctx.accounts.certification;
+        certification.init_from_badge(ctx.accounts.manager.key(), &ctx.accounts.manager_to);
+        require!(
+            certification.status != CertificationStatus::Decertified,
+            SeedlotContractsError::ManagerAlreadyDecertified
         );
         require_neq!(
             new_tier,
-            CertificationTier::Decertified,
-            SeedlotContractsError::CannotCertifyAboveTierFour
-        );
-        let new_tier_number = new_tier as u64;
-        let current_certification = ctx.accounts.manager_to.amount;
-        require_neq!(
-            current_certification,
-            CertificationTier::Decertified as u64,
-            SeedlotContractsError::ManagerAlreadyDecertified
+            certification.tier,
+            SeedlotContractsError::CertificationTierUnchanged
         );
-        require_eq!(
-            current_certification + 1,
-            new_tier_number,
+        // Tiers can drop to any lower one but only go up one at a time
+        require_gte!(
+            certification.tier as u8 + 1,
+            new_tier as u8,
             SeedlotContractsError::CertificationsMustIncreaseByOneTier
         );
 
-        // Mint one token to the manager's token account to show they are certified or increase their tier
-        mint_frozen_tokens_to(
-            Context::new(
-                ctx.program_id,
-                &mut MintFrozenTokensTo {
-                    authority: ctx.accounts.manager.to_account_info(),
-                    contract: ctx.accounts.contract.clone(),
-                    mint: ctx.accounts.certification_mint.to_account_info(),
-                    to: ctx.accounts.manager_to.to_account_info(),
-                    associated_token_program: ctx.accounts.associated_token_program.clone(),
-                    token_program: ctx.accounts.token_program.clone(),
-                },
-                &[],
-                MintFrozenTokensToBumps {
-                    contract: ctx.bumps.contract,
-                },
-            ),
-            1,
-        )?;
+        let now = Clock::get()?.unix_timestamp;
+        certification.set_validity(now, valid_for)?;
+        certification.tier = new_tier;
+        certification.issuer = ctx.accounts.admin.key();
+        certification.issued_at = now;
+        certification.evidence_hash = evidence_hash;
+        certification.emit_change();
 
-        Ok(())
+        ctx.accounts
+            .badge(ctx.bumps.contract)
+            .show(ctx.program_id, &ctx.accounts.certification)
     }
 
-    pub fn decertify(ctx: Context<Certify>) -> Result<()> {
-        let decertified_tier_as_u64 = CertificationTier::Decertified as u64;
-        require_neq!(
-            ctx.accounts.manager_to.amount,
-            decertified_tier_as_u64,
-            SeedlotContractsError::ManagerAlreadyDecertified
+    // Starts a new validity period at the manager's current tier, lapsed or not. Renewing before
+    // the certification lapses adds the new period to what is left of the current one.
+    pub fn renew_certification(
+        ctx: Context<Certify>,
+        evidence_hash: [u8; 32],
+        valid_for: i64,
+    ) -> Result<()> {
+        let certification = &mut ctx.accounts.certification;
+        certification.init_from_badge(ctx.accounts.manager.key(), &ctx.accounts.manager_to);
+        require!(
+            certification.status == CertificationStatus::Active
+                && certification.tier != CertificationTier::Undefined,
+            SeedlotContractsError::ManagerNotCertified
         );
-        let current_number_of_certification_tokens = ctx.accounts.manager_to.amount;
-        let number_of_tokens_needed_to_decertify =
-            decertified_tier_as_u64 - current_number_of_certification_tokens;
-
-        mint_frozen_tokens_to(
-            Context::new(
-                ctx.program_id,
-                &mut MintFrozenTokensTo {
-                    authority: ctx.accounts.manager.to_account_info(),
-                    contract: ctx.accounts.contract.clone(),
-                    mint: ctx.accounts.certification_mint.to_account_info(),
-                    to: ctx.accounts.manager_to.to_account_info(),
-                    associated_token_program: ctx.accounts.associated_token_program.clone(),
-                    token_program: ctx.accounts.token_program.clone(),
-                },
-                &[],
-                MintFrozenTokensToBumps {
-                    contract: ctx.bumps.contract,
-                },
-            ),
-            number_of_tokens_needed_to_decertify,
-        )?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let renewed_from = certification
+            .expires_at
+            .map_or(now, |expires_at| expires_at.max(now));
+        certification.set_validity(renewed_from, valid_for)?;
+        certification.issuer = ctx.accounts.admin.key();
+        certification.issued_at = now;
+        certification.evidence_hash = evidence_hash;
+        certification.emit_change();
         Ok(())
     }
+
+    pub fn decertify(ctx: Context<Certify>) -> Result<()> {
+        let certification = &mut ctx.accounts.certification;
+        certification.init_from_badge(ctx.accounts.manager.key(), &ctx.accounts.manager_to);
+        certification.decertify(ctx.accounts.admin.key())?;
+
+        ctx.accounts
+            .badge(ctx.bumps.contract)
+            .show(ctx.program_id, &ctx.accounts.certification)
+    }
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
 #[repr(u8)]
 pub enum CertificationTier {
     Undefined = 0,
-    Tier1 = 1,       // Can get up to 10 lots
-    Tier2 = 2,       // Can get up to 1,000 lots
-    Tier3 = 3,       // Can get up to 10,000 lots
-    Tier4 = 4,       // No limit
+    Tier1 = 1,       // Can get up to 10 lots by default
+    Tier2 = 2,       // Can get up to 1,000 lots by default
+    Tier3 = 3,       // Can get up to 10,000 lots by default
+    Tier4 = 4,       // No limit by default
     Decertified = 5, // Can no longer be used for certification
 }
 
+impl CertificationTier {
+    // Each contract starts with these and the admin can change them with set_lot_limits.
+    pub const DEFAULT_LOT_LIMITS: [u64; 4] = [10, 1_000, 10_000, u64::MAX];
+
+    // Only tiers 1 - 4 can be given to a manager.
+    pub fn require_certifiable(self) -> Result<()> {
+        require_neq!(
+            self,
+            CertificationTier::Undefined,
+            SeedlotContractsError::NoCertificationTierZero
+        );
+        require_neq!(
+            self,
+            CertificationTier::Decertified,
+            SeedlotContractsError::CannotCertifyAboveTierFour
+        );
+        Ok(())
+    }
+
+    // The tier below this one, which is Undefined for Tier1.
+    pub fn lower(self) -> CertificationTier {
+        match self {
+            CertificationTier::Tier2 => CertificationTier::Tier1,
+            CertificationTier::Tier3 => CertificationTier::Tier2,
+            CertificationTier::Tier4 => CertificationTier::Tier3,
+            _ => CertificationTier::Undefined,
+        }
+    }
+}
+
 impl fmt::Display for CertificationTier {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{:?}", self)
     }
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CertificationStatus {
+    Active,
+    Decertified,
+}
+
+// Why the admin denied a manager's lot. The reason code is up to the admin.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Denial {
+    pub lot_mint: Pubkey,
+    pub reason: u16,
+    pub denied_at: i64,
+}
+
+// A lower lot cap for a reinstated manager, on top of their tier's limit.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Probation {
+    pub ends_at: i64,
+    pub lot_limit: u64,
+}
+
+// A manager's certification, seeded by [b"certification", contract, manager]. It used to be read
+// off the amount of the manager's certification token, which now only mirrors it as a badge.
+#[account]
+pub struct Certification {
+    pub manager: Pubkey,
+    // Kept through decertification so there is a record of where the manager was.
+    pub tier: CertificationTier,
+    pub status: CertificationStatus,
+    pub issuer: Pubkey,
+    pub issued_at: i64,
+    // None for certifications carried over from badges, which don't lapse.
+    pub expires_at: Option<i64>,
+    // Hash of the inspection report or whatever else the tier was given for.
+    pub evidence_hash: [u8; 32],
+    // Kept through reinstatement.
+    pub decertifications: u16,
+    pub last_decertified_at: Option<i64>,
+    pub probation: Option<Probation>,
+    // Denied lots since the last penalty.
+    pub strikes: u8,
+    pub last_denial: Option<Denial>,
+}
+
+impl Certification {
+    pub const LEN: usize = 8 // Discriminator
+     + 32 // manager
+     + 1 // tier
+     + 1 // status
+     + 32 // issuer
+     + 8 // issued_at
+     + 1 + 8 // expires_at
+     + 32 // evidence_hash
+     + 2 // decertifications
+     + 1 + 8 // last_decertified_at
+     + 1 + 8 + 8 // probation
+     + 1 // strikes
+     + 1 + 32 + 2 + 8; // last_denial
+
+    // Fills in a new record from what the manager's badge said before records existed.
+    pub fn init_from_badge(&mut self, manager: Pubkey, badge: &InterfaceAccount<TokenAccount>) {
+        if self.manager != Pubkey::default() {
+            return;
+        }
+        self.manager = manager;
+        self.tier = match badge.amount {
+            1 => CertificationTier::Tier1,
+            2 => CertificationTier::Tier2,
+            3 => CertificationTier::Tier3,
+            4 => CertificationTier::Tier4,
+            _ => CertificationTier::Undefined,
+        };
+        if badge.amount >= CertificationTier::Decertified as u64 {
+            self.status = CertificationStatus::Decertified;
+            self.decertifications = 1;
+        } else {
+            self.status = CertificationStatus::Active;
+        }
+    }
+
+    // The tier the manager can prepare lots under.
+    pub fn active_tier(&self) -> Result<CertificationTier> {
+        require!(
+            self.status == CertificationStatus::Active && self.tier != CertificationTier::Undefined,
+            SeedlotContractsError::ManagerNotCertified
+        );
+        if let Some(expires_at) = self.expires_at {
+            require_gt!(
+                expires_at,
+                Clock::get()?.unix_timestamp,
+                SeedlotContractsError::CertificationLapsed
+            );
+        }
+        Ok(self.tier)
+    }
+
+    // The most lots the manager can have active given their tier's limit.
+    pub fn lot_limit(&self, tier_limit: u64) -> Result<u64> {
+        match self.probation {
+            Some(probation) if probation.ends_at > Clock::get()?.unix_timestamp => {
+                Ok(tier_limit.min(probation.lot_limit))
+            }
+            _ => Ok(tier_limit),
+        }
+    }
+
+    // Valid for valid_for seconds from `from`.
+    fn set_validity(&mut self, from: i64, valid_for: i64) -> Result<()> {
+        require_gt!(valid_for, 0, SeedlotContractsError::InvalidValidityPeriod);
+        self.expires_at = Some(
+            from.checked_add(valid_for)
+                .ok_or(SeedlotContractsError::InvalidValidityPeriod)?,
+        );
+        Ok(())
+    }
+
+    pub fn decertify(&mut self, issuer: Pubkey) -> Result<()> {
+        require!(
+            self.status != CertificationStatus::Decertified,
+            SeedlotContractsError::ManagerAlreadyDecertified
+        );
+        let now = Clock::get()?.unix_timestamp;
+        self.status = CertificationStatus::Decertified;
+        self.issuer = issuer;
+        self.issued_at = now;
+        self.decertifications += 1;
+        self.last_decertified_at = Some(now);
+        self.emit_change();
+        Ok(())
+    }
+
+    pub fn reinstate(
+        &mut self,
+        tier: CertificationTier,
+        issuer: Pubkey,
+        evidence_hash: [u8; 32],
+        valid_for: i64,
+        probation: Option<Probation>,
+    ) -> Result<()> {
+        tier.require_certifiable()?;
+        require!(
+            self.status == CertificationStatus::Decertified,
+            SeedlotContractsError::ManagerNotDecertified
+        );
+        let now = Clock::get()?.unix_timestamp;
+        if let Some(probation) = probation {
+            require_gt!(
+                probation.ends_at,
+                now,
+                SeedlotContractsError::InvalidProbation
+            );
+        }
+        self.set_validity(now, valid_for)?;
+        self.status = CertificationStatus::Active;
+        self.tier = tier;
+        self.issuer = issuer;
+        self.issued_at = now;
+        self.evidence_hash = evidence_hash;
+        self.probation = probation;
+        self.strikes = 0;
+        self.emit_change();
+        Ok(())
+    }
+
+    // Adds a strike for a denied lot and penalizes the manager once they have enough of them.
+    pub fn strike(&mut self, policy: &StrikePolicy, issuer: Pubkey, denial: Denial) -> Result<()> {
+        self.last_denial = Some(denial);
+        self.strikes = self.strikes.saturating_add(1);
+        emit!(LotDenied {
+            manager: self.manager,
+            lot_mint: denial.lot_mint,
+            reason: denial.reason,
+            strikes: self.strikes,
+        });
+        // Already decertified managers have nothing left to lose
+        if self.strikes < policy.strikes_per_penalty
+            || self.status == CertificationStatus::Decertified
+        {
+            return Ok(());
+        }
+
+        self.strikes = 0;
+        match (policy.penalty, self.tier.lower()) {
+            (StrikePenalty::DropTier, lower) if lower != CertificationTier::Undefined => {
+                self.tier = lower;
+                self.issuer = issuer;
+                self.issued_at = denial.denied_at;
+                self.emit_change();
+                Ok(())
+            }
+            _ => self.decertify(issuer),
+        }
+    }
+
+    // What the badge should hold: the tier, or 5 once decertified.
+    pub fn badge_amount(&self) -> u64 {
+        match self.status {
+            CertificationStatus::Active => self.tier as u64,
+            CertificationStatus::Decertified => CertificationTier::Decertified as u64,
+        }
+    }
+
+    // Records the change in the transaction logs, which is where the history is kept.
+    pub fn emit_change(&self) {
+        emit!(CertificationChanged {
+            manager: self.manager,
+            tier: self.tier,
+            status: self.status,
+            issuer: self.issuer,
+            issued_at: self.issued_at,
+            expires_at: self.expires_at,
+            evidence_hash: self.evidence_hash,
+            decertifications: self.decertifications,
+            probation: self.probation,
+        });
+    }
+}
+
+#[event]
+pub struct CertificationChanged {
+    pub manager: Pubkey,
+    pub tier: CertificationTier,
+    pub status: CertificationStatus,
+    pub issuer: Pubkey,
+    pub issued_at: i64,
+    pub expires_at: Option<i64>,
+    pub evidence_hash: [u8; 32],
+    pub decertifications: u16,
+    pub probation: Option<Probation>,
+}
+
+#[event]
+pub struct LotDenied {
+    pub manager: Pubkey,
+    pub lot_mint: Pubkey,
+    pub reason: u16,
+    pub strikes: u8,
+}
+
+// The manager's soulbound certification token, kept in line with their Certification.
+pub struct Badge<'info> {
+    pub manager: AccountInfo<'info>,
+    pub contract: Account<'info, Contract>,
+    pub contract_bump: u8,
+    pub certification_mint: InterfaceAccount<'info, Mint>,
+    pub token_account: InterfaceAccount<'info, TokenAccount>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Program<'info, Token2022>,
+}
+
+impl<'info> Badge<'info> {
+    pub fn show(&self, program_id: &Pubkey, certification: &Certification) -> Result<()> {
+        let amount = certification.badge_amount();
+        let current_amount = self.token_account.amount;
+        if amount > current_amount {
+            mint_frozen_tokens_to(
+                Context::new(
+                    program_id,
+                    &mut MintFrozenTokensTo {
+                        authority: self.manager.clone(),
+                        contract: self.contract.clone(),
+                        mint: self.certification_mint.to_account_info(),
+                        to: self.token_account.to_account_info(),
+                        associated_token_program: self.associated_token_program.clone(),
+                        token_program: self.token_program.clone(),
+                    },
+                    &[],
+                    MintFrozenTokensToBumps {
+                        contract: self.contract_bump,
+                    },
+                ),
+                amount - current_amount,
+            )?;
+        } else if amount < current_amount {
+            burn_frozen_tokens_from(
+                Context::new(
+                    program_id,
+                    &mut BurnFrozenTokensFrom {
+                        authority: self.manager.clone(),
+                        contract: self.contract.clone(),
+                        mint: self.certification_mint.clone(),
+                        from: self.token_account.clone(),
+                        associated_token_program: self.associated_token_program.clone(),
+                        token_program: self.token_program.clone(),
+                    },
+                    &[],
+                    BurnFrozenTokensFromBumps {
+                        contract: self.contract_bump,
+                    },
+                ),
+                current_amount - amount,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<'info> Certify<'info> {
+    fn badge(&self, contract_bump: u8) -> Badge<'info> {
+        Badge {
+            manager: self.manager.to_account_info(),
+            contract: self.contract.clone(),
+            contract_bump,
+            certification_mint: self.certification_mint.clone(),
+            token_account: self.manager_to.clone(),
+            associated_token_program: self.associated_token_program.clone(),
+            token_program: self.token_program.clone(),
+        }
+    }
+}
+
 #[derive(Accounts)]
 pub struct Certify<'info> {
     #[account(mut)]
@@ -118,10 +457,19 @@ pub struct Certify<'info> {
     pub manager: SystemAccount<'info>,
     #[account(
         mut,
-        seeds = [b"contract", admin.key().as_ref()],
-        bump
+        seeds = [b"contract", contract.contract_id.as_ref()],
+        bump,
+        has_one = admin,
     )]
     pub contract: Account<'info, Contract>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = Certification::LEN,
+        seeds = [b"certification", contract.key().as_ref(), manager.key().as_ref()],
+        bump
+    )]
+    pub certification: Account<'info, Certification>,
     #[account(
         mut,
         constraint = certification_mint.mint_authority == COption::Some(contract.key())
@@ -0,0 +1,287 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_interface::Mint;
+
+use crate::{Contract, LegacyOffer, SeedlotContractsError};
+
+pub mod instructions {
+    use super::*;
+
+    // Lot limits are indexed by certification tier, Tier1 first.
+    pub fn set_lot_limits(ctx: Context<UpdateContract>, lot_limits: [u64; 4]) -> Result<()> {
+        ctx.accounts.contract.lot_limits = lot_limits;
+        Ok(())
+    }
+
+    pub fn update_config(ctx: Context<UpdateContract>, fee_config: FeeConfig) -> Result<()> {
+        fee_config.validate()?;
+        ctx.accounts.contract.fee_config = fee_config;
+        Ok(())
+    }
+
+    pub fn set_manager_bond(ctx: Context<UpdateContract>, manager_bond: u64) -> Result<()> {
+        ctx.accounts.contract.manager_bond = manager_bond;
+        Ok(())
+    }
+
+    pub fn set_strike_policy(
+        ctx: Context<UpdateContract>,
+        strike_policy: StrikePolicy,
+    ) -> Result<()> {
+        strike_policy.validate()?;
+        ctx.accounts.contract.strike_policy = strike_policy;
+        Ok(())
+    }
+
+    // The arbitrator can't be the admin, since they rule on the admin's denials, and can't be
+    // changed while any of those denials are still in dispute.
+    pub fn set_arbitrator(
+        ctx: Context<UpdateContract>,
+        arbitrator: Option<Pubkey>,
+        contest_window: i64,
+    ) -> Result<()> {
+        require!(
+            arbitrator != Some(ctx.accounts.admin.key()),
+            SeedlotContractsError::ArbitratorCannotBeAdmin
+        );
+        require!(
+            arbitrator == ctx.accounts.contract.arbitrator
+                || ctx.accounts.contract.open_disputes == 0,
+            SeedlotContractsError::DisputesOpen
+        );
+        require_gt!(
+            contest_window,
+            0,
+            SeedlotContractsError::InvalidContestWindow
+        );
+        let contract = &mut ctx.accounts.contract;
+        contract.arbitrator = arbitrator;
+        contract.contest_window = contest_window;
+        Ok(())
+    }
+
+    pub fn set_max_expense_share(ctx: Context<UpdateContract>, max_expense_bps: u16) -> Result<()> {
+        require_gte!(
+            BPS_DENOMINATOR,
+            max_expense_bps,
+            SeedlotContractsError::InvalidExpenseCap
+        );
+        ctx.accounts.contract.max_expense_bps = max_expense_bps;
+        Ok(())
+    }
+
+    // Lots keep the schedule of the offer they were prepared from.
+    pub fn set_payout_schedule(
+        ctx: Context<UpdateOfferConfig>,
+        payout_schedule: Vec<u16>,
+    ) -> Result<()> {
+        ctx.accounts
+            .contract
+            .verify_offer(&ctx.accounts.order_mint, ctx.accounts.legacy_offer.as_ref())?;
+        let offer_config = &mut ctx.accounts.offer_config;
+        offer_config.order_mint = ctx.accounts.order_mint.key();
+        offer_config.set_payout_schedule(payout_schedule)
+    }
+
+    // Passing None makes the offer go back to the contract's fees.
+    pub fn update_offer_config(
+        ctx: Context<UpdateOfferConfig>,
+        fee_config: Option<FeeConfig>,
+    ) -> Result<()> {
+        ctx.accounts
+            .contract
+            .verify_offer(&ctx.accounts.order_mint, ctx.accounts.legacy_offer.as_ref())?;
+        ctx.accounts
+            .offer_config
+            .set(ctx.accounts.order_mint.key(), fee_config)
+    }
+}
+
+const BPS_DENOMINATOR: u16 = 10_000;
+pub const MAX_MILESTONES: usize = 4;
+
+// All in basis points. The manager gets upfront_fee_bps of the lot's price when preparing it and
+// the rest once the admin confirms it. Harvest profit is split between user, admin and manager.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FeeConfig {
+    pub upfront_fee_bps: u16,
+    pub user_profit_bps: u16,
+    pub admin_profit_bps: u16,
+    pub manager_profit_bps: u16,
+}
+
+impl FeeConfig {
+    pub const LEN: usize = 2 * 4;
+
+    // What every lot paid before fees were configurable.
+    pub const LEGACY: FeeConfig = FeeConfig {
+        upfront_fee_bps: 1_000,
+        user_profit_bps: 5_000,
+        admin_profit_bps: 2_500,
+        manager_profit_bps: 2_500,
+    };
+
+    pub fn validate(&self) -> Result<()> {
+        require_gte!(
+            BPS_DENOMINATOR,
+            self.upfront_fee_bps,
+            SeedlotContractsError::InvalidFeeConfig
+        );
+        require_eq!(
+            self.user_profit_bps as u32
+                + self.admin_profit_bps as u32
+                + self.manager_profit_bps as u32,
+            BPS_DENOMINATOR as u32,
+            SeedlotContractsError::InvalidFeeConfig
+        );
+        Ok(())
+    }
+
+    pub fn upfront_fee(&self, total_price: u64) -> u64 {
+        apply_bps(total_price, self.upfront_fee_bps)
+    }
+
+    // (user, admin, manager)
+    pub fn split_profit(&self, profit: u64) -> (u64, u64, u64) {
+        (
+            apply_bps(profit, self.user_profit_bps),
+            apply_bps(profit, self.admin_profit_bps),
+            apply_bps(profit, self.manager_profit_bps),
+        )
+    }
+}
+
+pub(crate) fn apply_bps(amount: u64, bps: u16) -> u64 {
+    // bps is at most 10,000 so this always fits back into a u64.
+    (amount as u128 * bps as u128 / BPS_DENOMINATOR as u128) as u64
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StrikePenalty {
+    DropTier, // Managers at Tier1 are decertified instead
+    Decertify,
+}
+
+// How a manager is penalized for denied lots. Every denied lot is a strike and every
+// strikes_per_penalty strikes the penalty is applied and the count starts over.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct StrikePolicy {
+    pub strikes_per_penalty: u8,
+    pub penalty: StrikePenalty,
+}
+
+impl StrikePolicy {
+    pub const LEN: usize = 1 + 1;
+
+    // What a denied lot did before strikes were configurable.
+    pub const DEFAULT: StrikePolicy = StrikePolicy {
+        strikes_per_penalty: 1,
+        penalty: StrikePenalty::Decertify,
+    };
+
+    pub fn validate(&self) -> Result<()> {
+        require_gt!(
+            self.strikes_per_penalty,
+            0,
+            SeedlotContractsError::InvalidStrikePolicy
+        );
+        Ok(())
+    }
+}
+
+// Seeded by [b"offer_config", contract, order_mint]. Created with the offer in add_offer.
+#[account]
+pub struct OfferConfig {
+    pub order_mint: Pubkey,
+    // Overrides the contract's fees for lots prepared from this offer.
+    pub fee_config: Option<FeeConfig>,
+    // Basis points of what's left of a lot's price after the upfront fee, paid to the manager at
+    // each milestone after confirmation, in order. Whatever they leave over is paid at
+    // confirmation, so an empty schedule pays it all then.
+    pub payout_schedule: Vec<u16>,
+}
+
+impl OfferConfig {
+    pub const LEN: usize = 8 // Discriminator
+     + 32 // order_mint
+     + 1 + FeeConfig::LEN // fee_config
+     + 4 + 2 * MAX_MILESTONES; // payout_schedule
+
+    pub fn set(&mut self, order_mint: Pubkey, fee_config: Option<FeeConfig>) -> Result<()> {
+        if let Some(ref fee_config) = fee_config {
+            fee_config.validate()?;
+        }
+        self.order_mint = order_mint;
+        self.fee_config = fee_config;
+        Ok(())
+    }
+
+    pub fn set_payout_schedule(&mut self, payout_schedule: Vec<u16>) -> Result<()> {
+        require_gte!(
+            MAX_MILESTONES,
+            payout_schedule.len(),
+            SeedlotContractsError::InvalidPayoutSchedule
+        );
+        require!(
+            payout_schedule.iter().all(|bps| *bps > 0),
+            SeedlotContractsError::InvalidPayoutSchedule
+        );
+        require_gte!(
+            BPS_DENOMINATOR as u32,
+            payout_schedule.iter().map(|bps| *bps as u32).sum::<u32>(),
+            SeedlotContractsError::InvalidPayoutSchedule
+        );
+        self.payout_schedule = payout_schedule;
+        Ok(())
+    }
+
+    pub fn fee_config(&self, contract: &Contract) -> FeeConfig {
+        self.fee_config.unwrap_or(contract.fee_config)
+    }
+}
+
+#[derive(Accounts)]
+pub struct UpdateContract<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"contract", contract.contract_id.as_ref()],
+        bump,
+        has_one = admin,
+    )]
+    pub contract: Account<'info, Contract>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateOfferConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"contract", contract.contract_id.as_ref()],
+        bump,
+        has_one = admin,
+    )]
+    pub contract: Account<'info, Contract>,
+    #[account(
+        mint::authority = contract,
+        mint::token_program = token_program,
+    )]
+    pub order_mint: InterfaceAccount<'info, Mint>,
+    // Only for offers added before groups, see allow_legacy_offer.
+    #[account(
+        seeds = [b"legacy_offer", contract.key().as_ref(), order_mint.key().as_ref()],
+        bump
+    )]
+    pub legacy_offer: Option<Account<'info, LegacyOffer>>,
+    // init_if_needed so offers added before offer configs existed can get one too.
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = OfferConfig::LEN,
+        seeds = [b"offer_config", contract.key().as_ref(), order_mint.key().as_ref()],
+        bump
+    )]
+    pub offer_config: Account<'info, OfferConfig>,
+    pub token_program: Program<'info, Token2022>,
+    pub system_program: Program<'info, System>,
+}
@@ -1,204 +1,468 @@
 use anchor_lang::prelude::*;
 use anchor_spl::associated_token::AssociatedToken;
-use anchor_spl::token::Token;
-use anchor_spl::token_2022::Token2022;
+use anchor_spl::token::{self, Token};
+use anchor_spl::token_2022::{close_account, thaw_account, CloseAccount, ThawAccount, Token2022};
 use anchor_spl::token_interface::{Mint, TokenAccount};
 
-use crate::Lots;
+use crate::progress::verify_manager;
+use crate::utils::{
+    burn_frozen_tokens_from, mint_frozen_tokens_to, BurnFrozenTokensFrom,
+    BurnFrozenTokensFromBumps, MintFrozenTokensTo, MintFrozenTokensToBumps,
+};
+use crate::{Lot, LotState};
 
-use crate::{Contract, Offers, SeedlotContractsError};
+use crate::{
+    Badge, Certification, Contract, Denial, Dispute, LegacyOffer, ManagerRecord, OrderBalance,
+    OrderReceipt, SeedlotContractsError,
+};
 
 pub mod instructions {
-    use crate::certify::instructions::decertify;
-    use crate::utils::{
-        burn_frozen_tokens_from, mint_frozen_tokens_to, price_cents_2_usdc, BurnFrozenTokensFrom,
-        BurnFrozenTokensFromBumps, MintFrozenTokensTo, MintFrozenTokensToBumps,
-    };
-    use crate::{CertificationTier, Certify, CertifyBumps};
-
     use super::*;
-    use anchor_spl::token::{transfer, Transfer};
-    use anchor_spl::token_2022::{close_account, thaw_account, CloseAccount, ThawAccount};
-    use anchor_spl::token_interface::{token_metadata_update_field, TokenMetadataUpdateField};
-    use spl_token_metadata_interface::state::Field;
 
+    // denial_reason is required when the lots are denied and ignored otherwise.
     pub fn confirm_lots(
         ctx: Context<ConfirmLots>,
         confirmed: bool,
-        offer_index: u64,
-        lot_index: u64,
+        denial_reason: Option<u16>,
     ) -> Result<()> {
-        let lot = ctx.accounts.lots_account.load()?.get(lot_index)?;
-        require_eq!(
-            lot.mint.key(),
-            ctx.accounts.lot_mint.key(),
-            SeedlotContractsError::LotMintMismatch
-        );
-        ctx.accounts
-            .offers_account
-            .load()?
-            .verify_order(offer_index, ctx.accounts.order_mint.key())?;
-        let prepared_lots = ctx.accounts.lot_mint.supply;
-        let price_in_usdc = price_cents_2_usdc(&lot.original_price_per_tree);
-        let total_price = price_in_usdc * ctx.accounts.contract.trees_per_lot * prepared_lots;
+        ctx.accounts.require_admin()?;
+        ctx.accounts.verify_manager()?;
+        // Only lots that are still waiting on confirmation can be confirmed or denied.
+        ctx.accounts.lot.require_state(LotState::Preparation)?;
+        ctx.accounts.contract.verify_offer(
+            &ctx.accounts.order_mint,
+            ctx.accounts.legacy_offer.as_deref(),
+        )?;
 
         if confirmed {
-            let remaining_fee = total_price * 9 / 10; // 90% of the total price. TODO(tech): make this configurable
-            transfer(
-                CpiContext::new_with_signer(
-                    ctx.accounts.token_program_standard.to_account_info(),
-                    Transfer {
-                        from: ctx.accounts.contract_usdc_token_account.to_account_info(),
-                        to: ctx.accounts.manager_usdc_token_account.to_account_info(),
-                        authority: ctx.accounts.contract.to_account_info(),
-                    },
-                    &[&[
-                        b"contract",
-                        ctx.accounts.contract.admin.as_ref(),
-                        &[ctx.bumps.contract],
-                    ]],
-                ),
-                remaining_fee,
-            )?;
-
-            // Now we update the lot's metadata
-            token_metadata_update_field(
-                CpiContext::new_with_signer(
-                    ctx.accounts.token_program.to_account_info(),
-                    TokenMetadataUpdateField {
-                        token_program_id: ctx.accounts.token_program.to_account_info(),
-                        metadata: ctx.accounts.lot_mint.to_account_info(),
-                        update_authority: ctx.accounts.contract.to_account_info(),
-                    },
-                    &[&[
-                        b"contract",
-                        ctx.accounts.contract.admin.as_ref(),
-                        &[ctx.bumps.contract],
-                    ]],
-                ),
-                Field::Key("state".to_string()),
-                "1".to_string(),
-            )?;
-
-            thaw_account(CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                ThawAccount {
-                    account: ctx.accounts.user_lot_token_account.to_account_info(),
-                    mint: ctx.accounts.lot_mint.to_account_info(),
-                    authority: ctx.accounts.contract.to_account_info(),
-                },
-                &[&[
-                    b"contract",
-                    ctx.accounts.contract.admin.as_ref(),
-                    &[ctx.bumps.contract],
-                ]],
-            ))?;
+            ctx.accounts.confirm(ctx.bumps.contract)
         } else {
-            // Decertify the manager
-            let decertified_tier_as_u64 = CertificationTier::Decertified as u64;
-            require_neq!(
-                ctx.accounts.manager_certification_token_account.amount,
-                decertified_tier_as_u64,
-                SeedlotContractsError::ManagerAlreadyDecertified
+            // Contracts with an arbitrator let managers contest denials with open_denial instead
+            require!(
+                ctx.accounts.contract.arbitrator.is_none(),
+                SeedlotContractsError::DenialMustBeDisputed
             );
-            let current_number_of_certification_tokens =
-                ctx.accounts.manager_certification_token_account.amount;
-            let number_of_tokens_needed_to_decertify =
-                decertified_tier_as_u64 - current_number_of_certification_tokens;
+            let reason = denial_reason.ok_or(SeedlotContractsError::MissingDenialReason)?;
+            ctx.accounts
+                .deny(ctx.program_id, reason, ctx.bumps.contract)
+        }
+    }
 
-            mint_frozen_tokens_to(
-                Context::new(
-                    ctx.program_id,
-                    &mut MintFrozenTokensTo {
-                        authority: ctx.accounts.manager.to_account_info(),
-                        contract: *ctx.accounts.contract.clone(),
-                        mint: ctx.accounts.certification_mint.to_account_info(),
-                        to: ctx
-                            .accounts
-                            .manager_certification_token_account
-                            .to_account_info(),
-                        associated_token_program: ctx.accounts.associated_token_program.clone(),
-                        token_program: ctx.accounts.token_program.clone(),
-                    },
-                    &[],
-                    MintFrozenTokensToBumps {
-                        contract: ctx.bumps.contract,
-                    },
-                ),
-                number_of_tokens_needed_to_decertify,
+    // Confirms only the trees that survived. The manager is paid for those, and the user gets the
+    // rest back as order tokens for every whole lot lost and in USDC for what's left, or all in
+    // USDC when refund_usdc is set.
+    pub fn confirm_survivors(
+        ctx: Context<ConfirmLots>,
+        surviving_trees: u64,
+        refund_usdc: bool,
+    ) -> Result<()> {
+        ctx.accounts.require_admin()?;
+        ctx.accounts.verify_manager()?;
+        ctx.accounts.lot.require_state(LotState::Preparation)?;
+        ctx.accounts.contract.verify_offer(
+            &ctx.accounts.order_mint,
+            ctx.accounts.legacy_offer.as_deref(),
+        )?;
+        let prepared_lots = Lot::lots_in(&ctx.accounts.lot_mint);
+        let trees_per_lot = ctx.accounts.lot.trees_per_lot;
+        let prepared_trees = prepared_lots * trees_per_lot;
+        // Lots with no survivors should be denied
+        require!(
+            surviving_trees > 0 && surviving_trees <= prepared_trees,
+            SeedlotContractsError::InvalidSurvivorCount
+        );
+        // Every lost tree burns its share of a lot's units, which lot mints from before units
+        // only have for whole lots
+        let lost_trees = prepared_trees - surviving_trees;
+        let units_per_lot = Lot::units_per_lot(&ctx.accounts.lot_mint);
+        require!(
+            (lost_trees * units_per_lot).checked_rem(trees_per_lot) == Some(0),
+            SeedlotContractsError::InvalidSurvivorCount
+        );
+        let lost_units = lost_trees * units_per_lot / trees_per_lot;
+        let total_price = ctx.accounts.lot.price(prepared_lots);
+        let surviving_price = ctx.accounts.lot.price_of_trees(surviving_trees);
+
+        // The manager keeps the upfront fee on the surviving lots only
+        if ctx.accounts.lot.fee_escrowed {
+            let returned = ctx.accounts.lot.fee_config.upfront_fee(total_price)
+                - ctx.accounts.lot.fee_config.upfront_fee(surviving_price);
+            ctx.accounts.transfer_from_contract(
+                ctx.accounts
+                    .lot_escrow
+                    .as_ref()
+                    .ok_or(SeedlotContractsError::MissingLotEscrow)?
+                    .to_account_info(),
+                ctx.accounts.contract_usdc_token_account.to_account_info(),
+                returned,
+                ctx.bumps.contract,
             )?;
+            ctx.accounts.close_lot_escrow(
+                ctx.accounts.manager_usdc_token_account.to_account_info(),
+                ctx.bumps.contract,
+            )?;
+        } else {
+            ctx.accounts
+                .slash_upfront_fee(total_price - surviving_price, ctx.bumps.contract)?;
+        }
+        let paid_now = ctx.accounts.lot.start_payouts(surviving_price);
+        ctx.accounts.pay_manager(paid_now, ctx.bumps.contract)?;
 
-            // Return 10% to the contract's USDC account
-            let return_manager_fee = total_price / 10; // 10% of the total price
-            transfer(
-                CpiContext::new(
-                    ctx.accounts.token_program_standard.to_account_info(),
-                    Transfer {
-                        from: ctx.accounts.admin_usdc_token_account.to_account_info(),
-                        to: ctx.accounts.contract_usdc_token_account.to_account_info(),
-                        authority: ctx.accounts.admin.to_account_info(),
-                    },
-                ),
-                return_manager_fee,
+        // Lots without a surviving tree no longer count against the manager's tier
+        let lost_lots = prepared_lots - surviving_trees.div_ceil(trees_per_lot);
+        ctx.accounts.manager_record.remove_lots(lost_lots);
+        // Order tokens are whole lots, so trees short of one are refunded in USDC
+        let reminted_orders = if refund_usdc {
+            0
+        } else {
+            lost_trees / trees_per_lot
+        };
+        let refunded_usdc = total_price - surviving_price - ctx.accounts.lot.price(reminted_orders);
+        if lost_units > 0 {
+            ctx.accounts
+                .burn_user_lots(ctx.program_id, lost_units, ctx.bumps.contract)?;
+        }
+        if reminted_orders > 0 {
+            ctx.accounts
+                .remint_orders(ctx.program_id, reminted_orders, ctx.bumps.contract)?;
+        }
+        if refunded_usdc > 0 {
+            let user_usdc_token_account = ctx
+                .accounts
+                .user_usdc_token_account
+                .as_ref()
+                .ok_or(ErrorCode::AccountNotEnoughKeys)?;
+            ctx.accounts.transfer_from_contract(
+                ctx.accounts.contract_usdc_token_account.to_account_info(),
+                user_usdc_token_account.to_account_info(),
+                refunded_usdc,
+                ctx.bumps.contract,
             )?;
+        }
 
-            // Re-mint order tokens
-            mint_frozen_tokens_to(
-                Context::new(
-                    ctx.program_id,
-                    &mut MintFrozenTokensTo {
-                        authority: ctx.accounts.user.clone(),
-                        contract: *ctx.accounts.contract.clone(),
-                        mint: ctx.accounts.order_mint.to_account_info(),
-                        to: ctx.accounts.user_order_token_account.to_account_info(),
-                        associated_token_program: ctx.accounts.associated_token_program.clone(),
-                        token_program: ctx.accounts.token_program.clone(),
-                    },
-                    &[],
-                    MintFrozenTokensToBumps {
-                        contract: ctx.bumps.contract,
-                    },
-                ),
-                prepared_lots,
+        ctx.accounts.lot.confirm()?;
+        ctx.accounts.thaw_user_lots(ctx.bumps.contract)?;
+
+        emit!(SurvivorsConfirmed {
+            lot_mint: ctx.accounts.lot_mint.key(),
+            surviving_trees,
+            lost_trees,
+            reminted_orders,
+            refunded_usdc,
+        });
+        Ok(())
+    }
+}
+
+#[event]
+pub struct SurvivorsConfirmed {
+    pub lot_mint: Pubkey,
+    pub surviving_trees: u64,
+    pub lost_trees: u64,
+    pub reminted_orders: u64,
+    pub refunded_usdc: u64,
+}
+
+impl<'info> ConfirmLots<'info> {
+    fn require_admin(&self) -> Result<()> {
+        require_keys_eq!(
+            self.admin.key(),
+            self.contract.admin,
+            SeedlotContractsError::AdminMismatch
+        );
+        Ok(())
+    }
+
+    // The manager has to be the one the lot mint names, or the wrong manager would be paid or
+    // struck.
+    pub(crate) fn verify_manager(&self) -> Result<()> {
+        verify_manager(&self.lot_mint, &self.manager.key())
+    }
+
+    // Pays the manager and moves the lot into the growing state.
+    pub(crate) fn confirm(&mut self, contract_bump: u8) -> Result<()> {
+        let total_price = self.lot.price(Lot::lots_in(&self.lot_mint));
+        // Release the upfront fee, unless the manager already got it when preparing the lots
+        if self.lot.fee_escrowed {
+            self.close_lot_escrow(
+                self.manager_usdc_token_account.to_account_info(),
+                contract_bump,
             )?;
+        }
+        // Pay what the offer's milestones don't hold back
+        let paid_now = self.lot.start_payouts(total_price);
+        self.pay_manager(paid_now, contract_bump)?;
 
-            // Burn lot tokens
-            burn_frozen_tokens_from(
-                Context::new(
-                    ctx.program_id,
-                    &mut BurnFrozenTokensFrom {
-                        authority: ctx.accounts.user.clone(),
-                        contract: *ctx.accounts.contract.clone(),
-                        mint: ctx.accounts.lot_mint.clone(),
-                        from: ctx.accounts.user_lot_token_account.clone(),
-                        associated_token_program: ctx.accounts.associated_token_program.clone(),
-                        token_program: ctx.accounts.token_program.clone(),
-                    },
-                    &[],
-                    BurnFrozenTokensFromBumps {
-                        contract: ctx.bumps.contract,
-                    },
-                ),
-                prepared_lots,
+        self.lot.confirm()?;
+        self.thaw_user_lots(contract_bump)
+    }
+
+    // Strikes the manager, gives the user their order tokens back and closes the lot.
+    pub(crate) fn deny(
+        &mut self,
+        program_id: &Pubkey,
+        reason: u16,
+        contract_bump: u8,
+    ) -> Result<()> {
+        let prepared_lots = Lot::lots_in(&self.lot_mint);
+        let total_price = self.lot.price(prepared_lots);
+
+        // Denied lots no longer count against the manager's tier
+        self.manager_record.remove_lots(prepared_lots);
+
+        // Give the manager a strike, which may cost them a tier or their certification
+        self.certification.init_from_badge(
+            self.manager.key(),
+            &self.manager_certification_token_account,
+        );
+        self.certification.strike(
+            &self.contract.strike_policy,
+            self.admin.key(),
+            Denial {
+                lot_mint: self.lot_mint.key(),
+                reason,
+                denied_at: Clock::get()?.unix_timestamp,
+            },
+        )?;
+        Badge {
+            manager: self.manager.to_account_info(),
+            contract: *self.contract.clone(),
+            contract_bump,
+            certification_mint: *self.certification_mint.clone(),
+            token_account: *self.manager_certification_token_account.clone(),
+            associated_token_program: self.associated_token_program.clone(),
+            token_program: self.token_program.clone(),
+        }
+        .show(program_id, &self.certification)?;
+
+        // Return the upfront fee to the contract's USDC account
+        if self.lot.fee_escrowed {
+            self.close_lot_escrow(
+                self.contract_usdc_token_account.to_account_info(),
+                contract_bump,
             )?;
+        } else {
+            // The manager was already paid, so slash it from their bond. The admin only
+            // covers what the bond can't.
+            self.slash_upfront_fee(total_price, contract_bump)?;
+        }
 
-            // Close lot mint
-            close_account(CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                CloseAccount {
-                    account: ctx.accounts.lot_mint.to_account_info(),
-                    destination: ctx.accounts.contract.to_account_info(),
-                    authority: ctx.accounts.contract.to_account_info(),
+        // Re-mint order tokens and burn lot tokens
+        self.remint_orders(program_id, prepared_lots, contract_bump)?;
+        self.burn_user_lots(
+            program_id,
+            prepared_lots * Lot::units_per_lot(&self.lot_mint),
+            contract_bump,
+        )?;
+
+        // Close lot mint
+        close_account(CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            CloseAccount {
+                account: self.lot_mint.to_account_info(),
+                destination: self.contract.to_account_info(),
+                authority: self.contract.to_account_info(),
+            },
+            &[&[
+                b"contract",
+                self.contract.contract_id.as_ref(),
+                &[contract_bump],
+            ]],
+        ))?;
+
+        // Close the lot's record
+        self.lot.close(self.contract.to_account_info())
+    }
+
+    fn transfer_from_contract(
+        &self,
+        from: AccountInfo<'info>,
+        to: AccountInfo<'info>,
+        amount: u64,
+        contract_bump: u8,
+    ) -> Result<()> {
+        token::transfer(
+            CpiContext::new_with_signer(
+                self.token_program_standard.to_account_info(),
+                token::Transfer {
+                    from,
+                    to,
+                    authority: self.contract.to_account_info(),
                 },
                 &[&[
                     b"contract",
-                    ctx.accounts.contract.admin.as_ref(),
-                    &[ctx.bumps.contract],
+                    self.contract.contract_id.as_ref(),
+                    &[contract_bump],
                 ]],
-            ))?;
+            ),
+            amount,
+        )
+    }
+
+    fn pay_manager(&self, amount: u64, contract_bump: u8) -> Result<()> {
+        self.transfer_from_contract(
+            self.contract_usdc_token_account.to_account_info(),
+            self.manager_usdc_token_account.to_account_info(),
+            amount,
+            contract_bump,
+        )
+    }
+
+    // Lets the user move their lot tokens once the lot is growing.
+    fn thaw_user_lots(&self, contract_bump: u8) -> Result<()> {
+        thaw_account(CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            ThawAccount {
+                account: self.user_lot_token_account.to_account_info(),
+                mint: self.lot_mint.to_account_info(),
+                authority: self.contract.to_account_info(),
+            },
+            &[&[
+                b"contract",
+                self.contract.contract_id.as_ref(),
+                &[contract_bump],
+            ]],
+        ))
+    }
+
+    // Gives the user back order tokens for lots they no longer get, along with the receipt they
+    // were settled against.
+    fn remint_orders(
+        &mut self,
+        program_id: &Pubkey,
+        quantity: u64,
+        contract_bump: u8,
+    ) -> Result<()> {
+        if let Some(order_id) = self.lot.order_id {
+            let receipt = self
+                .receipt
+                .as_mut()
+                .ok_or(SeedlotContractsError::OrderReceiptMismatch)?;
+            require_eq!(
+                receipt.order_id,
+                order_id,
+                SeedlotContractsError::OrderReceiptMismatch
+            );
+            receipt.restore(quantity)?;
+            self.order_balance
+                .as_mut()
+                .ok_or(ErrorCode::AccountNotEnoughKeys)?
+                .restore(quantity)?;
+        }
+        mint_frozen_tokens_to(
+            Context::new(
+                program_id,
+                &mut MintFrozenTokensTo {
+                    authority: self.user.clone(),
+                    contract: *self.contract.clone(),
+                    mint: self.order_mint.to_account_info(),
+                    to: self.user_order_token_account.to_account_info(),
+                    associated_token_program: self.associated_token_program.clone(),
+                    token_program: self.token_program.clone(),
+                },
+                &[],
+                MintFrozenTokensToBumps {
+                    contract: contract_bump,
+                },
+            ),
+            quantity,
+        )
+    }
+
+    fn burn_user_lots(&self, program_id: &Pubkey, units: u64, contract_bump: u8) -> Result<()> {
+        burn_frozen_tokens_from(
+            Context::new(
+                program_id,
+                &mut BurnFrozenTokensFrom {
+                    authority: self.user.clone(),
+                    contract: *self.contract.clone(),
+                    mint: self.lot_mint.clone(),
+                    from: self.user_lot_token_account.clone(),
+                    associated_token_program: self.associated_token_program.clone(),
+                    token_program: self.token_program.clone(),
+                },
+                &[],
+                BurnFrozenTokensFromBumps {
+                    contract: contract_bump,
+                },
+            ),
+            units,
+        )
+    }
+
+    // Empties the lot's escrow into `to` and closes it.
+    fn close_lot_escrow(&self, to: AccountInfo<'info>, contract_bump: u8) -> Result<()> {
+        let lot_escrow = self
+            .lot_escrow
+            .as_ref()
+            .ok_or(SeedlotContractsError::MissingLotEscrow)?;
+        let bump = [contract_bump];
+        let signer_seeds: &[&[&[u8]]] =
+            &[&[b"contract", self.contract.contract_id.as_ref(), &bump]];
+        token::transfer(
+            CpiContext::new_with_signer(
+                self.token_program_standard.to_account_info(),
+                token::Transfer {
+                    from: lot_escrow.to_account_info(),
+                    to,
+                    authority: self.contract.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            lot_escrow.amount,
+        )?;
+        token::close_account(CpiContext::new_with_signer(
+            self.token_program_standard.to_account_info(),
+            token::CloseAccount {
+                account: lot_escrow.to_account_info(),
+                destination: self.contract.to_account_info(),
+                authority: self.contract.to_account_info(),
+            },
+            signer_seeds,
+        ))
+    }
 
-            // Remove the lot from the lots account
-            ctx.accounts.lots_account.load_mut()?.remove(lot_index)?;
+    // Takes back the upfront fee of a lot prepared before escrows.
+    fn slash_upfront_fee(&self, total_price: u64, contract_bump: u8) -> Result<()> {
+        let upfront_fee = self.lot.fee_config.upfront_fee(total_price);
+        let bump = [contract_bump];
+        let signer_seeds: &[&[&[u8]]] =
+            &[&[b"contract", self.contract.contract_id.as_ref(), &bump]];
+        let slashed = match &self.bond {
+            Some(bond) => {
+                let slashed = upfront_fee.min(bond.amount);
+                token::transfer(
+                    CpiContext::new_with_signer(
+                        self.token_program_standard.to_account_info(),
+                        token::Transfer {
+                            from: bond.to_account_info(),
+                            to: self.contract_usdc_token_account.to_account_info(),
+                            authority: self.contract.to_account_info(),
+                        },
+                        signer_seeds,
+                    ),
+                    slashed,
+                )?;
+                slashed
+            }
+            None => 0,
+        };
+        if upfront_fee > slashed {
+            let admin_usdc_token_account = self
+                .admin_usdc_token_account
+                .as_ref()
+                .ok_or(ErrorCode::AccountNotEnoughKeys)?;
+            token::transfer(
+                CpiContext::new(
+                    self.token_program_standard.to_account_info(),
+                    token::Transfer {
+                        from: admin_usdc_token_account.to_account_info(),
+                        to: self.contract_usdc_token_account.to_account_info(),
+                        authority: self.admin.to_account_info(),
+                    },
+                ),
+                upfront_fee - slashed,
+            )?;
         }
         Ok(())
     }
@@ -206,21 +470,22 @@ pub mod instructions {
 
 #[derive(Accounts)]
 pub struct ConfirmLots<'info> {
+    // The contract's admin, or the dispute's arbitrator in resolve_dispute. Each instruction
+    // checks which.
+    #[account(mut)]
     pub admin: Signer<'info>,
+    // Only needed to deny lots prepared before escrows when the manager's bond falls short.
     #[account(
         mut,
         associated_token::mint = usdc_mint,
         associated_token::authority = admin,
         associated_token::token_program = token_program_standard,
     )]
-    pub admin_usdc_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub admin_usdc_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
     #[account(
         mut,
-        seeds = [b"contract", contract.admin.as_ref()],
+        seeds = [b"contract", contract.contract_id.as_ref()],
         bump,
-        has_one = admin,
-        has_one = offers_account,
-        has_one = lots_account,
         has_one = usdc_mint,
         has_one = certification_mint,
     )]
@@ -240,6 +505,20 @@ pub struct ConfirmLots<'info> {
         associated_token::token_program = token_program,
     )]
     pub manager_certification_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = Certification::LEN,
+        seeds = [b"certification", contract.key().as_ref(), manager.key().as_ref()],
+        bump
+    )]
+    pub certification: Box<Account<'info, Certification>>,
+    #[account(
+        mut,
+        seeds = [b"manager", contract.key().as_ref(), manager.key().as_ref()],
+        bump
+    )]
+    pub manager_record: Box<Account<'info, ManagerRecord>>,
     pub usdc_mint: Box<InterfaceAccount<'info, Mint>>,
     #[account(
         mut,
@@ -249,21 +528,53 @@ pub struct ConfirmLots<'info> {
     )]
     pub contract_usdc_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
-        mut,
+        init_if_needed,
+        payer = admin,
         associated_token::mint = usdc_mint,
         associated_token::authority = manager,
         associated_token::token_program = token_program_standard,
     )]
     pub manager_usdc_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    // Lots prepared before escrows don't have one.
+    #[account(
+        mut,
+        seeds = [b"lot_escrow", contract.key().as_ref(), lot_mint.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = contract,
+        token::token_program = token_program_standard,
+    )]
+    pub lot_escrow: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    // Managers who prepared lots before bonds existed may not have one.
+    #[account(
+        mut,
+        seeds = [b"bond", contract.key().as_ref(), manager.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = contract,
+        token::token_program = token_program_standard,
+    )]
+    pub bond: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
     pub token_program_standard: Program<'info, Token>,
     pub associated_token_program: Program<'info, AssociatedToken>,
-    #[account(mut)]
-    pub lots_account: AccountLoader<'info, Lots>,
     #[account(mut,
         mint::authority = contract,
         mint::token_program = token_program,
     )]
     pub lot_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [b"lot", contract.key().as_ref(), lot_mint.key().as_ref()],
+        bump
+    )]
+    pub lot: Box<Account<'info, Lot>>,
+    // Only needed to resolve a disputed denial.
+    #[account(
+        mut,
+        seeds = [b"dispute", contract.key().as_ref(), lot_mint.key().as_ref()],
+        bump
+    )]
+    pub dispute: Option<Box<Account<'info, Dispute>>>,
     pub token_program: Program<'info, Token2022>,
     /// CHECK: This account is used for getting the associated token addresses only.
     pub user: AccountInfo<'info>,
@@ -275,12 +586,17 @@ pub struct ConfirmLots<'info> {
     )]
     pub user_lot_token_account: InterfaceAccount<'info, TokenAccount>,
     pub system_program: Program<'info, System>,
-    pub offers_account: AccountLoader<'info, Offers>,
     #[account(mut,
         mint::authority = contract,
         mint::token_program = token_program,
     )]
     pub order_mint: InterfaceAccount<'info, Mint>,
+    // Only for offers added before groups, see allow_legacy_offer.
+    #[account(
+        seeds = [b"legacy_offer", contract.key().as_ref(), order_mint.key().as_ref()],
+        bump
+    )]
+    pub legacy_offer: Option<Box<Account<'info, LegacyOffer>>>,
     #[account(
         mut,
         associated_token::mint = order_mint,
@@ -288,4 +604,31 @@ pub struct ConfirmLots<'info> {
         associated_token::token_program = token_program,
     )]
     pub user_order_token_account: InterfaceAccount<'info, TokenAccount>,
+    // Only needed to give order tokens back to the lot's receipt, see Lot::order_id.
+    #[account(
+        mut,
+        seeds = [b"order", contract.key().as_ref(), order_mint.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub order_balance: Option<Box<Account<'info, OrderBalance>>>,
+    #[account(
+        mut,
+        seeds = [
+            b"receipt",
+            contract.key().as_ref(),
+            order_mint.key().as_ref(),
+            user.key().as_ref(),
+            &receipt.order_id.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub receipt: Option<Box<Account<'info, OrderReceipt>>>,
+    // Only needed to refund lost lots in USDC.
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program_standard,
+    )]
+    pub user_usdc_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
 }
@@ -1,4 +1,9 @@
 use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use anchor_spl::token_interface::Mint;
+
+use crate::utils::{get_group, MintMetadata};
+use crate::{CertificationTier, FeeConfig, LegacyOffer, Lot, SeedlotContractsError, StrikePolicy};
 
 const DISCRIMINATOR_LENGTH: usize = 8;
 const PUBLIC_KEY_LENGTH: usize = 32;
@@ -6,24 +11,164 @@ const U64_LENGTH: usize = 8;
 
 impl Contract {
     pub const LEN: usize = DISCRIMINATOR_LENGTH
+        + PUBLIC_KEY_LENGTH // contract_id
         + PUBLIC_KEY_LENGTH // admin
+        + 1 + PUBLIC_KEY_LENGTH // pending_admin
         + U64_LENGTH // trees_per_lot
         + PUBLIC_KEY_LENGTH // certification_mint
-        + PUBLIC_KEY_LENGTH // offers_account
+        + PUBLIC_KEY_LENGTH // offers_group
+        + PUBLIC_KEY_LENGTH // lots_group
         + PUBLIC_KEY_LENGTH // usdc_mint
         + PUBLIC_KEY_LENGTH // usdc_token_account
-        + PUBLIC_KEY_LENGTH; // lots_account
+        + PUBLIC_KEY_LENGTH // legacy_lots_account
+        + PUBLIC_KEY_LENGTH // legacy_admin
+        + U64_LENGTH * 4 // lot_limits
+        + FeeConfig::LEN // fee_config
+        + StrikePolicy::LEN // strike_policy
+        + U64_LENGTH // manager_bond
+        + 1 + PUBLIC_KEY_LENGTH // arbitrator
+        + U64_LENGTH // contest_window
+        + 2 // max_expense_bps
+        + U64_LENGTH; // open_disputes
+
+    // Managers get a week to contest a denial unless the admin sets otherwise.
+    pub const DEFAULT_CONTEST_WINDOW: i64 = 7 * 24 * 60 * 60;
+    pub const DEFAULT_MAX_EXPENSE_BPS: u16 = 5_000;
 }
 
 #[account]
 pub struct Contract {
+    // The contract PDA is seeded by [b"contract", contract_id] so the admin can change.
+    pub contract_id: Pubkey,
+    pub admin: Pubkey,
+    // Set by propose_admin until the proposed admin accepts.
+    pub pending_admin: Option<Pubkey>,
+    pub trees_per_lot: u64,
+    pub certification_mint: Pubkey,
+    // Token-2022 group mints. Every order mint is a member of offers_group and every lot mint is a
+    // member of lots_group.
+    pub offers_group: Pubkey,
+    pub lots_group: Pubkey,
+    pub usdc_mint: Pubkey,
+    pub usdc_token_account: Pubkey,
+    // Lots now live in their own PDAs. Contracts from before that still point at their old lots
+    // account until every entry is moved out with `migrate_lot`; new contracts leave it empty.
+    pub legacy_lots_account: Pubkey,
+    // Contracts used to be seeded by [b"contract", admin]. Migrated contracts keep that admin
+    // here so the old PDA can sign `migrate_mint`; new contracts leave it empty.
+    pub legacy_admin: Pubkey,
+    // How many active lots a manager may hold at each certification tier, Tier1 first.
+    pub lot_limits: [u64; 4],
+    // Default fees for every offer that doesn't override them in its OfferConfig.
+    pub fee_config: FeeConfig,
+    // What happens to a manager whose lots are denied in confirm_lots.
+    pub strike_policy: StrikePolicy,
+    // USDC a manager has to keep in their bond to prepare lots. Denied lots whose upfront fee
+    // went out before escrows are slashed from it.
+    pub manager_bond: u64,
+    // Rules on denials managers contest. Without one, confirm_lots denies lots outright.
+    pub arbitrator: Option<Pubkey>,
+    // Seconds a manager has to contest a denial opened with open_denial.
+    pub contest_window: i64,
+    // The most a manager's reported expenses may take out of a harvest's gross proceeds.
+    pub max_expense_bps: u16,
+    // Disputes waiting on a ruling. The arbitrator can't change until they're all resolved.
+    pub open_disputes: u64,
+}
+
+impl Contract {
+    // Offers added before groups can't join the offers group, so they pass with the LegacyOffer
+    // the admin made for them instead.
+    pub fn verify_offer(
+        &self,
+        order_mint: &InterfaceAccount<Mint>,
+        legacy_offer: Option<&Account<LegacyOffer>>,
+    ) -> Result<()> {
+        if let Some(legacy_offer) = legacy_offer {
+            require_keys_eq!(
+                legacy_offer.order_mint,
+                order_mint.key(),
+                SeedlotContractsError::OrderMintNotFound
+            );
+            return Ok(());
+        }
+        require_keys_eq!(
+            get_group(order_mint)?,
+            self.offers_group,
+            SeedlotContractsError::OrderMintNotFound
+        );
+        Ok(())
+    }
+
+    pub fn lot_limit(&self, tier: CertificationTier) -> Result<u64> {
+        require!(
+            (CertificationTier::Tier1 as u8..=CertificationTier::Tier4 as u8)
+                .contains(&(tier as u8)),
+            SeedlotContractsError::ManagerNotCertified
+        );
+        Ok(self.lot_limits[tier as usize - 1])
+    }
+
+    // confirm_survivors burns a whole number of units for every lost tree, so a lot's units have
+    // to split evenly between its trees.
+    pub fn validate_trees_per_lot(trees_per_lot: u64) -> Result<()> {
+        require!(
+            Lot::UNITS_PER_LOT.checked_rem(trees_per_lot) == Some(0),
+            SeedlotContractsError::InvalidTreesPerLot
+        );
+        Ok(())
+    }
+}
+
+pub fn offers_group_metadata() -> MintMetadata {
+    MintMetadata {
+        name: "Seedlot Offers".to_string(),
+        symbol: "SLO".to_string(),
+        uri: "https://app.seedlot.io/offers".to_string(),
+        location_variety_price: None,
+        manager_for_lot: None,
+    }
+}
+
+pub fn lots_group_metadata() -> MintMetadata {
+    MintMetadata {
+        name: "Seedlot Lots".to_string(),
+        symbol: "SLL".to_string(),
+        uri: "https://app.seedlot.io/lots".to_string(),
+        location_variety_price: None,
+        manager_for_lot: None,
+    }
+}
+
+// The layout of contracts seeded by [b"contract", admin], only read by `migrate_contract`.
+#[derive(AnchorDeserialize)]
+pub struct LegacyContract {
     pub admin: Pubkey,
     pub trees_per_lot: u64,
     pub certification_mint: Pubkey,
-    // TODO(techiejd): Change to group https://solana.com/developers/courses/token-extensions/group-member
     pub offers_account: Pubkey,
     pub usdc_mint: Pubkey,
     pub usdc_token_account: Pubkey,
-    // TODO(techiejd): Change to group https://solana.com/developers/courses/token-extensions/group-member
     pub lots_account: Pubkey,
 }
+
+impl LegacyContract {
+    pub const LEN: usize = DISCRIMINATOR_LENGTH
+        + PUBLIC_KEY_LENGTH // admin
+        + U64_LENGTH // trees_per_lot
+        + PUBLIC_KEY_LENGTH // certification_mint
+        + PUBLIC_KEY_LENGTH // offers_account
+        + PUBLIC_KEY_LENGTH // usdc_mint
+        + PUBLIC_KEY_LENGTH // usdc_token_account
+        + PUBLIC_KEY_LENGTH; // lots_account
+
+    pub fn try_from_account_info(account_info: &AccountInfo) -> Result<Self> {
+        let data = account_info.try_borrow_data()?;
+        // The length tells a legacy contract apart from a current one at the same address.
+        require!(
+            data.len() == Self::LEN && data[..DISCRIMINATOR_LENGTH] == Contract::DISCRIMINATOR,
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+        Self::deserialize(&mut &data[DISCRIMINATOR_LENGTH..]).map_err(Into::into)
+    }
+}
@@ -0,0 +1,191 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_interface::Mint;
+
+use crate::progress::verify_manager;
+use crate::{ConfirmLots, Contract, Lot, LotState, SeedlotContractsError};
+
+pub mod instructions {
+    use super::*;
+
+    // Denies the lot pending the arbitrator's ruling. The lot's tokens and escrow stay frozen
+    // until resolve_dispute settles them.
+    pub fn open_denial(
+        ctx: Context<OpenDenial>,
+        reason: u16,
+        evidence_hash: [u8; 32],
+    ) -> Result<()> {
+        let contract = &mut ctx.accounts.contract;
+        let arbitrator = contract
+            .arbitrator
+            .ok_or(SeedlotContractsError::ArbitratorNotSet)?;
+        verify_manager(&ctx.accounts.lot_mint, &ctx.accounts.manager.key())?;
+        ctx.accounts.lot.transition_to(LotState::Disputed)?;
+        contract.open_disputes += 1;
+
+        let opened_at = Clock::get()?.unix_timestamp;
+        ctx.accounts.dispute.set_inner(Dispute {
+            lot_mint: ctx.accounts.lot_mint.key(),
+            manager: ctx.accounts.manager.key(),
+            arbitrator,
+            reason,
+            evidence_hash,
+            opened_at,
+            contest_deadline: opened_at + contract.contest_window,
+            manager_evidence_hash: None,
+        });
+        Ok(())
+    }
+
+    // Contesting again before the deadline replaces the manager's evidence.
+    pub fn contest_denial(ctx: Context<ContestDenial>, evidence_hash: [u8; 32]) -> Result<()> {
+        let dispute = &mut ctx.accounts.dispute;
+        require_gt!(
+            dispute.contest_deadline,
+            Clock::get()?.unix_timestamp,
+            SeedlotContractsError::ContestWindowClosed
+        );
+        dispute.manager_evidence_hash = Some(evidence_hash);
+        Ok(())
+    }
+
+    // Upholding the denial settles the lot as confirm_lots would have denied it, overturning it
+    // confirms the lot. Uncontested denials can only be ruled on once the contest window ends.
+    // Only the arbitrator the dispute was opened with can rule on it.
+    pub fn resolve_dispute(ctx: Context<ConfirmLots>, upheld: bool) -> Result<()> {
+        ctx.accounts.lot.require_state(LotState::Disputed)?;
+        ctx.accounts.contract.verify_offer(
+            &ctx.accounts.order_mint,
+            ctx.accounts.legacy_offer.as_deref(),
+        )?;
+        let dispute = ctx
+            .accounts
+            .dispute
+            .take()
+            .ok_or(SeedlotContractsError::MissingDispute)?;
+        require_keys_eq!(
+            ctx.accounts.admin.key(),
+            dispute.arbitrator,
+            SeedlotContractsError::NotArbitrator
+        );
+        require_keys_eq!(
+            ctx.accounts.manager.key(),
+            dispute.manager,
+            SeedlotContractsError::ManagerMismatch
+        );
+        ctx.accounts.verify_manager()?;
+        require!(
+            dispute.manager_evidence_hash.is_some()
+                || Clock::get()?.unix_timestamp >= dispute.contest_deadline,
+            SeedlotContractsError::ContestWindowOpen
+        );
+
+        let contract = &mut ctx.accounts.contract;
+        contract.open_disputes = contract
+            .open_disputes
+            .checked_sub(1)
+            .ok_or(SeedlotContractsError::MissingDispute)?;
+
+        if upheld {
+            ctx.accounts
+                .deny(ctx.program_id, dispute.reason, ctx.bumps.contract)?;
+        } else {
+            ctx.accounts.confirm(ctx.bumps.contract)?;
+        }
+
+        emit!(DisputeResolved {
+            lot_mint: dispute.lot_mint,
+            manager: dispute.manager,
+            upheld,
+            arbitrator: dispute.arbitrator,
+        });
+        dispute.close(ctx.accounts.contract.to_account_info())
+    }
+}
+
+// A denied lot waiting on the arbitrator, seeded by [b"dispute", contract, lot_mint].
+#[account]
+pub struct Dispute {
+    pub lot_mint: Pubkey,
+    pub manager: Pubkey,
+    // Whoever was the contract's arbitrator when the denial was opened.
+    pub arbitrator: Pubkey,
+    pub reason: u16,
+    // The admin's evidence for the denial.
+    pub evidence_hash: [u8; 32],
+    pub opened_at: i64,
+    pub contest_deadline: i64,
+    pub manager_evidence_hash: Option<[u8; 32]>,
+}
+
+impl Dispute {
+    pub const LEN: usize = 8 // Discriminator
+     + 32 // lot_mint
+     + 32 // manager
+     + 32 // arbitrator
+     + 2 // reason
+     + 32 // evidence_hash
+     + 8 // opened_at
+     + 8 // contest_deadline
+     + 1 + 32; // manager_evidence_hash
+}
+
+#[event]
+pub struct DisputeResolved {
+    pub lot_mint: Pubkey,
+    pub manager: Pubkey,
+    pub upheld: bool,
+    pub arbitrator: Pubkey,
+}
+
+#[derive(Accounts)]
+pub struct OpenDenial<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"contract", contract.contract_id.as_ref()],
+        bump,
+        has_one = admin,
+    )]
+    pub contract: Account<'info, Contract>,
+    pub manager: SystemAccount<'info>,
+    #[account(
+        mint::authority = contract,
+        mint::token_program = token_program,
+    )]
+    pub lot_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [b"lot", contract.key().as_ref(), lot_mint.key().as_ref()],
+        bump
+    )]
+    pub lot: Account<'info, Lot>,
+    #[account(
+        init,
+        payer = admin,
+        space = Dispute::LEN,
+        seeds = [b"dispute", contract.key().as_ref(), lot_mint.key().as_ref()],
+        bump
+    )]
+    pub dispute: Account<'info, Dispute>,
+    pub token_program: Program<'info, Token2022>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ContestDenial<'info> {
+    pub manager: Signer<'info>,
+    #[account(
+        seeds = [b"contract", contract.contract_id.as_ref()],
+        bump,
+    )]
+    pub contract: Account<'info, Contract>,
+    #[account(
+        mut,
+        seeds = [b"dispute", contract.key().as_ref(), dispute.lot_mint.as_ref()],
+        bump,
+        has_one = manager,
+    )]
+    pub dispute: Account<'info, Dispute>,
+}
@@ -7,15 +7,62 @@ pub enum SeedlotContractsError {
     CannotCertifyAboveTierFour,
     NoCertificationTierZero,
     ManagerAlreadyDecertified,
+    // Deprecated, offers are Token-2022 groups now. Kept so later codes don't change.
     OffersFull,
+    // Deprecated, offers are no longer looked up by index. Kept so later codes don't change.
     InvalidOfferIndex,
     OrderMintNotFound,
     AdditionalMetadataIllFormed,
     InvalidPrice,
+    // Deprecated, lots have their own PDAs now. Kept so later codes don't change.
     LotsFull,
     ManagerNotCertified,
     InvalidLotIndex,
     LotMintMismatch,
     ManagerMismatch,
     UserMismatch,
+    InvalidLotState,
+    InvalidLotStateTransition,
+    LotLimitExceeded,
+    ManagerNotAuthorized,
+    InvalidFeeConfig,
+    NotPendingAdmin,
+    NotALegacyContract,
+    InsufficientOrderBalance,
+    OrderExpired,
+    PriceAboveMaximum,
+    AdminMismatch,
+    CertificationTierUnchanged,
+    CertificationLapsed,
+    InvalidValidityPeriod,
+    ManagerNotDecertified,
+    InvalidProbation,
+    InvalidStrikePolicy,
+    MissingDenialReason,
+    InsufficientBond,
+    ManagerHasOpenLots,
+    MissingLotEscrow,
+    InvalidPayoutSchedule,
+    MilestoneOutOfOrder,
+    NotAnAttester,
+    InvalidSurvivorCount,
+    DenialMustBeDisputed,
+    ArbitratorNotSet,
+    ArbitratorCannotBeAdmin,
+    NotArbitrator,
+    InvalidContestWindow,
+    ContestWindowClosed,
+    ContestWindowOpen,
+    MissingDispute,
+    EmptyHolding,
+    LotTokensHeld,
+    ExpensesOverCap,
+    ExpenseAlreadySettled,
+    InvalidExpenseCap,
+    OrderReceiptMismatch,
+    NoPendingPayout,
+    InvalidTreesPerLot,
+    DisputesOpen,
+    MathOverflow,
+    NotABuyer,
 }
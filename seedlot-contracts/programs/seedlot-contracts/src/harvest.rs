@@ -6,28 +6,24 @@ use anchor_spl::{
     token_interface::{Mint, TokenAccount},
 };
 
-use crate::{Contract, Lots};
+use crate::{Contract, Lot};
 
 #[derive(Accounts)]
-pub struct PayHarvest<'info> {
+pub struct DepositHarvest<'info> {
     #[account(
-      seeds = [b"contract", contract.admin.as_ref()],
+      seeds = [b"contract", contract.contract_id.as_ref()],
       bump,
-      has_one = admin,
-      has_one = lots_account,
       has_one = usdc_mint,
   )]
     pub contract: Box<Account<'info, Contract>>,
     #[account(mut)]
     pub payer: Signer<'info>,
-    #[account(mut)]
-    pub user: SystemAccount<'info>,
-    #[account(mut)]
-    pub manager: SystemAccount<'info>,
-    #[account(mut)]
-    pub admin: SystemAccount<'info>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    #[account(mut)]
+    // Left out when the admin deposits.
+    #[account(
+        seeds = [b"buyer", contract.key().as_ref(), payer.key().as_ref()],
+        bump
+    )]
+    pub buyer_record: Option<Account<'info, HarvestBuyer>>,
     pub usdc_mint: InterfaceAccount<'info, Mint>,
     pub token_program_standard: Program<'info, Token>,
     #[account(
@@ -37,120 +33,550 @@ pub struct PayHarvest<'info> {
         associated_token::token_program = token_program_standard
     )]
     pub payer_usdc_account: InterfaceAccount<'info, TokenAccount>,
+    pub lot_mint: InterfaceAccount<'info, Mint>,
     #[account(
         mut,
-        associated_token::mint = usdc_mint,
-        associated_token::authority = user,
-        associated_token::token_program = token_program_standard
+        seeds = [b"lot", contract.key().as_ref(), lot_mint.key().as_ref()],
+        bump
+    )]
+    pub lot: Account<'info, Lot>,
+    // Holds every party's share of the lot's harvests until they claim it.
+    #[account(
+        init_if_needed,
+        payer = payer,
+        seeds = [b"harvest_vault", contract.key().as_ref(), lot_mint.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = contract,
+        token::token_program = token_program_standard,
     )]
-    pub user_usdc_account: InterfaceAccount<'info, TokenAccount>,
+    pub harvest_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    // The lot's next entry in its harvest ledger.
+    #[account(
+        init,
+        payer = payer,
+        space = HarvestRecord::LEN,
+        seeds = [
+            b"harvest",
+            contract.key().as_ref(),
+            lot_mint.key().as_ref(),
+            lot.harvest_totals.harvests.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub harvest_record: Box<Account<'info, HarvestRecord>>,
+    pub token_program: Program<'info, Token2022>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReportHarvestExpense<'info> {
+    #[account(mut)]
+    pub manager: Signer<'info>,
+    #[account(
+      seeds = [b"contract", contract.contract_id.as_ref()],
+      bump,
+  )]
+    pub contract: Box<Account<'info, Contract>>,
+    #[account(
+        mint::authority = contract,
+        mint::token_program = token_program,
+    )]
+    pub lot_mint: Box<InterfaceAccount<'info, Mint>>,
     #[account(
         mut,
-        associated_token::mint = usdc_mint,
-        associated_token::authority = manager,
-        associated_token::token_program = token_program_standard
+        seeds = [b"lot", contract.key().as_ref(), lot_mint.key().as_ref()],
+        bump
     )]
-    pub manager_usdc_account: InterfaceAccount<'info, TokenAccount>,
+    pub lot: Box<Account<'info, Lot>>,
+    #[account(
+        init,
+        payer = manager,
+        space = HarvestExpense::LEN,
+        seeds = [
+            b"expense",
+            contract.key().as_ref(),
+            lot_mint.key().as_ref(),
+            lot.harvest_totals.harvests.to_le_bytes().as_ref(),
+            lot.expense_count.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub expense: Box<Account<'info, HarvestExpense>>,
+    pub token_program: Program<'info, Token2022>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RetractHarvestExpense<'info> {
+    #[account(mut)]
+    pub manager: Signer<'info>,
+    #[account(
+      seeds = [b"contract", contract.contract_id.as_ref()],
+      bump,
+  )]
+    pub contract: Box<Account<'info, Contract>>,
+    pub lot_mint: Box<InterfaceAccount<'info, Mint>>,
     #[account(
         mut,
-        associated_token::mint = usdc_mint,
-        associated_token::authority = admin,
-        associated_token::token_program = token_program_standard
+        seeds = [b"lot", contract.key().as_ref(), lot_mint.key().as_ref()],
+        bump
     )]
-    pub admin_usdc_account: InterfaceAccount<'info, TokenAccount>,
-    pub lots_account: AccountLoader<'info, Lots>,
-    pub lot_mint: InterfaceAccount<'info, Mint>,
+    pub lot: Box<Account<'info, Lot>>,
+    #[account(
+        mut,
+        close = manager,
+        seeds = [
+            b"expense",
+            contract.key().as_ref(),
+            lot_mint.key().as_ref(),
+            expense.harvest.to_le_bytes().as_ref(),
+            expense.index.to_le_bytes().as_ref()
+        ],
+        bump,
+        has_one = manager,
+    )]
+    pub expense: Box<Account<'info, HarvestExpense>>,
+}
+
+#[derive(Accounts)]
+pub struct AddBuyer<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"contract", contract.contract_id.as_ref()],
+        bump,
+        has_one = admin,
+    )]
+    pub contract: Account<'info, Contract>,
+    pub buyer: SystemAccount<'info>,
+    #[account(
+        init,
+        payer = admin,
+        space = HarvestBuyer::LEN,
+        seeds = [b"buyer", contract.key().as_ref(), buyer.key().as_ref()],
+        bump
+    )]
+    pub buyer_record: Account<'info, HarvestBuyer>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveBuyer<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"contract", contract.contract_id.as_ref()],
+        bump,
+        has_one = admin,
+    )]
+    pub contract: Account<'info, Contract>,
+    pub buyer: SystemAccount<'info>,
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"buyer", contract.key().as_ref(), buyer.key().as_ref()],
+        bump
+    )]
+    pub buyer_record: Account<'info, HarvestBuyer>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveExpenseOverrun<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+      seeds = [b"contract", contract.contract_id.as_ref()],
+      bump,
+      has_one = admin,
+  )]
+    pub contract: Box<Account<'info, Contract>>,
+    #[account(
+        mint::authority = contract,
+        mint::token_program = token_program,
+    )]
+    pub lot_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [b"lot", contract.key().as_ref(), lot_mint.key().as_ref()],
+        bump
+    )]
+    pub lot: Box<Account<'info, Lot>>,
     pub token_program: Program<'info, Token2022>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimHarvest<'info> {
+    // The contract's admin or the lot's manager.
+    #[account(mut)]
+    pub beneficiary: Signer<'info>,
+    #[account(
+      seeds = [b"contract", contract.contract_id.as_ref()],
+      bump,
+      has_one = usdc_mint,
+  )]
+    pub contract: Box<Account<'info, Contract>>,
+    #[account(
+        mint::authority = contract,
+        mint::token_program = token_program,
+    )]
+    pub lot_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [b"lot", contract.key().as_ref(), lot_mint.key().as_ref()],
+        bump
+    )]
+    pub lot: Box<Account<'info, Lot>>,
+    pub usdc_mint: Box<InterfaceAccount<'info, Mint>>,
     #[account(
         mut,
-        associated_token::mint = lot_mint,
-        associated_token::authority = user,
-        associated_token::token_program = token_program
+        seeds = [b"harvest_vault", contract.key().as_ref(), lot_mint.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = contract,
+        token::token_program = token_program_standard,
     )]
-    pub user_lot_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub harvest_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = beneficiary,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = beneficiary,
+        associated_token::token_program = token_program_standard,
+    )]
+    pub beneficiary_usdc_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Program<'info, Token2022>,
+    pub token_program_standard: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
 pub mod instructions {
-    use anchor_spl::token::{transfer_checked, Transfer, TransferChecked};
+    use anchor_spl::token::{transfer_checked, TransferChecked};
 
     use super::*;
     use crate::{
-        utils::{get_token_metadata, get_value, price_cents_2_usdc},
+        apply_bps, progress::verify_manager, utils::price_cents_2_usdc, LotState,
         SeedlotContractsError,
     };
 
-    pub fn pay_harvest(
-        ctx: Context<PayHarvest>,
-        lot_index: u64,
-        manager_payment_for_harvest: u64,
-        profit: u64,
+    pub fn add_buyer(ctx: Context<AddBuyer>) -> Result<()> {
+        ctx.accounts.buyer_record.buyer = ctx.accounts.buyer.key();
+        Ok(())
+    }
+
+    pub fn remove_buyer(_ctx: Context<RemoveBuyer>) -> Result<()> {
+        Ok(())
+    }
+
+    // Moves a harvest's gross proceeds into the lot's harvest vault. The manager's reported
+    // expenses come off the top and the rest is split as profit, with what the holders, the admin
+    // and the manager are each owed recorded so nobody needs a USDC account to be paid into yet.
+    // The payer is a buyer the admin added with add_buyer, or the admin on their behalf. Expenses
+    // over the cap need the admin's approval first, which is how losses get recorded.
+    pub fn deposit_harvest(
+        ctx: Context<DepositHarvest>,
+        season: u16,
+        gross_proceeds: u64,
     ) -> Result<()> {
-        let lot = ctx.accounts.lots_account.load()?.get(lot_index)?;
-        require_eq!(
-            lot.mint.key(),
-            ctx.accounts.lot_mint.key(),
-            SeedlotContractsError::LotMintMismatch
+        require!(
+            ctx.accounts.payer.key() == ctx.accounts.contract.admin
+                || ctx.accounts.buyer_record.is_some(),
+            SeedlotContractsError::NotABuyer
         );
-        // Get lot metadata
-        let lot_token_metadata = get_token_metadata(&ctx.accounts.lot_mint)?;
+        // Harvests are only paid out once the manager has marked them ready.
+        ctx.accounts.lot.require_state(LotState::Ready)?;
 
-        // Extract manager from lot metadata and compare to manager
-        let manager = get_value(&lot_token_metadata, "manager")?;
-        require_eq!(
-            manager,
-            ctx.accounts.manager.key().to_string(),
-            SeedlotContractsError::ManagerMismatch
+        let gross_revenue = price_cents_2_usdc(&gross_proceeds);
+        let expenses = ctx.accounts.lot.pending_expenses;
+        let cap = apply_bps(gross_revenue, ctx.accounts.contract.max_expense_bps);
+        require_gte!(
+            cap.max(ctx.accounts.lot.approved_expenses),
+            expenses,
+            SeedlotContractsError::ExpensesOverCap
         );
 
-        // Check the user_lot_token_account
+        let lot = &mut ctx.accounts.lot;
+        let profit = gross_revenue as i64 - expenses as i64;
+        let (loss_recovered, split_profit) = lot.carry_loss(profit);
+
+        // Calculate payment splits
+        let (holders_amount, admin_amount, manager_profit) =
+            lot.fee_config.split_profit(split_profit);
+        // The manager is paid back their expenses as far as the proceeds go
+        let manager_amount = expenses.min(gross_revenue) + loss_recovered + manager_profit;
+
+        lot.add_profit(holders_amount, ctx.accounts.lot_mint.supply)?;
+        lot.holders_unclaimed += holders_amount;
+        lot.admin_unclaimed += admin_amount;
+        lot.manager_unclaimed += manager_amount;
+
+        let record = HarvestRecord {
+            lot_mint: ctx.accounts.lot_mint.key(),
+            index: lot.harvest_totals.harvests,
+            season,
+            depositor: ctx.accounts.payer.key(),
+            gross_revenue,
+            manager_cost: expenses,
+            expenses: lot.expense_count,
+            over_cap: expenses.saturating_sub(cap),
+            profit,
+            loss_recovered,
+            carried_loss: lot.carried_loss,
+            holders_amount,
+            admin_amount,
+            manager_amount,
+            deposited_at: Clock::get()?.unix_timestamp,
+        };
+        lot.harvest_totals.add(&record);
+        ctx.accounts.harvest_record.set_inner(record);
+        // Expenses reported from here on are for the next harvest
+        lot.pending_expenses = 0;
+        lot.expense_count = 0;
+        lot.approved_expenses = 0;
+
+        transfer_checked(
+            CpiContext::new(
+                ctx.accounts.token_program_standard.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.payer_usdc_account.to_account_info(),
+                    to: ctx.accounts.harvest_vault.to_account_info(),
+                    authority: ctx.accounts.payer.to_account_info(),
+                    mint: ctx.accounts.usdc_mint.to_account_info(),
+                },
+            ),
+            holders_amount + admin_amount + manager_amount,
+            ctx.accounts.usdc_mint.decimals,
+        )
+    }
+
+    // Itemizes one of the manager's costs for the lot's next harvest, in USD cents.
+    pub fn report_harvest_expense(
+        ctx: Context<ReportHarvestExpense>,
+        amount: u64,
+        receipt_hash: [u8; 32],
+    ) -> Result<()> {
+        verify_manager(&ctx.accounts.lot_mint, &ctx.accounts.manager.key())?;
+        let lot = &mut ctx.accounts.lot;
+        require!(
+            matches!(lot.state, LotState::Growing | LotState::Ready),
+            SeedlotContractsError::InvalidLotState
+        );
+
+        let amount = price_cents_2_usdc(&amount);
+        ctx.accounts.expense.set_inner(HarvestExpense {
+            lot_mint: ctx.accounts.lot_mint.key(),
+            manager: ctx.accounts.manager.key(),
+            harvest: lot.harvest_totals.harvests,
+            index: lot.expense_count,
+            amount,
+            receipt_hash,
+            reported_at: Clock::get()?.unix_timestamp,
+        });
+        lot.pending_expenses += amount;
+        lot.expense_count += 1;
+        Ok(())
+    }
+
+    // Lets the manager take back an expense before its harvest is deposited, for instance to
+    // get back under the expense cap.
+    pub fn retract_harvest_expense(ctx: Context<RetractHarvestExpense>) -> Result<()> {
+        let lot = &mut ctx.accounts.lot;
+        let expense = &ctx.accounts.expense;
         require_eq!(
-            ctx.accounts.user_lot_token_account.amount,
-            ctx.accounts.lot_mint.supply,
-            SeedlotContractsError::UserMismatch
+            expense.harvest,
+            lot.harvest_totals.harvests,
+            SeedlotContractsError::ExpenseAlreadySettled
         );
+        lot.pending_expenses -= expense.amount;
+        Ok(())
+    }
 
-        let profit_in_usdc = price_cents_2_usdc(&profit);
-        let manager_payment_for_harvest_in_usdc = price_cents_2_usdc(&manager_payment_for_harvest);
+    // Lets the lot's next harvest be deposited with up to `approved_expenses` USD cents of
+    // expenses even if that's over the cap, for seasons that really did cost more than they made.
+    pub fn approve_expense_overrun(
+        ctx: Context<ApproveExpenseOverrun>,
+        approved_expenses: u64,
+    ) -> Result<()> {
+        let lot = &mut ctx.accounts.lot;
+        lot.approved_expenses = price_cents_2_usdc(&approved_expenses);
+        emit!(ExpenseOverrunApproved {
+            lot_mint: ctx.accounts.lot_mint.key(),
+            harvest: lot.harvest_totals.harvests,
+            approved_expenses: lot.approved_expenses,
+        });
+        Ok(())
+    }
 
-        // Calculate payment splits
-        let user_amount = profit_in_usdc / 2;
-        let admin_amount = profit_in_usdc / 4;
-        let manager_amount = profit_in_usdc / 4 + manager_payment_for_harvest_in_usdc;
-
-        let payment_info = [
-            (
-                ctx.accounts.user_usdc_account.to_account_info(),
-                user_amount,
-            ),
-            (
-                ctx.accounts.admin_usdc_account.to_account_info(),
-                admin_amount,
-            ),
-            (
-                ctx.accounts.manager_usdc_account.to_account_info(),
-                manager_amount,
-            ),
-        ];
-
-        for (account, amount) in payment_info {
-            transfer_checked(
-                CpiContext::new(
-                    ctx.accounts.token_program_standard.to_account_info(),
-                    TransferChecked {
-                        from: ctx.accounts.payer_usdc_account.to_account_info(),
-                        to: account,
-                        authority: ctx.accounts.payer.to_account_info(),
-                        mint: ctx.accounts.usdc_mint.to_account_info(),
-                    },
-                ),
-                amount,
-                6,
-            )?;
+    // Pays the admin or the manager everything they're owed from the lot's harvests so far.
+    // Holders claim their share through their holdings instead.
+    pub fn claim_harvest(ctx: Context<ClaimHarvest>) -> Result<()> {
+        let beneficiary = ctx.accounts.beneficiary.key();
+        let lot = &mut ctx.accounts.lot;
+        let amount = if beneficiary == ctx.accounts.contract.admin {
+            std::mem::take(&mut lot.admin_unclaimed)
+        } else {
+            verify_manager(&ctx.accounts.lot_mint, &beneficiary)?;
+            std::mem::take(&mut lot.manager_unclaimed)
+        };
+        if amount == 0 {
+            return Ok(());
         }
+
+        transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program_standard.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.harvest_vault.to_account_info(),
+                    to: ctx
+                        .accounts
+                        .beneficiary_usdc_token_account
+                        .to_account_info(),
+                    authority: ctx.accounts.contract.to_account_info(),
+                    mint: ctx.accounts.usdc_mint.to_account_info(),
+                },
+                &[&[
+                    b"contract",
+                    ctx.accounts.contract.contract_id.as_ref(),
+                    &[ctx.bumps.contract],
+                ]],
+            ),
+            amount,
+            ctx.accounts.usdc_mint.decimals,
+        )?;
+
+        emit!(HarvestClaimed {
+            lot_mint: ctx.accounts.lot_mint.key(),
+            beneficiary,
+            amount,
+        });
         Ok(())
     }
 }
+
+// One deposited harvest, seeded by [b"harvest", contract, lot_mint, index]. All amounts in USDC.
+#[account]
+pub struct HarvestRecord {
+    pub lot_mint: Pubkey,
+    // The lot's harvests before this one.
+    pub index: u32,
+    pub season: u16,
+    // The buyer, or the admin depositing for them.
+    pub depositor: Pubkey,
+    pub gross_revenue: u64,
+    // The manager's reported expenses. Their HarvestExpense indices run up to `expenses`, less
+    // any the manager retracted.
+    pub manager_cost: u64,
+    pub expenses: u16,
+    // How far manager_cost went over the expense cap with the admin's approval.
+    pub over_cap: u64,
+    // Gross revenue less the manager's expenses, negative for a loss.
+    pub profit: i64,
+    // How much of the lot's carried loss this harvest's profit paid back to the manager before
+    // the rest was split, and the carried loss left after it.
+    pub loss_recovered: u64,
+    pub carried_loss: u64,
+    // Each party's share. The manager's includes their cost.
+    pub holders_amount: u64,
+    pub admin_amount: u64,
+    pub manager_amount: u64,
+    pub deposited_at: i64,
+}
+
+impl HarvestRecord {
+    pub const LEN: usize = 8 // Discriminator
+     + 32 // lot_mint
+     + 4 // index
+     + 2 // season
+     + 32 // depositor
+     + 8 // gross_revenue
+     + 8 // manager_cost
+     + 2 // expenses
+     + 8 // over_cap
+     + 8 // profit
+     + 8 // loss_recovered
+     + 8 // carried_loss
+     + 8 // holders_amount
+     + 8 // admin_amount
+     + 8 // manager_amount
+     + 8; // deposited_at
+}
+
+// Someone the admin lets deposit harvests they bought, seeded by [b"buyer", contract, buyer].
+#[account]
+pub struct HarvestBuyer {
+    pub buyer: Pubkey,
+}
+
+impl HarvestBuyer {
+    pub const LEN: usize = 8 // Discriminator
+     + 32; // buyer
+}
+
+// A cost the manager reported for a harvest, seeded by
+// [b"expense", contract, lot_mint, harvest, index]. In USDC.
+#[account]
+pub struct HarvestExpense {
+    pub lot_mint: Pubkey,
+    pub manager: Pubkey,
+    // The index of the harvest record it's deducted in.
+    pub harvest: u32,
+    pub index: u16,
+    pub amount: u64,
+    pub receipt_hash: [u8; 32],
+    pub reported_at: i64,
+}
+
+impl HarvestExpense {
+    pub const LEN: usize = 8 // Discriminator
+     + 32 // lot_mint
+     + 32 // manager
+     + 4 // harvest
+     + 2 // index
+     + 8 // amount
+     + 32 // receipt_hash
+     + 8; // reported_at
+}
+
+// What the lot's harvests have added up to over its lifetime.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct HarvestTotals {
+    pub harvests: u32,
+    pub gross_revenue: u64,
+    pub manager_cost: u64,
+    pub profit: i64,
+    pub holders_amount: u64,
+    pub admin_amount: u64,
+    pub manager_amount: u64,
+}
+
+impl HarvestTotals {
+    pub const LEN: usize = 4 + 8 * 6;
+
+    pub fn add(&mut self, record: &HarvestRecord) {
+        self.harvests += 1;
+        self.gross_revenue += record.gross_revenue;
+        self.manager_cost += record.manager_cost;
+        self.profit += record.profit;
+        self.holders_amount += record.holders_amount;
+        self.admin_amount += record.admin_amount;
+        self.manager_amount += record.manager_amount;
+    }
+}
+
+#[event]
+pub struct ExpenseOverrunApproved {
+    pub lot_mint: Pubkey,
+    // The index of the harvest record the expenses will be deducted in.
+    pub harvest: u32,
+    pub approved_expenses: u64,
+}
+
+#[event]
+pub struct HarvestClaimed {
+    pub lot_mint: Pubkey,
+    pub beneficiary: Pubkey,
+    pub amount: u64,
+}
 // Add these error codes to your error.rs file
 #[error_code]
 pub enum ErrorCode {
@@ -0,0 +1,354 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::Token;
+use anchor_spl::token_2022::{freeze_account, thaw_account, FreezeAccount, ThawAccount, Token2022};
+use anchor_spl::token_interface::{Mint, TokenAccount};
+
+use crate::{Contract, Lot, LotState, SeedlotContractsError};
+
+pub mod instructions {
+    use super::*;
+
+    // Registers the holder's lot tokens for their share of harvest profit. Their lot token
+    // account stays frozen while the holding is open so the units can't move behind its back.
+    // The units also bring along what they earned outside holdings since the holder's last one
+    // closed, or since the lot started for holders who never had one.
+    pub fn open_holding(ctx: Context<OpenHolding>) -> Result<()> {
+        require_confirmed(&ctx.accounts.lot)?;
+        require_eq!(
+            ctx.accounts.holding.units,
+            0,
+            SeedlotContractsError::LotTokensHeld
+        );
+        let units = ctx.accounts.holder_lot_token_account.amount;
+        require_gt!(units, 0, SeedlotContractsError::EmptyHolding);
+
+        let lot = &mut ctx.accounts.lot;
+        let paid_through = ctx.accounts.holding.paid_through;
+        let unregistered_profit = lot.hold(units, paid_through)?;
+        ctx.accounts.holding.set_inner(Holding {
+            holder: ctx.accounts.holder.key(),
+            lot_mint: ctx.accounts.lot_mint.key(),
+            units,
+            profit_debt: lot
+                .profit_per_unit
+                .checked_mul(units as u128)
+                .ok_or(SeedlotContractsError::MathOverflow)?,
+            unregistered_profit,
+            paid_through,
+        });
+
+        if !ctx.accounts.holder_lot_token_account.is_frozen() {
+            freeze_account(CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                FreezeAccount {
+                    account: ctx.accounts.holder_lot_token_account.to_account_info(),
+                    mint: ctx.accounts.lot_mint.to_account_info(),
+                    authority: ctx.accounts.contract.to_account_info(),
+                },
+                &[&[
+                    b"contract",
+                    ctx.accounts.contract.contract_id.as_ref(),
+                    &[ctx.bumps.contract],
+                ]],
+            ))?;
+        }
+        Ok(())
+    }
+
+    pub fn claim_harvest_share(ctx: Context<ClaimHarvestShare>) -> Result<()> {
+        ctx.accounts.claim(ctx.bumps.contract)
+    }
+
+    // Pays out what the holding is still owed and frees the holder's lot tokens. The holding
+    // account stays behind to remember what its units were paid through, so registering them
+    // again can't collect the same profit twice.
+    pub fn close_holding(ctx: Context<ClaimHarvestShare>) -> Result<()> {
+        ctx.accounts.claim(ctx.bumps.contract)?;
+        let holding = &mut ctx.accounts.holding;
+        ctx.accounts.lot.release(holding.units)?;
+        holding.units = 0;
+        holding.profit_debt = 0;
+        holding.paid_through = ctx.accounts.lot.profit_per_unit;
+        ctx.accounts.thaw_holder_lots(ctx.bumps.contract)
+    }
+
+    // Lot token accounts start out frozen, so holders without a holding call this to be able to
+    // send or receive lot tokens.
+    pub fn thaw_lot_account(ctx: Context<ThawLotAccount>) -> Result<()> {
+        require_confirmed(&ctx.accounts.lot)?;
+        if !ctx.accounts.holding.data_is_empty() {
+            let holding =
+                Holding::try_deserialize(&mut &ctx.accounts.holding.try_borrow_data()?[..])?;
+            require_eq!(holding.units, 0, SeedlotContractsError::LotTokensHeld);
+        }
+        thaw_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            ThawAccount {
+                account: ctx.accounts.holder_lot_token_account.to_account_info(),
+                mint: ctx.accounts.lot_mint.to_account_info(),
+                authority: ctx.accounts.contract.to_account_info(),
+            },
+            &[&[
+                b"contract",
+                ctx.accounts.contract.contract_id.as_ref(),
+                &[ctx.bumps.contract],
+            ]],
+        ))
+    }
+
+    // Lot tokens only change hands once the admin has confirmed the lot.
+    fn require_confirmed(lot: &Lot) -> Result<()> {
+        require!(
+            matches!(
+                lot.state,
+                LotState::Growing | LotState::Ready | LotState::Shipped
+            ),
+            SeedlotContractsError::InvalidLotState
+        );
+        Ok(())
+    }
+}
+
+// Lot tokens registered for harvest profit, seeded by [b"holding", contract, lot_mint, holder].
+#[account]
+pub struct Holding {
+    pub holder: Pubkey,
+    pub lot_mint: Pubkey,
+    // Zero once the holding is closed.
+    pub units: u64,
+    // The lot's profit_per_unit times units as of the holding's last claim.
+    pub profit_debt: u128,
+    // What the units earned outside holdings before they were registered, paid out with the
+    // next claim.
+    pub unregistered_profit: u64,
+    // The lot's profit_per_unit when the holding last closed.
+    pub paid_through: u128,
+}
+
+impl Holding {
+    pub const LEN: usize = 8 // Discriminator
+     + 32 // holder
+     + 32 // lot_mint
+     + 8 // units
+     + 16 // profit_debt
+     + 8 // unregistered_profit
+     + 16; // paid_through
+
+    // Harvest profit the units have earned in the holding since its last claim.
+    pub fn earned(&self, lot: &Lot) -> Result<u64> {
+        let earned = lot
+            .profit_per_unit
+            .checked_mul(self.units as u128)
+            .and_then(|profit| profit.checked_sub(self.profit_debt))
+            .map(|earned| earned / Lot::PROFIT_PRECISION)
+            .ok_or(SeedlotContractsError::MathOverflow)?;
+        u64::try_from(earned).map_err(|_| SeedlotContractsError::MathOverflow.into())
+    }
+}
+
+impl<'info> ClaimHarvestShare<'info> {
+    fn claim(&mut self, contract_bump: u8) -> Result<()> {
+        let earned = self.holding.earned(&self.lot)?;
+        self.holding.profit_debt = self
+            .holding
+            .profit_debt
+            .checked_add(earned as u128 * Lot::PROFIT_PRECISION)
+            .ok_or(SeedlotContractsError::MathOverflow)?;
+        let owed = earned
+            .checked_add(std::mem::take(&mut self.holding.unregistered_profit))
+            .ok_or(SeedlotContractsError::MathOverflow)?;
+        if owed == 0 {
+            return Ok(());
+        }
+        self.lot.holders_unclaimed = self
+            .lot
+            .holders_unclaimed
+            .checked_sub(owed)
+            .ok_or(SeedlotContractsError::MathOverflow)?;
+        // Only needed once a harvest has been deposited
+        let harvest_vault = self
+            .harvest_vault
+            .as_ref()
+            .ok_or(ErrorCode::AccountNotEnoughKeys)?;
+        anchor_spl::token::transfer(
+            CpiContext::new_with_signer(
+                self.token_program_standard.to_account_info(),
+                anchor_spl::token::Transfer {
+                    from: harvest_vault.to_account_info(),
+                    to: self.holder_usdc_token_account.to_account_info(),
+                    authority: self.contract.to_account_info(),
+                },
+                &[&[
+                    b"contract",
+                    self.contract.contract_id.as_ref(),
+                    &[contract_bump],
+                ]],
+            ),
+            owed,
+        )
+    }
+
+    fn thaw_holder_lots(&self, contract_bump: u8) -> Result<()> {
+        thaw_account(CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            ThawAccount {
+                account: self.holder_lot_token_account.to_account_info(),
+                mint: self.lot_mint.to_account_info(),
+                authority: self.contract.to_account_info(),
+            },
+            &[&[
+                b"contract",
+                self.contract.contract_id.as_ref(),
+                &[contract_bump],
+            ]],
+        ))
+    }
+}
+
+#[derive(Accounts)]
+pub struct OpenHolding<'info> {
+    #[account(mut)]
+    pub holder: Signer<'info>,
+    #[account(
+        seeds = [b"contract", contract.contract_id.as_ref()],
+        bump,
+    )]
+    pub contract: Box<Account<'info, Contract>>,
+    #[account(
+        mint::authority = contract,
+        mint::token_program = token_program,
+    )]
+    pub lot_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [b"lot", contract.key().as_ref(), lot_mint.key().as_ref()],
+        bump
+    )]
+    pub lot: Box<Account<'info, Lot>>,
+    #[account(
+        init_if_needed,
+        payer = holder,
+        space = Holding::LEN,
+        seeds = [
+            b"holding",
+            contract.key().as_ref(),
+            lot_mint.key().as_ref(),
+            holder.key().as_ref()
+        ],
+        bump
+    )]
+    pub holding: Box<Account<'info, Holding>>,
+    #[account(
+        mut,
+        associated_token::mint = lot_mint,
+        associated_token::authority = holder,
+        associated_token::token_program = token_program,
+    )]
+    pub holder_lot_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Program<'info, Token2022>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimHarvestShare<'info> {
+    #[account(mut)]
+    pub holder: Signer<'info>,
+    #[account(
+        seeds = [b"contract", contract.contract_id.as_ref()],
+        bump,
+        has_one = usdc_mint,
+    )]
+    pub contract: Box<Account<'info, Contract>>,
+    #[account(
+        mint::authority = contract,
+        mint::token_program = token_program,
+    )]
+    pub lot_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [b"lot", contract.key().as_ref(), lot_mint.key().as_ref()],
+        bump
+    )]
+    pub lot: Box<Account<'info, Lot>>,
+    #[account(
+        mut,
+        seeds = [
+            b"holding",
+            contract.key().as_ref(),
+            lot_mint.key().as_ref(),
+            holder.key().as_ref()
+        ],
+        bump,
+        has_one = holder,
+    )]
+    pub holding: Box<Account<'info, Holding>>,
+    #[account(
+        mut,
+        associated_token::mint = lot_mint,
+        associated_token::authority = holder,
+        associated_token::token_program = token_program,
+    )]
+    pub holder_lot_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub usdc_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [b"harvest_vault", contract.key().as_ref(), lot_mint.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = contract,
+        token::token_program = token_program_standard,
+    )]
+    pub harvest_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    #[account(
+        init_if_needed,
+        payer = holder,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = holder,
+        associated_token::token_program = token_program_standard,
+    )]
+    pub holder_usdc_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Program<'info, Token2022>,
+    pub token_program_standard: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ThawLotAccount<'info> {
+    pub holder: Signer<'info>,
+    #[account(
+        seeds = [b"contract", contract.contract_id.as_ref()],
+        bump,
+    )]
+    pub contract: Box<Account<'info, Contract>>,
+    #[account(
+        mint::authority = contract,
+        mint::token_program = token_program,
+    )]
+    pub lot_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        seeds = [b"lot", contract.key().as_ref(), lot_mint.key().as_ref()],
+        bump
+    )]
+    pub lot: Box<Account<'info, Lot>>,
+    /// CHECK: Only checked to hold no units, since open holdings keep their lot tokens frozen.
+    #[account(
+        seeds = [
+            b"holding",
+            contract.key().as_ref(),
+            lot_mint.key().as_ref(),
+            holder.key().as_ref()
+        ],
+        bump
+    )]
+    pub holding: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        associated_token::mint = lot_mint,
+        associated_token::authority = holder,
+        associated_token::token_program = token_program,
+    )]
+    pub holder_lot_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Program<'info, Token2022>,
+}
@@ -4,25 +4,47 @@ use anchor_spl::token::Token;
 use anchor_spl::token_2022::Token2022;
 use anchor_spl::token_interface::{Mint, TokenAccount};
 
+mod admin;
+mod appeal;
+mod authorize;
+mod bond;
 mod certify;
+mod config;
 mod confirm;
 mod contract;
+mod disputes;
 mod errors;
 mod harvest;
+mod holdings;
 mod lots;
+mod manager;
+mod migrate;
+mod milestones;
 mod offers;
 mod orders;
+mod progress;
 mod utils;
 
+pub use admin::*;
+pub use appeal::*;
+pub use authorize::*;
+pub use bond::*;
 pub use certify::*;
+pub use config::*;
 pub use confirm::*;
 pub use contract::*;
+pub use disputes::*;
 pub use errors::*;
 pub use harvest::*;
+pub use holdings::*;
 pub use lots::*;
+pub use manager::*;
+pub use migrate::*;
+pub use milestones::*;
 pub use offers::*;
 pub use orders::*;
-use utils::{init_mint, InitMint, InitMintBumps, MintMetadata};
+pub use progress::*;
+use utils::{init_mint, InitMint, InitMintBumps, MintGroup, MintMetadata};
 
 declare_id!("5AZhBpUbTiZkqM2Z8LVTev8z6awDUGENeBvZYdjWVod9");
 
@@ -33,111 +55,378 @@ pub mod seedlot_contracts {
 
     pub fn initialize<'info>(
         ctx: Context<'_, '_, '_, 'info, Initialize<'info>>,
+        contract_id: Pubkey,
         trees_per_lot: u64,
         certification_mint_metadata: MintMetadata,
+        fee_config: FeeConfig,
     ) -> Result<()> {
-        ctx.accounts.contract.admin = ctx.accounts.admin.key(); // must be done before initializing the mint because it relies on context.admin
-        init_mint(
-            Context::new(
-                ctx.program_id,
-                &mut InitMint {
-                    payer: ctx.accounts.admin.clone(),
-                    contract: ctx.accounts.contract.clone(),
-                    mint: ctx.accounts.certification_mint.clone(),
-                    rent: ctx.accounts.rent.clone(),
-                    token_program: ctx.accounts.token_program.to_account_info(),
-                    system_program: ctx.accounts.system_program.to_account_info(),
-                },
-                &[],
-                InitMintBumps {
-                    contract: ctx.bumps.contract,
-                },
+        fee_config.validate()?;
+        Contract::validate_trees_per_lot(trees_per_lot)?;
+        ctx.accounts.contract.contract_id = contract_id; // must be done before initializing the mint because it relies on context.contract_id
+        let offers_group_metadata = offers_group_metadata();
+        let lots_group_metadata = lots_group_metadata();
+        for (mint, mint_metadata, mint_group) in [
+            (
+                &ctx.accounts.certification_mint,
+                &certification_mint_metadata,
+                MintGroup::None,
             ),
-            &certification_mint_metadata,
-        )?;
-        ctx.accounts.offers_account.load_init()?.owner = ctx.accounts.contract.key();
-        ctx.accounts.lots_account.load_init()?.owner = ctx.accounts.contract.key();
+            (
+                &ctx.accounts.offers_group,
+                &offers_group_metadata,
+                MintGroup::Group,
+            ),
+            (
+                &ctx.accounts.lots_group,
+                &lots_group_metadata,
+                MintGroup::Group,
+            ),
+        ] {
+            init_mint(
+                Context::new(
+                    ctx.program_id,
+                    &mut InitMint {
+                        payer: ctx.accounts.admin.clone(),
+                        contract: ctx.accounts.contract.clone(),
+                        mint: mint.clone(),
+                        group: None,
+                        rent: ctx.accounts.rent.clone(),
+                        token_program: ctx.accounts.token_program.to_account_info(),
+                        system_program: ctx.accounts.system_program.to_account_info(),
+                    },
+                    &[],
+                    InitMintBumps {
+                        contract: ctx.bumps.contract,
+                    },
+                ),
+                mint_metadata,
+                mint_group,
+                0,
+            )?;
+        }
         let contract = &mut ctx.accounts.contract;
+        contract.contract_id = contract_id;
         contract.admin = ctx.accounts.admin.key();
-        contract.offers_account = ctx.accounts.offers_account.key();
-        contract.lots_account = ctx.accounts.lots_account.key();
+        contract.pending_admin = None;
+        contract.offers_group = ctx.accounts.offers_group.key();
+        contract.lots_group = ctx.accounts.lots_group.key();
+        contract.legacy_lots_account = Pubkey::default();
+        contract.legacy_admin = Pubkey::default();
         contract.trees_per_lot = trees_per_lot;
         contract.certification_mint = ctx.accounts.certification_mint.key();
         contract.usdc_token_account = ctx.accounts.contract_usdc_token_account.key();
         contract.usdc_mint = ctx.accounts.usdc_mint.key();
+        contract.lot_limits = CertificationTier::DEFAULT_LOT_LIMITS;
+        contract.fee_config = fee_config;
+        contract.strike_policy = StrikePolicy::DEFAULT;
+        contract.manager_bond = 0;
+        contract.arbitrator = None;
+        contract.contest_window = Contract::DEFAULT_CONTEST_WINDOW;
+        contract.max_expense_bps = Contract::DEFAULT_MAX_EXPENSE_BPS;
+        contract.open_disputes = 0;
         Ok(())
     }
 
-    pub fn certify(ctx: Context<Certify>, new_tier: CertificationTier) -> Result<()> {
-        certify::instructions::certify(ctx, new_tier)
+    pub fn propose_admin(ctx: Context<ProposeAdmin>, new_admin: Pubkey) -> Result<()> {
+        admin::instructions::propose_admin(ctx, new_admin)
+    }
+
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        admin::instructions::accept_admin(ctx)
+    }
+
+    pub fn certify(
+        ctx: Context<Certify>,
+        new_tier: CertificationTier,
+        evidence_hash: [u8; 32],
+        valid_for: i64,
+    ) -> Result<()> {
+        certify::instructions::certify(ctx, new_tier, evidence_hash, valid_for)
+    }
+
+    pub fn renew_certification(
+        ctx: Context<Certify>,
+        evidence_hash: [u8; 32],
+        valid_for: i64,
+    ) -> Result<()> {
+        certify::instructions::renew_certification(ctx, evidence_hash, valid_for)
     }
 
     pub fn decertify(ctx: Context<Certify>) -> Result<()> {
         certify::instructions::decertify(ctx)
     }
 
-    pub fn add_offer(ctx: Context<AddOffer>, offer_mint_metadata: MintMetadata) -> Result<()> {
-        offers::instructions::add_offer(ctx, &offer_mint_metadata)
+    pub fn file_appeal(ctx: Context<FileAppeal>, evidence_hash: [u8; 32]) -> Result<()> {
+        appeal::instructions::file_appeal(ctx, evidence_hash)
+    }
+
+    pub fn reinstate(
+        ctx: Context<Reinstate>,
+        tier: CertificationTier,
+        valid_for: i64,
+        probation: Option<Probation>,
+    ) -> Result<()> {
+        appeal::instructions::reinstate(ctx, tier, valid_for, probation)
+    }
+
+    pub fn set_lot_limits(ctx: Context<UpdateContract>, lot_limits: [u64; 4]) -> Result<()> {
+        config::instructions::set_lot_limits(ctx, lot_limits)
+    }
+
+    pub fn update_config(ctx: Context<UpdateContract>, fee_config: FeeConfig) -> Result<()> {
+        config::instructions::update_config(ctx, fee_config)
+    }
+
+    pub fn set_manager_bond(ctx: Context<UpdateContract>, manager_bond: u64) -> Result<()> {
+        config::instructions::set_manager_bond(ctx, manager_bond)
+    }
+
+    pub fn set_strike_policy(
+        ctx: Context<UpdateContract>,
+        strike_policy: StrikePolicy,
+    ) -> Result<()> {
+        config::instructions::set_strike_policy(ctx, strike_policy)
+    }
+
+    pub fn set_arbitrator(
+        ctx: Context<UpdateContract>,
+        arbitrator: Option<Pubkey>,
+        contest_window: i64,
+    ) -> Result<()> {
+        config::instructions::set_arbitrator(ctx, arbitrator, contest_window)
+    }
+
+    pub fn set_max_expense_share(ctx: Context<UpdateContract>, max_expense_bps: u16) -> Result<()> {
+        config::instructions::set_max_expense_share(ctx, max_expense_bps)
+    }
+
+    pub fn update_offer_config(
+        ctx: Context<UpdateOfferConfig>,
+        fee_config: Option<FeeConfig>,
+    ) -> Result<()> {
+        config::instructions::update_offer_config(ctx, fee_config)
+    }
+
+    pub fn set_payout_schedule(
+        ctx: Context<UpdateOfferConfig>,
+        payout_schedule: Vec<u16>,
+    ) -> Result<()> {
+        config::instructions::set_payout_schedule(ctx, payout_schedule)
+    }
+
+    pub fn add_inspector(ctx: Context<AddInspector>) -> Result<()> {
+        milestones::instructions::add_inspector(ctx)
+    }
+
+    pub fn remove_inspector(ctx: Context<RemoveInspector>) -> Result<()> {
+        milestones::instructions::remove_inspector(ctx)
+    }
+
+    pub fn release_milestone(
+        ctx: Context<ReleaseMilestone>,
+        milestone: u8,
+        evidence_hash: [u8; 32],
+    ) -> Result<()> {
+        milestones::instructions::release_milestone(ctx, milestone, evidence_hash)
+    }
+
+    pub fn add_offer(
+        ctx: Context<AddOffer>,
+        offer_mint_metadata: MintMetadata,
+        fee_config: Option<FeeConfig>,
+    ) -> Result<()> {
+        offers::instructions::add_offer(ctx, &offer_mint_metadata, fee_config)
+    }
+
+    pub fn allow_legacy_offer(ctx: Context<AllowLegacyOffer>) -> Result<()> {
+        offers::instructions::allow_legacy_offer(ctx)
     }
 
     pub fn place_order(
         ctx: Context<PlaceOrder>,
-        offer_index: u64,
         order_quantity: u64,
+        max_total_price: u64,
+        expires_at: Option<i64>,
     ) -> Result<()> {
-        orders::instructions::place_order(ctx, offer_index, order_quantity)
+        orders::instructions::place_order(ctx, order_quantity, max_total_price, expires_at)
+    }
+
+    pub fn cancel_order(ctx: Context<CancelOrder>, order_quantity: u64) -> Result<()> {
+        orders::instructions::cancel_order(ctx, order_quantity)
+    }
+
+    pub fn authorize_manager(ctx: Context<AuthorizeManager>, lots: u64) -> Result<()> {
+        authorize::instructions::authorize_manager(ctx, lots)
+    }
+
+    pub fn revoke_authorization(ctx: Context<RevokeAuthorization>) -> Result<()> {
+        authorize::instructions::revoke_authorization(ctx)
+    }
+
+    pub fn post_bond(ctx: Context<PostBond>, amount: u64) -> Result<()> {
+        bond::instructions::post_bond(ctx, amount)
+    }
+
+    pub fn withdraw_bond(ctx: Context<WithdrawBond>, amount: u64) -> Result<()> {
+        bond::instructions::withdraw_bond(ctx, amount)
     }
 
     pub fn prepare_lots(
         ctx: Context<PrepareLots>,
-        order_index: u64,
         order_quantity: u64,
         manager_for_lot: String,
     ) -> Result<()> {
-        lots::instructions::prepare_lots(ctx, order_index, order_quantity, manager_for_lot)
+        lots::instructions::prepare_lots(ctx, order_quantity, manager_for_lot)
     }
 
     pub fn confirm_lots(
         ctx: Context<ConfirmLots>,
         confirmed: bool,
-        order_index: u64,
-        lot_index: u64,
+        denial_reason: Option<u16>,
     ) -> Result<()> {
-        confirm::instructions::confirm_lots(ctx, confirmed, order_index, lot_index)
+        confirm::instructions::confirm_lots(ctx, confirmed, denial_reason)
     }
 
-    pub fn pay_harvest(
-        ctx: Context<PayHarvest>,
-        lot_index: u64,
-        manager_payment_for_harvest: u64,
-        profit: u64,
+    pub fn confirm_survivors(
+        ctx: Context<ConfirmLots>,
+        surviving_trees: u64,
+        refund_usdc: bool,
+    ) -> Result<()> {
+        confirm::instructions::confirm_survivors(ctx, surviving_trees, refund_usdc)
+    }
+
+    pub fn open_denial(
+        ctx: Context<OpenDenial>,
+        reason: u16,
+        evidence_hash: [u8; 32],
+    ) -> Result<()> {
+        disputes::instructions::open_denial(ctx, reason, evidence_hash)
+    }
+
+    pub fn contest_denial(ctx: Context<ContestDenial>, evidence_hash: [u8; 32]) -> Result<()> {
+        disputes::instructions::contest_denial(ctx, evidence_hash)
+    }
+
+    pub fn resolve_dispute(ctx: Context<ConfirmLots>, upheld: bool) -> Result<()> {
+        disputes::instructions::resolve_dispute(ctx, upheld)
+    }
+
+    pub fn mark_growing(ctx: Context<ManagerLotProgress>) -> Result<()> {
+        progress::instructions::mark_growing(ctx)
+    }
+
+    pub fn mark_ready(ctx: Context<ManagerLotProgress>) -> Result<()> {
+        progress::instructions::mark_ready(ctx)
+    }
+
+    pub fn mark_shipped(ctx: Context<AdminLotProgress>) -> Result<()> {
+        progress::instructions::mark_shipped(ctx)
+    }
+
+    pub fn retire_lot(ctx: Context<RetireLot>) -> Result<()> {
+        progress::instructions::retire_lot(ctx)
+    }
+
+    pub fn add_buyer(ctx: Context<AddBuyer>) -> Result<()> {
+        harvest::instructions::add_buyer(ctx)
+    }
+
+    pub fn remove_buyer(ctx: Context<RemoveBuyer>) -> Result<()> {
+        harvest::instructions::remove_buyer(ctx)
+    }
+
+    pub fn deposit_harvest(
+        ctx: Context<DepositHarvest>,
+        season: u16,
+        gross_proceeds: u64,
+    ) -> Result<()> {
+        harvest::instructions::deposit_harvest(ctx, season, gross_proceeds)
+    }
+
+    pub fn report_harvest_expense(
+        ctx: Context<ReportHarvestExpense>,
+        amount: u64,
+        receipt_hash: [u8; 32],
+    ) -> Result<()> {
+        harvest::instructions::report_harvest_expense(ctx, amount, receipt_hash)
+    }
+
+    pub fn retract_harvest_expense(ctx: Context<RetractHarvestExpense>) -> Result<()> {
+        harvest::instructions::retract_harvest_expense(ctx)
+    }
+
+    pub fn approve_expense_overrun(
+        ctx: Context<ApproveExpenseOverrun>,
+        approved_expenses: u64,
     ) -> Result<()> {
-        harvest::instructions::pay_harvest(ctx, lot_index, manager_payment_for_harvest, profit)
+        harvest::instructions::approve_expense_overrun(ctx, approved_expenses)
+    }
+
+    pub fn claim_harvest(ctx: Context<ClaimHarvest>) -> Result<()> {
+        harvest::instructions::claim_harvest(ctx)
+    }
+
+    pub fn open_holding(ctx: Context<OpenHolding>) -> Result<()> {
+        holdings::instructions::open_holding(ctx)
+    }
+
+    pub fn claim_harvest_share(ctx: Context<ClaimHarvestShare>) -> Result<()> {
+        holdings::instructions::claim_harvest_share(ctx)
     }
 
-    #[constant]
-    pub const TOTAL_OFFERS: u64 = Offers::TOTAL_OFFERS;
+    pub fn close_holding(ctx: Context<ClaimHarvestShare>) -> Result<()> {
+        holdings::instructions::close_holding(ctx)
+    }
 
-    #[constant]
-    pub const TOTAL_LOTS: u64 = Lots::TOTAL_LOTS;
+    pub fn thaw_lot_account(ctx: Context<ThawLotAccount>) -> Result<()> {
+        holdings::instructions::thaw_lot_account(ctx)
+    }
+
+    pub fn migrate_contract<'info>(
+        ctx: Context<'_, '_, '_, 'info, MigrateContract<'info>>,
+        contract_id: Pubkey,
+    ) -> Result<()> {
+        migrate::instructions::migrate_contract(ctx, contract_id)
+    }
+
+    pub fn migrate_mint(ctx: Context<MigrateMint>) -> Result<()> {
+        migrate::instructions::migrate_mint(ctx)
+    }
+
+    pub fn migrate_certification(ctx: Context<MigrateCertification>) -> Result<()> {
+        migrate::instructions::migrate_certification(ctx)
+    }
+
+    pub fn migrate_lot(ctx: Context<MigrateLot>) -> Result<()> {
+        migrate::instructions::migrate_lot(ctx)
+    }
+
+    pub fn migrate_order(
+        ctx: Context<MigrateOrder>,
+        order_quantity: u64,
+        price_per_tree: u64,
+    ) -> Result<()> {
+        migrate::instructions::migrate_order(ctx, order_quantity, price_per_tree)
+    }
 }
 
 #[derive(Accounts)]
+#[instruction(contract_id: Pubkey)]
 pub struct Initialize<'info> {
     #[account(
         init,
         payer = admin,
         space = Contract::LEN,
-        seeds = [b"contract", admin.key().as_ref()],
+        seeds = [b"contract", contract_id.as_ref()],
         bump
     )]
     pub contract: Account<'info, Contract>,
     #[account(mut)]
     pub certification_mint: Signer<'info>,
-    #[account(zero)]
-    pub offers_account: AccountLoader<'info, Offers>,
-    #[account(zero)]
-    pub lots_account: AccountLoader<'info, Lots>,
+    #[account(mut)]
+    pub offers_group: Signer<'info>,
+    #[account(mut)]
+    pub lots_group: Signer<'info>,
     #[account(mut)]
     pub admin: Signer<'info>,
     pub system_program: Program<'info, System>,
@@ -3,46 +3,64 @@ use anchor_spl::associated_token::AssociatedToken;
 use anchor_spl::token::Token;
 use anchor_spl::token_2022::Token2022;
 use anchor_spl::token_interface::{Mint, TokenAccount};
-use solana_program::program_option::COption;
+use session_keys::{Session, SessionToken};
 
-use crate::utils::{init_mint, InitMint, InitMintBumps, MintMetadata};
-use crate::{Contract, Offers, SeedlotContractsError};
+use crate::utils::{
+    init_mint, price_cents_2_usdc, InitMint, InitMintBumps, MintGroup, MintMetadata,
+};
+use crate::{
+    apply_bps, Certification, Contract, FeeConfig, HarvestTotals, LegacyOffer,
+    ManagerAuthorization, ManagerRecord, OfferConfig, OrderBalance, OrderReceipt,
+    SeedlotContractsError, MAX_MILESTONES,
+};
 
 pub mod instructions {
     use crate::utils::{
         burn_frozen_tokens_from, get_token_metadata, get_value, mint_frozen_tokens_to,
-        price_cents_2_usdc, price_string_2_cents, BurnFrozenTokensFrom, BurnFrozenTokensFromBumps,
-        MintFrozenTokensTo, MintFrozenTokensToBumps,
+        price_string_2_cents, BurnFrozenTokensFrom, BurnFrozenTokensFromBumps, MintFrozenTokensTo,
+        MintFrozenTokensToBumps,
     };
-    use crate::CertificationTier;
 
     use super::*;
     use anchor_spl::associated_token::{create, Create};
     use anchor_spl::token::{transfer, Transfer};
+    use session_keys::{session_auth_or, SessionError};
 
+    #[session_auth_or(
+        ctx.accounts.manager.key() == ctx.accounts.signer.key(),
+        SeedlotContractsError::ManagerMismatch
+    )]
     pub fn prepare_lots(
         ctx: Context<PrepareLots>,
-        order_index: u64,
         lot_quantity: u64,
         manager_for_lot: String,
     ) -> Result<()> {
-        // Make sure the manager is certified
-        require_gt!(
-            ctx.accounts.manager_certification_token_account.amount,
-            0,
-            SeedlotContractsError::ManagerNotCertified
-        );
-        require_gt!(
-            CertificationTier::Decertified as u64,
-            ctx.accounts.manager_certification_token_account.amount,
-            SeedlotContractsError::ManagerNotCertified
+        // The manager needs a bond posted before preparing lots, if the contract asks for one
+        require_gte!(
+            ctx.accounts.bond.as_ref().map_or(0, |bond| bond.amount),
+            ctx.accounts.contract.manager_bond,
+            SeedlotContractsError::InsufficientBond
         );
 
+        // Make sure the manager is certified and their tier has room for these lots
+        let certification = &ctx.accounts.certification;
+        let lot_limit = certification.lot_limit(
+            ctx.accounts
+                .contract
+                .lot_limit(certification.active_tier()?)?,
+        )?;
+        let manager_record = &mut ctx.accounts.manager_record;
+        manager_record.manager = ctx.accounts.manager.key();
+        manager_record.add_lots(lot_quantity, lot_limit)?;
+
         // Verify the order
-        ctx.accounts
-            .offers_account
-            .load()?
-            .verify_order(order_index, ctx.accounts.order_mint.key())?;
+        ctx.accounts.contract.verify_offer(
+            &ctx.accounts.order_mint,
+            ctx.accounts.legacy_offer.as_deref(),
+        )?;
+
+        // The user has to have authorized this manager for these lots
+        ctx.accounts.authorization.use_lots(lot_quantity)?;
 
         // Get order metadata
         let order_token_metadata = get_token_metadata(&ctx.accounts.order_mint)?;
@@ -52,6 +70,39 @@ pub mod instructions {
         let variety = get_value(&order_token_metadata, "variety")?;
         let price = get_value(&order_token_metadata, "price")?;
 
+        // Settle against what the user paid for these order tokens
+        let order_balance = &mut ctx.accounts.order_balance;
+        order_balance.user = ctx.accounts.user.key();
+        order_balance.order_mint = ctx.accounts.order_mint.key();
+        let (price_per_tree, trees_per_lot, order_id) = match &mut ctx.accounts.receipt {
+            Some(receipt) => {
+                receipt.settle(lot_quantity)?;
+                order_balance.remove(lot_quantity)?;
+                (
+                    receipt.price_per_tree,
+                    receipt.trees_per_lot,
+                    Some(receipt.order_id),
+                )
+            }
+            // Orders placed before receipts existed have no locked in price, so they settle at the
+            // offer's current one. Only order tokens not covered by a receipt can go this way.
+            None => {
+                require_gte!(
+                    ctx.accounts
+                        .user_order_token_account
+                        .amount
+                        .saturating_sub(order_balance.quantity),
+                    lot_quantity,
+                    SeedlotContractsError::InsufficientOrderBalance
+                );
+                (
+                    price_string_2_cents(&price)?,
+                    ctx.accounts.contract.trees_per_lot,
+                    None,
+                )
+            }
+        };
+
         burn_frozen_tokens_from(
             Context::new(
                 ctx.program_id,
@@ -84,9 +135,10 @@ pub mod instructions {
             Context::new(
                 ctx.program_id,
                 &mut InitMint {
-                    payer: ctx.accounts.manager.clone(),
+                    payer: ctx.accounts.signer.clone(),
                     contract: *ctx.accounts.contract.clone(),
                     mint: ctx.accounts.lot_mint.clone(),
+                    group: Some(ctx.accounts.lots_group.to_account_info()),
                     rent: ctx.accounts.rent.clone(),
                     token_program: ctx.accounts.token_program.to_account_info(),
                     system_program: ctx.accounts.system_program.to_account_info(),
@@ -97,13 +149,15 @@ pub mod instructions {
                 },
             ),
             &lot_mint_metadata,
+            MintGroup::Member,
+            Lot::DECIMALS,
         )?;
 
         // Create user lot token account
         create(CpiContext::new(
             ctx.accounts.associated_token_program.to_account_info(),
             Create {
-                payer: ctx.accounts.manager.to_account_info(),
+                payer: ctx.accounts.signer.to_account_info(),
                 associated_token: ctx.accounts.user_lot_token_account.to_account_info(),
                 authority: ctx.accounts.user.to_account_info(),
                 mint: ctx.accounts.lot_mint.to_account_info(),
@@ -128,33 +182,53 @@ pub mod instructions {
                     contract: ctx.bumps.contract,
                 },
             ),
-            lot_quantity,
+            lot_quantity * Lot::UNITS_PER_LOT,
         )?;
 
-        // Add the new lot to the lots account
-        ctx.accounts.lots_account.load_mut()?.push(Lot {
+        // Record the new lot in its own account
+        let fee_config = ctx.accounts.offer_config.fee_config(&ctx.accounts.contract);
+        ctx.accounts.lot.set_inner(Lot {
             mint: ctx.accounts.lot_mint.key(),
             // We save the original price per tree in cents so that we can pay the right amount later even if the metadata price changes.
-            original_price_per_tree: price_string_2_cents(&price)?,
-        })?;
+            original_price_per_tree: price_per_tree,
+            trees_per_lot,
+            order_id,
+            state: LotState::Preparation,
+            // The lot keeps the fees it was prepared under, even if the contract's change later.
+            fee_config,
+            fee_escrowed: true,
+            payout_schedule: ctx.accounts.offer_config.payout_schedule.clone(),
+            milestones_released: 0,
+            released: 0,
+            pending: 0,
+            held_units: 0,
+            profit_per_unit: 0,
+            unregistered_profit: 0,
+            holders_unclaimed: 0,
+            admin_unclaimed: 0,
+            manager_unclaimed: 0,
+            pending_expenses: 0,
+            expense_count: 0,
+            approved_expenses: 0,
+            carried_loss: 0,
+            harvest_totals: HarvestTotals::default(),
+        });
 
-        // Calculate and transfer 10% of USDC to manager
-        let price_in_usdc = price_cents_2_usdc(&price_string_2_cents(&price)?);
-        let total_price = price_in_usdc * lot_quantity * ctx.accounts.contract.trees_per_lot;
-        // TODO(techiejd): Remove hardcoded 10%, but for now it's a hackathon.
-        let manager_fee = total_price / 10; // 10% of the total price
+        // Hold the upfront fee in the lot's escrow until the admin confirms the lot
+        let total_price = ctx.accounts.lot.price(lot_quantity);
+        let manager_fee = fee_config.upfront_fee(total_price);
 
         transfer(
             CpiContext::new_with_signer(
                 ctx.accounts.token_program_standard.to_account_info(),
                 Transfer {
                     from: ctx.accounts.contract_usdc_token_account.to_account_info(),
-                    to: ctx.accounts.manager_usdc_token_account.to_account_info(),
+                    to: ctx.accounts.lot_escrow.to_account_info(),
                     authority: ctx.accounts.contract.to_account_info(),
                 },
                 &[&[
                     b"contract",
-                    ctx.accounts.contract.admin.as_ref(),
+                    ctx.accounts.contract.contract_id.as_ref(),
                     &[ctx.bumps.contract],
                 ]],
             ),
@@ -165,36 +239,58 @@ pub mod instructions {
     }
 }
 
-#[derive(Accounts)]
+#[derive(Accounts, Session)]
 pub struct PrepareLots<'info> {
     pub user: SystemAccount<'info>,
+    pub manager: SystemAccount<'info>,
+    // Either the manager or a session key they approved for this program. It pays for new accounts.
     #[account(mut)]
-    pub manager: Signer<'info>,
+    pub signer: Signer<'info>,
+    #[session(signer = signer, authority = manager.key())]
+    pub session_token: Option<Account<'info, SessionToken>>,
     #[account(
         mut,
-        seeds = [b"contract", contract.admin.as_ref()],
+        seeds = [b"contract", contract.contract_id.as_ref()],
         bump,
-        has_one = offers_account,
-        has_one = lots_account,
+        has_one = lots_group,
         has_one = usdc_mint,
-        has_one = certification_mint,
     )]
     pub contract: Box<Account<'info, Contract>>,
     #[account(
-        constraint = certification_mint.mint_authority == COption::Some(contract.key())
+        seeds = [b"certification", contract.key().as_ref(), manager.key().as_ref()],
+        bump
     )]
-    pub certification_mint: Box<InterfaceAccount<'info, Mint>>,
+    pub certification: Box<Account<'info, Certification>>,
     #[account(
-        associated_token::mint = certification_mint,
-        associated_token::authority = manager,
-        associated_token::token_program = token_program,
+        init_if_needed,
+        payer = signer,
+        space = ManagerRecord::LEN,
+        seeds = [b"manager", contract.key().as_ref(), manager.key().as_ref()],
+        bump
     )]
-    pub manager_certification_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
-    pub offers_account: AccountLoader<'info, Offers>,
-    #[account(mut)]
-    pub lots_account: AccountLoader<'info, Lots>,
+    pub manager_record: Box<Account<'info, ManagerRecord>>,
+    // Posted with post_bond, only needed while the contract's manager_bond is above 0.
+    #[account(
+        seeds = [b"bond", contract.key().as_ref(), manager.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = contract,
+        token::token_program = token_program_standard,
+    )]
+    pub bond: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
     #[account(mut)]
     pub order_mint: InterfaceAccount<'info, Mint>,
+    // Only for offers added before groups, see allow_legacy_offer.
+    #[account(
+        seeds = [b"legacy_offer", contract.key().as_ref(), order_mint.key().as_ref()],
+        bump
+    )]
+    pub legacy_offer: Option<Box<Account<'info, LegacyOffer>>>,
+    #[account(
+        seeds = [b"offer_config", contract.key().as_ref(), order_mint.key().as_ref()],
+        bump
+    )]
+    pub offer_config: Box<Account<'info, OfferConfig>>,
     #[account(
         mut,
         associated_token::mint = order_mint,
@@ -202,8 +298,52 @@ pub struct PrepareLots<'info> {
         associated_token::token_program = token_program,
     )]
     pub user_order_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        space = OrderBalance::LEN,
+        seeds = [b"order", contract.key().as_ref(), order_mint.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub order_balance: Box<Account<'info, OrderBalance>>,
+    // Orders placed before receipts existed don't have one.
+    #[account(
+        mut,
+        seeds = [
+            b"receipt",
+            contract.key().as_ref(),
+            order_mint.key().as_ref(),
+            user.key().as_ref(),
+            &receipt.order_id.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub receipt: Option<Box<Account<'info, OrderReceipt>>>,
+    #[account(
+        mut,
+        seeds = [
+            b"authorization",
+            contract.key().as_ref(),
+            order_mint.key().as_ref(),
+            user.key().as_ref(),
+            manager.key().as_ref(),
+        ],
+        bump
+    )]
+    pub authorization: Box<Account<'info, ManagerAuthorization>>,
     #[account(mut)]
     pub lot_mint: Signer<'info>,
+    /// CHECK: Checked against the contract; the token program checks it is a group.
+    #[account(mut)]
+    pub lots_group: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = signer,
+        space = Lot::LEN,
+        seeds = [b"lot", contract.key().as_ref(), lot_mint.key().as_ref()],
+        bump
+    )]
+    pub lot: Box<Account<'info, Lot>>,
     /// CHECK: I'd like to initialize it here but can't because the lot_mint is not yet initialized.
     #[account(mut)]
     pub user_lot_token_account: UncheckedAccount<'info>,
@@ -216,13 +356,15 @@ pub struct PrepareLots<'info> {
     )]
     pub contract_usdc_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
-        init_if_needed,
-        payer = manager,
-        associated_token::mint = usdc_mint,
-        associated_token::authority = manager,
-        associated_token::token_program = token_program_standard,
+        init,
+        payer = signer,
+        seeds = [b"lot_escrow", contract.key().as_ref(), lot_mint.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = contract,
+        token::token_program = token_program_standard,
     )]
-    pub manager_usdc_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub lot_escrow: Box<InterfaceAccount<'info, TokenAccount>>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub token_program: Program<'info, Token2022>,
     pub token_program_standard: Program<'info, Token>,
@@ -230,21 +372,284 @@ pub struct PrepareLots<'info> {
     pub rent: Sysvar<'info, Rent>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u8)]
 pub enum LotState {
-    Preparation,
-    Growing,
-    Ready,
-    Shipped,
+    Preparation = 0, // Prepared by the manager, waiting on the admin's confirmation
+    Growing = 1,     // Confirmed by the admin (or shipped last season and growing again)
+    Ready = 2,       // The manager says the harvest is ready
+    Shipped = 3,     // The admin says the harvest has shipped
+    Retired = 4,     // The admin took the lot out of production so it no longer counts
+    Disputed = 5,    // The admin denied the lot and the arbitrator has yet to rule on it
 }
 
-#[zero_copy]
+impl LotState {
+    pub const LEN: usize = 1;
+
+    // Lots only start growing through `Lot::confirm`, the rest have their own instructions.
+    pub fn can_transition_to(&self, next: LotState) -> bool {
+        matches!(
+            (self, next),
+            (LotState::Preparation, LotState::Disputed)
+                | (LotState::Growing, LotState::Ready)
+                | (LotState::Ready, LotState::Shipped)
+                | (LotState::Shipped, LotState::Growing)
+                | (LotState::Shipped, LotState::Retired)
+        )
+    }
+}
+
+impl TryFrom<u8> for LotState {
+    type Error = anchor_lang::error::Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        match value {
+            0 => Ok(LotState::Preparation),
+            1 => Ok(LotState::Growing),
+            2 => Ok(LotState::Ready),
+            3 => Ok(LotState::Shipped),
+            4 => Ok(LotState::Retired),
+            5 => Ok(LotState::Disputed),
+            _ => err!(SeedlotContractsError::InvalidLotState),
+        }
+    }
+}
+
+#[account]
 pub struct Lot {
     pub mint: Pubkey,
     pub original_price_per_tree: u64,
+    pub trees_per_lot: u64,
+    // The receipt the lot's order tokens were settled against, which gets them back if the lot is
+    // denied or loses trees. None for orders placed before receipts.
+    pub order_id: Option<u64>,
+    pub state: LotState,
+    pub fee_config: FeeConfig,
+    // Whether the upfront fee is held in the lot's escrow, seeded by [b"lot_escrow", contract,
+    // lot_mint]. Lots prepared before escrows paid it to the manager straight away.
+    pub fee_escrowed: bool,
+    // The offer's payout schedule when the lot was prepared, and how far along it the lot is.
+    pub payout_schedule: Vec<u16>,
+    pub milestones_released: u8,
+    // What the manager has been paid and what is still waiting on milestones.
+    pub released: u64,
+    pub pending: u64,
+    // Lot tokens registered in holdings.
+    pub held_units: u64,
+    // The holders' harvest profit per unit of the lot so far, scaled up by PROFIT_PRECISION.
+    pub profit_per_unit: u128,
+    // What units outside holdings have earned and not yet brought into a holding. Units that
+    // change hands can't be told apart, so registering units never take more than this.
+    pub unregistered_profit: u64,
+    // Harvest proceeds sitting in the lot's harvest vault until their owners claim them.
+    pub holders_unclaimed: u64,
+    pub admin_unclaimed: u64,
+    pub manager_unclaimed: u64,
+    // Expenses the manager has reported for the next harvest, which come out of its proceeds.
+    pub pending_expenses: u64,
+    pub expense_count: u16,
+    // How much of them the admin let go over the expense cap, see approve_expense_overrun.
+    pub approved_expenses: u64,
+    // Losses from earlier harvests that later profits pay back to the manager before splitting.
+    pub carried_loss: u64,
+    pub harvest_totals: HarvestTotals,
 }
 
 impl Lot {
+    pub const LEN: usize = 8 // Discriminator
+     + 32 // mint
+     + 8 // original_price_per_tree
+     + 8 // trees_per_lot
+     + 1 + 8 // order_id
+     + LotState::LEN // state
+     + FeeConfig::LEN // fee_config
+     + 1 // fee_escrowed
+     + 4 + 2 * MAX_MILESTONES // payout_schedule
+     + 1 // milestones_released
+     + 8 // released
+     + 8 // pending
+     + 8 // held_units
+     + 16 // profit_per_unit
+     + 8 // unregistered_profit
+     + 8 // holders_unclaimed
+     + 8 // admin_unclaimed
+     + 8 // manager_unclaimed
+     + 8 // pending_expenses
+     + 2 // expense_count
+     + 8 // approved_expenses
+     + 8 // carried_loss
+     + HarvestTotals::LEN; // harvest_totals
+
+    // Lot tokens are split into units so many holders can share a lot.
+    pub const DECIMALS: u8 = 6;
+    pub const UNITS_PER_LOT: u64 = 1_000_000;
+    pub const PROFIT_PRECISION: u128 = 1_000_000_000_000;
+
+    // Lot mints from before units have no decimals, so each of their tokens is a whole lot.
+    pub fn units_per_lot(lot_mint: &InterfaceAccount<Mint>) -> u64 {
+        10u64.pow(lot_mint.decimals.into())
+    }
+
+    // Lots that lost some of their trees in confirm_survivors still count as a whole lot.
+    pub fn lots_in(lot_mint: &InterfaceAccount<Mint>) -> u64 {
+        lot_mint.supply.div_ceil(Lot::units_per_lot(lot_mint))
+    }
+
+    // Spreads the holders' share of a harvest across the lot's `supply`, setting aside the share
+    // of units outside holdings until they're registered.
+    pub fn add_profit(&mut self, profit: u64, supply: u64) -> Result<()> {
+        let per_unit = (profit as u128)
+            .checked_mul(Lot::PROFIT_PRECISION)
+            .and_then(|profit| profit.checked_div(supply as u128))
+            .ok_or(SeedlotContractsError::MathOverflow)?;
+        self.profit_per_unit = self
+            .profit_per_unit
+            .checked_add(per_unit)
+            .ok_or(SeedlotContractsError::MathOverflow)?;
+        let unregistered = supply
+            .checked_sub(self.held_units)
+            .ok_or(SeedlotContractsError::MathOverflow)?;
+        let unregistered_share = per_unit
+            .checked_mul(unregistered as u128)
+            .map(|share| share / Lot::PROFIT_PRECISION)
+            .and_then(|share| u64::try_from(share).ok())
+            .ok_or(SeedlotContractsError::MathOverflow)?;
+        self.unregistered_profit = self
+            .unregistered_profit
+            .checked_add(unregistered_share)
+            .ok_or(SeedlotContractsError::MathOverflow)?;
+        Ok(())
+    }
+
+    // Registers `units` in a holding that was last paid through `paid_through` and returns what
+    // they earned outside holdings since.
+    pub fn hold(&mut self, units: u64, paid_through: u128) -> Result<u64> {
+        let earned = self
+            .profit_per_unit
+            .checked_sub(paid_through)
+            .and_then(|per_unit| per_unit.checked_mul(units as u128))
+            .map(|earned| earned / Lot::PROFIT_PRECISION)
+            .ok_or(SeedlotContractsError::MathOverflow)?;
+        let earned = u64::try_from(earned)
+            .unwrap_or(u64::MAX)
+            .min(self.unregistered_profit);
+        self.unregistered_profit -= earned;
+        self.held_units = self
+            .held_units
+            .checked_add(units)
+            .ok_or(SeedlotContractsError::MathOverflow)?;
+        Ok(earned)
+    }
+
+    pub fn release(&mut self, units: u64) -> Result<()> {
+        self.held_units = self
+            .held_units
+            .checked_sub(units)
+            .ok_or(SeedlotContractsError::MathOverflow)?;
+        Ok(())
+    }
+
+    // Adds a harvest's loss to the carried loss, or pays as much of it off as the harvest's profit
+    // covers. Returns what was paid off and the profit left to split.
+    pub fn carry_loss(&mut self, profit: i64) -> (u64, u64) {
+        if profit < 0 {
+            self.carried_loss += profit.unsigned_abs();
+            return (0, 0);
+        }
+        let recovered = self.carried_loss.min(profit as u64);
+        self.carried_loss -= recovered;
+        (recovered, profit as u64 - recovered)
+    }
+
+    // What the user paid for `lots` of this lot's tokens.
+    pub fn price(&self, lots: u64) -> u64 {
+        self.price_of_trees(lots * self.trees_per_lot)
+    }
+
+    pub fn price_of_trees(&self, trees: u64) -> u64 {
+        price_cents_2_usdc(&self.original_price_per_tree) * trees
+    }
+
+    // Splits what's left of the price after the upfront fee between confirmation and the
+    // milestones. Returns what the manager gets at confirmation.
+    pub fn start_payouts(&mut self, total_price: u64) -> u64 {
+        let upfront_fee = self.fee_config.upfront_fee(total_price);
+        let remaining = total_price - upfront_fee;
+        let pending: u64 = self
+            .payout_schedule
+            .iter()
+            .map(|bps| apply_bps(remaining, *bps))
+            .sum();
+        self.released = total_price - pending;
+        self.pending = pending;
+        remaining - pending
+    }
+
+    // Returns what the next milestone pays. Each takes its share of what is still pending so
+    // the last one also picks up any rounding.
+    pub fn release_milestone(&mut self, milestone: u8) -> Result<u64> {
+        require_eq!(
+            milestone,
+            self.milestones_released,
+            SeedlotContractsError::MilestoneOutOfOrder
+        );
+        let index = milestone as usize;
+        require_gt!(
+            self.payout_schedule.len(),
+            index,
+            SeedlotContractsError::MilestoneOutOfOrder
+        );
+        let pending_bps: u64 = self.payout_schedule[index..]
+            .iter()
+            .map(|bps| *bps as u64)
+            .sum();
+        let amount = (self.pending as u128 * self.payout_schedule[index] as u128
+            / pending_bps as u128) as u64;
+        self.milestones_released += 1;
+        self.pending -= amount;
+        self.released += amount;
+        Ok(amount)
+    }
+
+    pub fn require_state(&self, expected: LotState) -> Result<()> {
+        require!(
+            self.state == expected,
+            SeedlotContractsError::InvalidLotState
+        );
+        Ok(())
+    }
+
+    pub fn transition_to(&mut self, next: LotState) -> Result<()> {
+        require!(
+            self.state.can_transition_to(next),
+            SeedlotContractsError::InvalidLotStateTransition
+        );
+        self.state = next;
+        Ok(())
+    }
+
+    // Moves a prepared lot into its first season. Only confirm_lots, confirm_survivors and an
+    // overturned dispute in resolve_dispute may do this.
+    pub fn confirm(&mut self) -> Result<()> {
+        require!(
+            matches!(self.state, LotState::Preparation | LotState::Disputed),
+            SeedlotContractsError::InvalidLotStateTransition
+        );
+        self.state = LotState::Growing;
+        Ok(())
+    }
+}
+
+// Lots used to live in one big array, which capped them at 10,000 and shifted indexes on removal.
+// It is only kept around so that `migrate_lot` can move existing entries into their own `Lot` PDAs.
+// Their state lives in the lot mint's metadata.
+#[zero_copy]
+pub struct LegacyLot {
+    pub mint: Pubkey,
+    pub original_price_per_tree: u64,
+}
+
+impl LegacyLot {
     pub const LEN: usize = 32 // key
      + 8; // original_price_per_tree.
 }
@@ -256,7 +661,7 @@ const _TOTAL_LOTS: usize = 10_000;
 pub struct Lots {
     pub owner: Pubkey,
     pub tail: u64,
-    pub lots: [Lot; _TOTAL_LOTS],
+    pub lots: [LegacyLot; _TOTAL_LOTS],
 }
 
 impl Lots {
@@ -264,26 +669,23 @@ impl Lots {
     pub const LEN: usize = 8 // Discriminator
     + 32 // owner
     + 8 // tail
-    + (Lot::LEN * Self::TOTAL_LOTS as usize);
+    + (LegacyLot::LEN * Self::TOTAL_LOTS as usize);
 
-    pub fn push(&mut self, lot: Lot) -> Result<()> {
-        require_neq!(self.tail, Self::TOTAL_LOTS, SeedlotContractsError::LotsFull);
-        self.lots[self.tail as usize] = lot;
-        self.tail += 1;
-        Ok(())
+    pub fn find(&self, mint: &Pubkey) -> Result<u64> {
+        (0..self.tail)
+            .find(|&i| self.lots[i as usize].mint == *mint)
+            .ok_or_else(|| error!(SeedlotContractsError::InvalidLotIndex))
     }
 
-    pub fn get(&self, index: u64) -> Result<Lot> {
+    pub fn get(&self, index: u64) -> Result<LegacyLot> {
         require!(index < self.tail, SeedlotContractsError::InvalidLotIndex);
         Ok(self.lots[index as usize])
     }
 
     pub fn remove(&mut self, index: u64) -> Result<()> {
-        // It's likely that we only remove a lot near the end of the array so not so expensive.
+        // Order no longer matters once lots have their own PDAs, so swap the last entry in.
         require!(index < self.tail, SeedlotContractsError::InvalidLotIndex);
-        for i in index..self.tail - 1 {
-            self.lots[i as usize] = self.lots[(i + 1) as usize];
-        }
+        self.lots[index as usize] = self.lots[(self.tail - 1) as usize];
         self.tail -= 1;
         Ok(())
     }
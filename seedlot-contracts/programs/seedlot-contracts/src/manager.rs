@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+
+use crate::SeedlotContractsError;
+
+// Per manager bookkeeping, seeded by [b"manager", contract, manager].
+#[account]
+pub struct ManagerRecord {
+    pub manager: Pubkey,
+    // Lots (lot tokens) the manager has prepared that have not been denied or retired yet.
+    pub active_lots: u64,
+}
+
+impl ManagerRecord {
+    pub const LEN: usize = 8 // Discriminator
+     + 32 // manager
+     + 8; // active_lots
+
+    pub fn add_lots(&mut self, lots: u64, lot_limit: u64) -> Result<()> {
+        let active_lots = self
+            .active_lots
+            .checked_add(lots)
+            .ok_or(SeedlotContractsError::LotLimitExceeded)?;
+        require_gte!(
+            lot_limit,
+            active_lots,
+            SeedlotContractsError::LotLimitExceeded
+        );
+        self.active_lots = active_lots;
+        Ok(())
+    }
+
+    pub fn remove_lots(&mut self, lots: u64) {
+        // Lots prepared before managers were tracked were never counted, so don't underflow.
+        self.active_lots = self.active_lots.saturating_sub(lots);
+    }
+}
@@ -0,0 +1,467 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::Token;
+use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_interface::{Mint, TokenAccount};
+
+use crate::utils::{get_token_metadata, get_value, init_mint, InitMint, InitMintBumps, MintGroup};
+use crate::{
+    lots_group_metadata, offers_group_metadata, Certification, CertificationTier, Contract,
+    FeeConfig, HarvestTotals, LegacyContract, LegacyOffer, Lot, LotState, Lots, ManagerRecord,
+    OrderBalance, OrderReceipt, SeedlotContractsError, StrikePolicy,
+};
+
+pub mod instructions {
+    use super::*;
+    use crate::progress::verify_manager;
+    use anchor_spl::token::{transfer, Transfer};
+    use anchor_spl::token_2022::spl_token_2022::instruction::AuthorityType;
+    use anchor_spl::token_2022::{set_authority, SetAuthority};
+    use anchor_spl::token_interface::spl_pod::optional_keys::OptionalNonZeroPubkey;
+    use anchor_spl::token_interface::{
+        token_metadata_update_authority, TokenMetadataUpdateAuthority,
+    };
+
+    // Moves a contract seeded by [b"contract", admin] to one seeded by [b"contract", contract_id].
+    // Its USDC comes along and the old account is closed. Every mint still belongs to the old PDA
+    // until it goes through `migrate_mint`, and lots then go through `migrate_lot`. Old offers
+    // can't join the new offers group after the fact, so they go through `allow_legacy_offer`,
+    // and orders placed before receipts go through `migrate_order`.
+    pub fn migrate_contract<'info>(
+        ctx: Context<'_, '_, '_, 'info, MigrateContract<'info>>,
+        contract_id: Pubkey,
+    ) -> Result<()> {
+        let legacy_contract = LegacyContract::try_from_account_info(&ctx.accounts.legacy_contract)?;
+        require_keys_eq!(
+            legacy_contract.admin,
+            ctx.accounts.admin.key(),
+            SeedlotContractsError::NotALegacyContract
+        );
+        require_keys_eq!(
+            legacy_contract.usdc_mint,
+            ctx.accounts.usdc_mint.key(),
+            SeedlotContractsError::NotALegacyContract
+        );
+        Contract::validate_trees_per_lot(legacy_contract.trees_per_lot)?;
+
+        // Must be done before initializing the mints because they rely on context.contract_id
+        ctx.accounts.contract.contract_id = contract_id;
+        for (mint, mint_metadata) in [
+            (&ctx.accounts.offers_group, offers_group_metadata()),
+            (&ctx.accounts.lots_group, lots_group_metadata()),
+        ] {
+            init_mint(
+                Context::new(
+                    ctx.program_id,
+                    &mut InitMint {
+                        payer: ctx.accounts.admin.clone(),
+                        contract: (*ctx.accounts.contract).clone(),
+                        mint: mint.clone(),
+                        group: None,
+                        rent: ctx.accounts.rent.clone(),
+                        token_program: ctx.accounts.token_program.to_account_info(),
+                        system_program: ctx.accounts.system_program.to_account_info(),
+                    },
+                    &[],
+                    InitMintBumps {
+                        contract: ctx.bumps.contract,
+                    },
+                ),
+                &mint_metadata,
+                MintGroup::Group,
+                0,
+            )?;
+        }
+
+        let contract = &mut ctx.accounts.contract;
+        contract.admin = legacy_contract.admin;
+        contract.pending_admin = None;
+        contract.trees_per_lot = legacy_contract.trees_per_lot;
+        contract.certification_mint = legacy_contract.certification_mint;
+        contract.offers_group = ctx.accounts.offers_group.key();
+        contract.lots_group = ctx.accounts.lots_group.key();
+        contract.usdc_mint = legacy_contract.usdc_mint;
+        contract.usdc_token_account = ctx.accounts.contract_usdc_token_account.key();
+        contract.legacy_lots_account = legacy_contract.lots_account;
+        contract.legacy_admin = legacy_contract.admin;
+        contract.lot_limits = CertificationTier::DEFAULT_LOT_LIMITS;
+        contract.fee_config = FeeConfig::LEGACY;
+        contract.strike_policy = StrikePolicy::DEFAULT;
+        contract.manager_bond = 0;
+        contract.arbitrator = None;
+        contract.contest_window = Contract::DEFAULT_CONTEST_WINDOW;
+        contract.max_expense_bps = Contract::DEFAULT_MAX_EXPENSE_BPS;
+        contract.open_disputes = 0;
+
+        // Move the USDC over
+        transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program_standard.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.legacy_usdc_token_account.to_account_info(),
+                    to: ctx.accounts.contract_usdc_token_account.to_account_info(),
+                    authority: ctx.accounts.legacy_contract.to_account_info(),
+                },
+                &[&[
+                    b"contract",
+                    ctx.accounts.admin.key().as_ref(),
+                    &[ctx.bumps.legacy_contract],
+                ]],
+            ),
+            ctx.accounts.legacy_usdc_token_account.amount,
+        )?;
+
+        // Close the old contract account so it can't be migrated twice
+        let legacy_contract = ctx.accounts.legacy_contract.to_account_info();
+        let admin = ctx.accounts.admin.to_account_info();
+        **admin.try_borrow_mut_lamports()? += legacy_contract.lamports();
+        **legacy_contract.try_borrow_mut_lamports()? = 0;
+        legacy_contract.assign(&System::id());
+        legacy_contract.realloc(0, false)?;
+        Ok(())
+    }
+
+    // Hands every authority the old contract PDA has over a mint to the migrated contract.
+    pub fn migrate_mint(ctx: Context<MigrateMint>) -> Result<()> {
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"contract",
+            ctx.accounts.contract.legacy_admin.as_ref(),
+            &[ctx.bumps.legacy_contract],
+        ]];
+        let new_authority = ctx.accounts.contract.key();
+
+        token_metadata_update_authority(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TokenMetadataUpdateAuthority {
+                    token_program_id: ctx.accounts.token_program.to_account_info(),
+                    metadata: ctx.accounts.mint.to_account_info(),
+                    current_authority: ctx.accounts.legacy_contract.to_account_info(),
+                    new_authority: ctx.accounts.contract.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            OptionalNonZeroPubkey::try_from(Some(new_authority))?,
+        )?;
+
+        for authority_type in [
+            AuthorityType::MetadataPointer,
+            AuthorityType::PermanentDelegate,
+            AuthorityType::CloseMint,
+            AuthorityType::FreezeAccount,
+            AuthorityType::MintTokens,
+        ] {
+            set_authority(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    SetAuthority {
+                        current_authority: ctx.accounts.legacy_contract.to_account_info(),
+                        account_or_mint: ctx.accounts.mint.to_account_info(),
+                    },
+                    signer_seeds,
+                ),
+                authority_type,
+                Some(new_authority),
+            )?;
+        }
+        Ok(())
+    }
+
+    // Gives a manager certified before records existed a record matching their badge.
+    pub fn migrate_certification(ctx: Context<MigrateCertification>) -> Result<()> {
+        let certification = &mut ctx.accounts.certification;
+        certification.init_from_badge(
+            ctx.accounts.manager.key(),
+            &ctx.accounts.manager_certification_token_account,
+        );
+        certification.issuer = ctx.accounts.admin.key();
+        certification.issued_at = Clock::get()?.unix_timestamp;
+        certification.emit_change();
+        Ok(())
+    }
+
+    // Moves one lot out of the legacy lots account into its own PDA. Lots still waiting on
+    // confirmation can be denied and slash the manager's bond, so they count as the manager's
+    // open lots from here on.
+    pub fn migrate_lot(ctx: Context<MigrateLot>) -> Result<()> {
+        verify_manager(&ctx.accounts.lot_mint, &ctx.accounts.manager.key())?;
+        let legacy_lots = &mut ctx.accounts.legacy_lots_account.load_mut()?;
+        let index = legacy_lots.find(&ctx.accounts.lot_mint.key())?;
+        let legacy_lot = legacy_lots.get(index)?;
+        // Legacy lots kept their state in the lot mint's metadata, "0" until confirm_lots set "1".
+        let lot_token_metadata = get_token_metadata(&ctx.accounts.lot_mint)?;
+        let state = LotState::try_from(
+            get_value(&lot_token_metadata, "state")?
+                .parse::<u8>()
+                .map_err(|_| SeedlotContractsError::InvalidLotState)?,
+        )?;
+        ctx.accounts.lot.set_inner(Lot {
+            mint: legacy_lot.mint,
+            original_price_per_tree: legacy_lot.original_price_per_tree,
+            trees_per_lot: ctx.accounts.contract.trees_per_lot,
+            order_id: None,
+            state,
+            // Legacy lots already paid the old hardcoded upfront fee.
+            fee_config: FeeConfig::LEGACY,
+            fee_escrowed: false,
+            payout_schedule: Vec::new(),
+            milestones_released: 0,
+            released: 0,
+            pending: 0,
+            held_units: 0,
+            profit_per_unit: 0,
+            unregistered_profit: 0,
+            holders_unclaimed: 0,
+            admin_unclaimed: 0,
+            manager_unclaimed: 0,
+            pending_expenses: 0,
+            expense_count: 0,
+            approved_expenses: 0,
+            carried_loss: 0,
+            harvest_totals: HarvestTotals::default(),
+        });
+
+        let manager_record = &mut ctx.accounts.manager_record;
+        manager_record.manager = ctx.accounts.manager.key();
+        if state == LotState::Preparation {
+            manager_record.active_lots += Lot::lots_in(&ctx.accounts.lot_mint);
+        }
+        legacy_lots.remove(index)
+    }
+
+    // Gives order tokens bought before receipts existed a receipt at the price per tree, in
+    // cents, that the user paid for them, so they're cancelled and settled like any other order.
+    pub fn migrate_order(
+        ctx: Context<MigrateOrder>,
+        order_quantity: u64,
+        price_per_tree: u64,
+    ) -> Result<()> {
+        ctx.accounts
+            .contract
+            .verify_offer(&ctx.accounts.order_mint, ctx.accounts.legacy_offer.as_ref())?;
+        require_gt!(price_per_tree, 0, SeedlotContractsError::InvalidPrice);
+        let order_balance = &mut ctx.accounts.order_balance;
+        // Only order tokens that no receipt covers yet
+        require_gte!(
+            ctx.accounts
+                .user_order_token_account
+                .amount
+                .saturating_sub(order_balance.quantity),
+            order_quantity,
+            SeedlotContractsError::InsufficientOrderBalance
+        );
+        order_balance.user = ctx.accounts.user.key();
+        order_balance.order_mint = ctx.accounts.order_mint.key();
+        let order_id = order_balance.add_order(order_quantity)?;
+        ctx.accounts.receipt.set_inner(OrderReceipt {
+            buyer: ctx.accounts.user.key(),
+            order_mint: ctx.accounts.order_mint.key(),
+            order_id,
+            quantity: order_quantity,
+            quantity_remaining: order_quantity,
+            price_per_tree,
+            trees_per_lot: ctx.accounts.contract.trees_per_lot,
+            placed_at: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(contract_id: Pubkey)]
+pub struct MigrateContract<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    /// CHECK: Read by hand in `LegacyContract::try_from_account_info` since it has the old layout.
+    #[account(
+        mut,
+        seeds = [b"contract", admin.key().as_ref()],
+        bump,
+        owner = crate::ID,
+    )]
+    pub legacy_contract: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = admin,
+        space = Contract::LEN,
+        seeds = [b"contract", contract_id.as_ref()],
+        bump
+    )]
+    pub contract: Box<Account<'info, Contract>>,
+    #[account(mut)]
+    pub offers_group: Signer<'info>,
+    #[account(mut)]
+    pub lots_group: Signer<'info>,
+    pub usdc_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = legacy_contract,
+        associated_token::token_program = token_program_standard
+    )]
+    pub legacy_usdc_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init,
+        payer = admin,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = contract,
+        associated_token::token_program = token_program_standard
+    )]
+    pub contract_usdc_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Program<'info, Token2022>,
+    pub token_program_standard: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateMint<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"contract", contract.contract_id.as_ref()],
+        bump,
+        has_one = admin,
+        constraint = contract.legacy_admin != Pubkey::default()
+            @ SeedlotContractsError::NotALegacyContract,
+    )]
+    pub contract: Account<'info, Contract>,
+    /// CHECK: Only signs; it was closed by `migrate_contract`.
+    #[account(
+        seeds = [b"contract", contract.legacy_admin.as_ref()],
+        bump,
+    )]
+    pub legacy_contract: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        mint::authority = legacy_contract,
+        mint::token_program = token_program,
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Program<'info, Token2022>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateCertification<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"contract", contract.contract_id.as_ref()],
+        bump,
+        has_one = admin,
+        has_one = certification_mint,
+    )]
+    pub contract: Account<'info, Contract>,
+    pub manager: SystemAccount<'info>,
+    pub certification_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        associated_token::mint = certification_mint,
+        associated_token::authority = manager,
+        associated_token::token_program = token_program,
+    )]
+    pub manager_certification_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = admin,
+        space = Certification::LEN,
+        seeds = [b"certification", contract.key().as_ref(), manager.key().as_ref()],
+        bump
+    )]
+    pub certification: Account<'info, Certification>,
+    pub token_program: Program<'info, Token2022>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateLot<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"contract", contract.contract_id.as_ref()],
+        bump,
+        has_one = admin,
+        has_one = legacy_lots_account,
+    )]
+    pub contract: Account<'info, Contract>,
+    #[account(mut)]
+    pub legacy_lots_account: AccountLoader<'info, Lots>,
+    #[account(
+        mint::authority = contract,
+        mint::token_program = token_program,
+    )]
+    pub lot_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = admin,
+        space = Lot::LEN,
+        seeds = [b"lot", contract.key().as_ref(), lot_mint.key().as_ref()],
+        bump
+    )]
+    pub lot: Account<'info, Lot>,
+    // The manager in the lot mint's metadata.
+    pub manager: SystemAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = ManagerRecord::LEN,
+        seeds = [b"manager", contract.key().as_ref(), manager.key().as_ref()],
+        bump
+    )]
+    pub manager_record: Account<'info, ManagerRecord>,
+    pub token_program: Program<'info, Token2022>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateOrder<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"contract", contract.contract_id.as_ref()],
+        bump,
+        has_one = admin,
+    )]
+    pub contract: Account<'info, Contract>,
+    pub user: SystemAccount<'info>,
+    #[account(
+        mint::authority = contract,
+        mint::token_program = token_program,
+    )]
+    pub order_mint: InterfaceAccount<'info, Mint>,
+    // Only for offers added before groups, see allow_legacy_offer.
+    #[account(
+        seeds = [b"legacy_offer", contract.key().as_ref(), order_mint.key().as_ref()],
+        bump
+    )]
+    pub legacy_offer: Option<Account<'info, LegacyOffer>>,
+    #[account(
+        associated_token::mint = order_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program,
+    )]
+    pub user_order_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = OrderBalance::LEN,
+        seeds = [b"order", contract.key().as_ref(), order_mint.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub order_balance: Box<Account<'info, OrderBalance>>,
+    #[account(
+        init,
+        payer = admin,
+        space = OrderReceipt::LEN,
+        seeds = [
+            b"receipt",
+            contract.key().as_ref(),
+            order_mint.key().as_ref(),
+            user.key().as_ref(),
+            &order_balance.orders_placed.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub receipt: Box<Account<'info, OrderReceipt>>,
+    pub token_program: Program<'info, Token2022>,
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,189 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::Token;
+use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_interface::{Mint, TokenAccount};
+
+use crate::progress::verify_manager;
+use crate::{Contract, Lot, LotState, SeedlotContractsError};
+
+pub mod instructions {
+    use super::*;
+    use anchor_spl::token::{transfer, Transfer};
+
+    pub fn add_inspector(ctx: Context<AddInspector>) -> Result<()> {
+        ctx.accounts.inspector_record.inspector = ctx.accounts.inspector.key();
+        Ok(())
+    }
+
+    pub fn remove_inspector(_ctx: Context<RemoveInspector>) -> Result<()> {
+        Ok(())
+    }
+
+    // Pays the manager the next milestone of the lot's payout schedule once the admin or an
+    // inspector attests to it.
+    pub fn release_milestone(
+        ctx: Context<ReleaseMilestone>,
+        milestone: u8,
+        evidence_hash: [u8; 32],
+    ) -> Result<()> {
+        let attester = ctx.accounts.attester.key();
+        require!(
+            attester == ctx.accounts.contract.admin || ctx.accounts.inspector_record.is_some(),
+            SeedlotContractsError::NotAnAttester
+        );
+        verify_manager(&ctx.accounts.lot_mint, &ctx.accounts.manager.key())?;
+        // Milestones only start once the lot is confirmed, and stop once it is retired
+        require!(
+            matches!(
+                ctx.accounts.lot.state,
+                LotState::Growing | LotState::Ready | LotState::Shipped
+            ),
+            SeedlotContractsError::InvalidLotState
+        );
+        require_gt!(
+            ctx.accounts.lot.pending,
+            0,
+            SeedlotContractsError::NoPendingPayout
+        );
+
+        let amount = ctx.accounts.lot.release_milestone(milestone)?;
+        transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program_standard.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.contract_usdc_token_account.to_account_info(),
+                    to: ctx.accounts.manager_usdc_token_account.to_account_info(),
+                    authority: ctx.accounts.contract.to_account_info(),
+                },
+                &[&[
+                    b"contract",
+                    ctx.accounts.contract.contract_id.as_ref(),
+                    &[ctx.bumps.contract],
+                ]],
+            ),
+            amount,
+        )?;
+
+        emit!(MilestoneReleased {
+            lot_mint: ctx.accounts.lot_mint.key(),
+            milestone,
+            amount,
+            attester,
+            evidence_hash,
+        });
+        Ok(())
+    }
+}
+
+// Someone the admin trusts to attest to milestones, seeded by [b"inspector", contract, inspector].
+#[account]
+pub struct Inspector {
+    pub inspector: Pubkey,
+}
+
+impl Inspector {
+    pub const LEN: usize = 8 // Discriminator
+     + 32; // inspector
+}
+
+#[event]
+pub struct MilestoneReleased {
+    pub lot_mint: Pubkey,
+    pub milestone: u8,
+    pub amount: u64,
+    pub attester: Pubkey,
+    pub evidence_hash: [u8; 32],
+}
+
+#[derive(Accounts)]
+pub struct AddInspector<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"contract", contract.contract_id.as_ref()],
+        bump,
+        has_one = admin,
+    )]
+    pub contract: Account<'info, Contract>,
+    pub inspector: SystemAccount<'info>,
+    #[account(
+        init,
+        payer = admin,
+        space = Inspector::LEN,
+        seeds = [b"inspector", contract.key().as_ref(), inspector.key().as_ref()],
+        bump
+    )]
+    pub inspector_record: Account<'info, Inspector>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveInspector<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"contract", contract.contract_id.as_ref()],
+        bump,
+        has_one = admin,
+    )]
+    pub contract: Account<'info, Contract>,
+    pub inspector: SystemAccount<'info>,
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"inspector", contract.key().as_ref(), inspector.key().as_ref()],
+        bump
+    )]
+    pub inspector_record: Account<'info, Inspector>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseMilestone<'info> {
+    #[account(mut)]
+    pub attester: Signer<'info>,
+    #[account(
+        seeds = [b"contract", contract.contract_id.as_ref()],
+        bump,
+        has_one = usdc_mint,
+    )]
+    pub contract: Box<Account<'info, Contract>>,
+    // Left out when the admin attests.
+    #[account(
+        seeds = [b"inspector", contract.key().as_ref(), attester.key().as_ref()],
+        bump
+    )]
+    pub inspector_record: Option<Account<'info, Inspector>>,
+    pub manager: SystemAccount<'info>,
+    #[account(
+        mint::authority = contract,
+        mint::token_program = token_program,
+    )]
+    pub lot_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [b"lot", contract.key().as_ref(), lot_mint.key().as_ref()],
+        bump
+    )]
+    pub lot: Box<Account<'info, Lot>>,
+    pub usdc_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = contract,
+        associated_token::token_program = token_program_standard,
+    )]
+    pub contract_usdc_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = attester,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = manager,
+        associated_token::token_program = token_program_standard,
+    )]
+    pub manager_usdc_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program_standard: Program<'info, Token>,
+    pub token_program: Program<'info, Token2022>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
@@ -1,14 +1,20 @@
 use anchor_lang::prelude::*;
 
 use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_interface::Mint;
 
-use crate::{Contract, SeedlotContractsError};
+use crate::{Contract, FeeConfig, OfferConfig};
 
 pub mod instructions {
-    use crate::utils::{init_mint, InitMint, InitMintBumps, MintMetadata};
+    use crate::utils::{init_mint, InitMint, InitMintBumps, MintGroup, MintMetadata};
 
     use super::*;
-    pub fn add_offer(ctx: Context<AddOffer>, offer_mint_metadata: &MintMetadata) -> Result<()> {
+    pub fn add_offer(
+        ctx: Context<AddOffer>,
+        offer_mint_metadata: &MintMetadata,
+        fee_config: Option<FeeConfig>,
+    ) -> Result<()> {
+        // The new order mint joins the contract's offers group, which is how we find it later.
         init_mint(
             Context::new(
                 ctx.program_id,
@@ -16,6 +22,7 @@ pub mod instructions {
                     payer: ctx.accounts.admin.clone(),
                     contract: ctx.accounts.contract.clone(),
                     mint: ctx.accounts.order_mint.clone(),
+                    group: Some(ctx.accounts.offers_group.to_account_info()),
                     rent: ctx.accounts.rent.clone(),
                     token_program: ctx.accounts.token_program.to_account_info(),
                     system_program: ctx.accounts.system_program.to_account_info(),
@@ -26,46 +33,87 @@ pub mod instructions {
                 },
             ),
             offer_mint_metadata,
+            MintGroup::Member,
+            0,
         )?;
-        let offers = &mut ctx.accounts.offers_account;
-        offers.load_mut()?.push(Offer {
-            mint: ctx.accounts.order_mint.key(),
-        })?;
-        Ok(())
+        ctx.accounts
+            .offer_config
+            .set(ctx.accounts.order_mint.key(), fee_config)
     }
-}
 
-impl Offers {
-    pub fn push(&mut self, offer: Offer) -> Result<()> {
-        require_neq!(
-            self.tail,
-            Self::TOTAL_OFFERS,
-            SeedlotContractsError::OffersFull
-        );
-        self.offers[self.tail as usize] = offer;
-        self.tail += 1;
+    // Offers added before groups can't join the offers group after the fact. Once their mint has
+    // gone through migrate_mint, this lets verify_offer take them anyway.
+    pub fn allow_legacy_offer(ctx: Context<AllowLegacyOffer>) -> Result<()> {
+        ctx.accounts.legacy_offer.order_mint = ctx.accounts.order_mint.key();
         Ok(())
     }
 }
 
+// An offer from before groups, seeded by [b"legacy_offer", contract, order_mint].
+#[account]
+pub struct LegacyOffer {
+    pub order_mint: Pubkey,
+}
+
+impl LegacyOffer {
+    pub const LEN: usize = 8 // Discriminator
+     + 32; // order_mint
+}
+
 #[derive(Accounts)]
 pub struct AddOffer<'info> {
     #[account(mut, constraint = admin.key() == contract.admin)]
     pub admin: Signer<'info>,
     #[account(
-      seeds = [b"contract", admin.key().as_ref()],
+      seeds = [b"contract", contract.contract_id.as_ref()],
       bump,
-      constraint = contract.offers_account == offers_account.key()
+      has_one = offers_group,
     )]
     pub contract: Account<'info, Contract>,
+    /// CHECK: Checked against the contract; the token program checks it is a group.
     #[account(mut)]
-    pub offers_account: AccountLoader<'info, Offers>,
+    pub offers_group: UncheckedAccount<'info>,
     #[account(mut)]
     pub order_mint: Signer<'info>,
+    #[account(
+        init,
+        payer = admin,
+        space = OfferConfig::LEN,
+        seeds = [b"offer_config", contract.key().as_ref(), order_mint.key().as_ref()],
+        bump
+    )]
+    pub offer_config: Account<'info, OfferConfig>,
     pub system_program: Program<'info, System>,
     pub token_program: Program<'info, Token2022>,
     pub rent: Sysvar<'info, Rent>,
 }
+
+#[derive(Accounts)]
+pub struct AllowLegacyOffer<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"contract", contract.contract_id.as_ref()],
+        bump,
+        has_one = admin,
+    )]
+    pub contract: Account<'info, Contract>,
+    #[account(
+        mint::authority = contract,
+        mint::token_program = token_program,
+    )]
+    pub order_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = admin,
+        space = LegacyOffer::LEN,
+        seeds = [b"legacy_offer", contract.key().as_ref(), order_mint.key().as_ref()],
+        bump
+    )]
+    pub legacy_offer: Account<'info, LegacyOffer>,
+    pub token_program: Program<'info, Token2022>,
+    pub system_program: Program<'info, System>,
+}
 /*
 // TODO(techiejd): Get back to this.
 #[account]
@@ -74,24 +122,3 @@ pub struct FulfilledOrderMints {
     pub mints: [Pubkey; 10_000],
 }
  */
-
-impl Offers {
-    pub const TOTAL_OFFERS: u64 = 300;
-    pub const LEN: usize = 8 // Discriminator
-    + 32 // owner Pubkey
-    + 32 // tail u64
-    + (Self::TOTAL_OFFERS as usize * 32);
-}
-
-#[zero_copy]
-pub struct Offer {
-    pub mint: Pubkey,
-}
-
-#[account(zero_copy)]
-#[repr(C)]
-pub struct Offers {
-    pub owner: Pubkey,
-    pub tail: u64,
-    pub offers: [Offer; 300],
-}
@@ -1,29 +1,45 @@
-use crate::{Contract, Offers, SeedlotContractsError};
+use crate::{Contract, LegacyOffer, SeedlotContractsError};
 use anchor_lang::prelude::*;
 use anchor_spl::associated_token::AssociatedToken;
 use anchor_spl::token::Token;
 use anchor_spl::token_2022::Token2022;
 use anchor_spl::token_interface::{Mint, TokenAccount};
+use session_keys::{Session, SessionToken};
 use solana_program::program_option::COption;
 
+use crate::utils::price_cents_2_usdc;
+
 pub mod instructions {
     use crate::utils::{
-        get_token_metadata, get_value, mint_frozen_tokens_to, price_cents_2_usdc,
-        price_string_2_cents, MintFrozenTokensTo, MintFrozenTokensToBumps,
+        burn_frozen_tokens_from, get_token_metadata, get_value, mint_frozen_tokens_to,
+        price_string_2_cents, BurnFrozenTokensFrom, BurnFrozenTokensFromBumps, MintFrozenTokensTo,
+        MintFrozenTokensToBumps,
     };
 
     use super::*;
     use anchor_spl::token::{transfer, Transfer};
+    use session_keys::{session_auth_or, SessionError};
 
+    #[session_auth_or(
+        ctx.accounts.user.key() == ctx.accounts.signer.key(),
+        SeedlotContractsError::UserMismatch
+    )]
     pub fn place_order(
         ctx: Context<PlaceOrder>,
-        offer_index: u64,
         order_quantity: u64,
+        max_total_price: u64,
+        expires_at: Option<i64>,
     ) -> Result<()> {
-        ctx.accounts
-            .offers_account
-            .load()?
-            .verify_order(offer_index, ctx.accounts.offer_mint.key())?;
+        ctx.accounts.contract.verify_offer(
+            &ctx.accounts.offer_mint,
+            ctx.accounts.legacy_offer.as_deref(),
+        )?;
+
+        // Don't place orders that sat around for longer than the user was willing to wait
+        let now = Clock::get()?.unix_timestamp;
+        if let Some(expires_at) = expires_at {
+            require_gte!(expires_at, now, SeedlotContractsError::OrderExpired);
+        }
 
         let order_token_metadata = get_token_metadata(&ctx.accounts.offer_mint)?;
         // The price is stored in [2] of order_token_metadata.additional_metadata
@@ -31,12 +47,19 @@ pub mod instructions {
         let price_in_cents = price_string_2_cents(&price)?;
         let price_in_usdc = price_cents_2_usdc(&price_in_cents);
         let total_price = price_in_usdc * order_quantity * ctx.accounts.contract.trees_per_lot;
+        // The price may have changed since the user saw it
+        require_gte!(
+            max_total_price,
+            total_price,
+            SeedlotContractsError::PriceAboveMaximum
+        );
 
-        // Now we need to transfer `price_in_usdc` USDC from the user to the contract
+        // Now we need to transfer `price_in_usdc` USDC from the user to the contract.
+        // A session key can only do so if the user approved it as a delegate of their USDC.
         let transfer_ctx = Transfer {
             from: ctx.accounts.usdc_from.to_account_info(),
             to: ctx.accounts.contract_usdc_token_account.to_account_info(),
-            authority: ctx.accounts.user.to_account_info(),
+            authority: ctx.accounts.signer.to_account_info(),
         };
         transfer(
             CpiContext::new(
@@ -66,52 +89,229 @@ pub mod instructions {
             order_quantity,
         )?;
 
+        // Lock in the price so everything later settles against what the user paid
+        let order_balance = &mut ctx.accounts.order_balance;
+        order_balance.user = ctx.accounts.user.key();
+        order_balance.order_mint = ctx.accounts.offer_mint.key();
+        let order_id = order_balance.add_order(order_quantity)?;
+        ctx.accounts.receipt.set_inner(OrderReceipt {
+            buyer: ctx.accounts.user.key(),
+            order_mint: ctx.accounts.offer_mint.key(),
+            order_id,
+            quantity: order_quantity,
+            quantity_remaining: order_quantity,
+            price_per_tree: price_in_cents,
+            trees_per_lot: ctx.accounts.contract.trees_per_lot,
+            placed_at: now,
+        });
+
+        Ok(())
+    }
+
+    // Lets the user back out of order tokens no manager has prepared yet, refunding what they paid.
+    // Orders placed before receipts existed need the admin to migrate_order them first.
+    pub fn cancel_order(ctx: Context<CancelOrder>, order_quantity: u64) -> Result<()> {
+        ctx.accounts.order_balance.remove(order_quantity)?;
+        let refund = ctx.accounts.receipt.settle(order_quantity)?;
+
+        burn_frozen_tokens_from(
+            Context::new(
+                ctx.program_id,
+                &mut BurnFrozenTokensFrom {
+                    authority: ctx.accounts.user.to_account_info(),
+                    contract: ctx.accounts.contract.clone(),
+                    mint: ctx.accounts.order_mint.clone(),
+                    from: ctx.accounts.user_order_token_account.clone(),
+                    associated_token_program: ctx.accounts.associated_token_program.clone(),
+                    token_program: ctx.accounts.token_program.clone(),
+                },
+                &[],
+                BurnFrozenTokensFromBumps {
+                    contract: ctx.bumps.contract,
+                },
+            ),
+            order_quantity,
+        )?;
+
+        transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program_standard.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.contract_usdc_token_account.to_account_info(),
+                    to: ctx.accounts.user_usdc_token_account.to_account_info(),
+                    authority: ctx.accounts.contract.to_account_info(),
+                },
+                &[&[
+                    b"contract",
+                    ctx.accounts.contract.contract_id.as_ref(),
+                    &[ctx.bumps.contract],
+                ]],
+            ),
+            refund,
+        )?;
+
+        Ok(())
+    }
+}
+
+// What a user holds of one offer, seeded by [b"order", contract, order_mint, user].
+#[account]
+pub struct OrderBalance {
+    pub user: Pubkey,
+    pub order_mint: Pubkey,
+    // Order tokens with a receipt that are not yet prepared into lots or cancelled.
+    pub quantity: u64,
+    // Numbers the user's receipts for this offer.
+    pub orders_placed: u64,
+}
+
+impl OrderBalance {
+    pub const LEN: usize = 8 // Discriminator
+     + 32 // user
+     + 32 // order_mint
+     + 8 // quantity
+     + 8; // orders_placed
+
+    // Returns the id of the receipt for this order.
+    pub fn add_order(&mut self, quantity: u64) -> Result<u64> {
+        let order_id = self.orders_placed;
+        self.orders_placed += 1;
+        self.quantity = self
+            .quantity
+            .checked_add(quantity)
+            .ok_or(SeedlotContractsError::InsufficientOrderBalance)?;
+        Ok(order_id)
+    }
+
+    // Puts back order tokens that a denied lot, or one that lost trees, gave back.
+    pub fn restore(&mut self, quantity: u64) -> Result<()> {
+        self.quantity = self
+            .quantity
+            .checked_add(quantity)
+            .ok_or(SeedlotContractsError::InsufficientOrderBalance)?;
+        Ok(())
+    }
+
+    pub fn remove(&mut self, quantity: u64) -> Result<()> {
+        self.quantity = self
+            .quantity
+            .checked_sub(quantity)
+            .ok_or(SeedlotContractsError::InsufficientOrderBalance)?;
         Ok(())
     }
 }
 
-impl Offers {
-    pub fn verify_order(&self, offer_index: u64, order_mint_key: Pubkey) -> Result<()> {
+// One placed order and the terms it was placed under, seeded by
+// [b"receipt", contract, order_mint, buyer, order_id].
+#[account]
+pub struct OrderReceipt {
+    pub buyer: Pubkey,
+    pub order_mint: Pubkey,
+    pub order_id: u64,
+    pub quantity: u64,
+    // Order tokens from this order that are not yet prepared into lots or cancelled.
+    pub quantity_remaining: u64,
+    // In cents, like the offer's price metadata.
+    pub price_per_tree: u64,
+    pub trees_per_lot: u64,
+    pub placed_at: i64,
+}
+
+impl OrderReceipt {
+    pub const LEN: usize = 8 // Discriminator
+     + 32 // buyer
+     + 32 // order_mint
+     + 8 // order_id
+     + 8 // quantity
+     + 8 // quantity_remaining
+     + 8 // price_per_tree
+     + 8 // trees_per_lot
+     + 8; // placed_at
+
+    // What the buyer paid for `quantity` of these order tokens.
+    pub fn price(&self, quantity: u64) -> u64 {
+        price_cents_2_usdc(&self.price_per_tree) * quantity * self.trees_per_lot
+    }
+
+    // Takes `quantity` order tokens off the receipt and returns what the buyer paid for them.
+    pub fn settle(&mut self, quantity: u64) -> Result<u64> {
+        self.quantity_remaining = self
+            .quantity_remaining
+            .checked_sub(quantity)
+            .ok_or(SeedlotContractsError::InsufficientOrderBalance)?;
+        Ok(self.price(quantity))
+    }
+
+    // Puts back order tokens that were settled against this receipt, so they keep its price.
+    pub fn restore(&mut self, quantity: u64) -> Result<()> {
+        let quantity_remaining = self.quantity_remaining + quantity;
         require_gte!(
-            self.tail,
-            offer_index,
-            SeedlotContractsError::InvalidOfferIndex
-        );
-        let offer = self.offers[offer_index as usize];
-        require_eq!(
-            offer.mint,
-            order_mint_key,
-            SeedlotContractsError::OrderMintNotFound
+            self.quantity,
+            quantity_remaining,
+            SeedlotContractsError::OrderReceiptMismatch
         );
+        self.quantity_remaining = quantity_remaining;
         Ok(())
     }
 }
 
-#[derive(Accounts)]
+#[derive(Accounts, Session)]
 pub struct PlaceOrder<'info> {
+    pub user: SystemAccount<'info>,
+    // Either the user or a session key they approved for this program. It pays for new accounts
+    // and the order, so a session key also has to be approved as a delegate of usdc_from for at
+    // least the total price, which clients do when they create the session.
     #[account(mut)]
-    pub user: Signer<'info>,
+    pub signer: Signer<'info>,
+    #[session(signer = signer, authority = user.key())]
+    pub session_token: Option<Account<'info, SessionToken>>,
     #[account(
       mut,
-      seeds = [b"contract", contract.admin.key().as_ref()],
+      seeds = [b"contract", contract.contract_id.as_ref()],
       bump,
-      has_one = offers_account,
       has_one = usdc_mint,
       constraint = contract.usdc_token_account.key() == contract_usdc_token_account.key(),
     )]
     pub contract: Account<'info, Contract>,
-    pub offers_account: AccountLoader<'info, Offers>,
     #[account(mut,
     constraint = offer_mint.mint_authority == COption::Some(contract.key()),
     )]
     pub offer_mint: InterfaceAccount<'info, Mint>,
+    // Only for offers added before groups, see allow_legacy_offer.
+    #[account(
+      seeds = [b"legacy_offer", contract.key().as_ref(), offer_mint.key().as_ref()],
+      bump
+    )]
+    pub legacy_offer: Option<Box<Account<'info, LegacyOffer>>>,
     #[account(
       init_if_needed,
-      payer = user,
+      payer = signer,
       associated_token::mint = offer_mint,
       associated_token::authority = user,
     )]
     pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+      init_if_needed,
+      payer = signer,
+      space = OrderBalance::LEN,
+      seeds = [b"order", contract.key().as_ref(), offer_mint.key().as_ref(), user.key().as_ref()],
+      bump
+    )]
+    pub order_balance: Box<Account<'info, OrderBalance>>,
+    #[account(
+      init,
+      payer = signer,
+      space = OrderReceipt::LEN,
+      seeds = [
+        b"receipt",
+        contract.key().as_ref(),
+        offer_mint.key().as_ref(),
+        user.key().as_ref(),
+        &order_balance.orders_placed.to_le_bytes(),
+      ],
+      bump
+    )]
+    pub receipt: Box<Account<'info, OrderReceipt>>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub token_program: Program<'info, Token2022>,
     pub system_program: Program<'info, System>,
@@ -130,3 +330,66 @@ pub struct PlaceOrder<'info> {
     pub contract_usdc_token_account: InterfaceAccount<'info, TokenAccount>,
     pub token_program_standard: Program<'info, Token>,
 }
+
+#[derive(Accounts)]
+pub struct CancelOrder<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        seeds = [b"contract", contract.contract_id.as_ref()],
+        bump,
+        has_one = usdc_mint,
+        constraint = contract.usdc_token_account.key() == contract_usdc_token_account.key(),
+    )]
+    pub contract: Account<'info, Contract>,
+    #[account(
+        mut,
+        mint::authority = contract,
+        mint::token_program = token_program,
+    )]
+    pub order_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = order_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program,
+    )]
+    pub user_order_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"order", contract.key().as_ref(), order_mint.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub order_balance: Account<'info, OrderBalance>,
+    #[account(
+        mut,
+        seeds = [
+            b"receipt",
+            contract.key().as_ref(),
+            order_mint.key().as_ref(),
+            user.key().as_ref(),
+            &receipt.order_id.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub receipt: Account<'info, OrderReceipt>,
+    pub usdc_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = contract,
+        associated_token::token_program = token_program_standard,
+    )]
+    pub contract_usdc_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program_standard,
+    )]
+    pub user_usdc_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Program<'info, Token2022>,
+    pub token_program_standard: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,151 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_interface::Mint;
+use session_keys::{Session, SessionToken};
+
+use crate::utils::{get_token_metadata, get_value};
+use crate::{Contract, Lot, LotState, ManagerRecord, SeedlotContractsError};
+
+pub mod instructions {
+    use super::*;
+    use session_keys::{session_auth_or, SessionError};
+
+    // The manager starts a new season on a lot whose last harvest has shipped.
+    #[session_auth_or(
+        ctx.accounts.manager.key() == ctx.accounts.signer.key(),
+        SeedlotContractsError::ManagerMismatch
+    )]
+    pub fn mark_growing(ctx: Context<ManagerLotProgress>) -> Result<()> {
+        ctx.accounts.verify_manager()?;
+        ctx.accounts.lot.transition_to(LotState::Growing)
+    }
+
+    // The manager says the lot's harvest is ready.
+    #[session_auth_or(
+        ctx.accounts.manager.key() == ctx.accounts.signer.key(),
+        SeedlotContractsError::ManagerMismatch
+    )]
+    pub fn mark_ready(ctx: Context<ManagerLotProgress>) -> Result<()> {
+        ctx.accounts.verify_manager()?;
+        ctx.accounts.lot.transition_to(LotState::Ready)
+    }
+
+    // The admin says the lot's harvest has shipped.
+    #[session_auth_or(
+        ctx.accounts.admin.key() == ctx.accounts.signer.key(),
+        SeedlotContractsError::AdminMismatch
+    )]
+    pub fn mark_shipped(ctx: Context<AdminLotProgress>) -> Result<()> {
+        ctx.accounts.lot.transition_to(LotState::Shipped)
+    }
+
+    // The admin takes a shipped lot out of production, freeing up room under the manager's tier.
+    pub fn retire_lot(ctx: Context<RetireLot>) -> Result<()> {
+        verify_manager(&ctx.accounts.lot_mint, &ctx.accounts.manager.key())?;
+        ctx.accounts.lot.transition_to(LotState::Retired)?;
+        ctx.accounts
+            .manager_record
+            .remove_lots(Lot::lots_in(&ctx.accounts.lot_mint));
+        Ok(())
+    }
+}
+
+pub(crate) fn verify_manager(lot_mint: &InterfaceAccount<Mint>, manager: &Pubkey) -> Result<()> {
+    let lot_token_metadata = get_token_metadata(lot_mint)?;
+    let lot_manager = get_value(&lot_token_metadata, "manager")?;
+    require_eq!(
+        lot_manager,
+        manager.to_string(),
+        SeedlotContractsError::ManagerMismatch
+    );
+    Ok(())
+}
+
+impl<'info> ManagerLotProgress<'info> {
+    fn verify_manager(&self) -> Result<()> {
+        verify_manager(&self.lot_mint, &self.manager.key())
+    }
+}
+
+#[derive(Accounts, Session)]
+pub struct ManagerLotProgress<'info> {
+    pub manager: SystemAccount<'info>,
+    // Either the manager or a session key they approved for this program.
+    pub signer: Signer<'info>,
+    #[session(signer = signer, authority = manager.key())]
+    pub session_token: Option<Account<'info, SessionToken>>,
+    #[account(
+        seeds = [b"contract", contract.contract_id.as_ref()],
+        bump,
+    )]
+    pub contract: Account<'info, Contract>,
+    #[account(
+        mint::authority = contract,
+        mint::token_program = token_program,
+    )]
+    pub lot_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [b"lot", contract.key().as_ref(), lot_mint.key().as_ref()],
+        bump
+    )]
+    pub lot: Account<'info, Lot>,
+    pub token_program: Program<'info, Token2022>,
+}
+
+#[derive(Accounts, Session)]
+pub struct AdminLotProgress<'info> {
+    pub admin: SystemAccount<'info>,
+    // Either the admin or a session key they approved for this program.
+    pub signer: Signer<'info>,
+    #[session(signer = signer, authority = admin.key())]
+    pub session_token: Option<Account<'info, SessionToken>>,
+    #[account(
+        seeds = [b"contract", contract.contract_id.as_ref()],
+        bump,
+        has_one = admin,
+    )]
+    pub contract: Account<'info, Contract>,
+    #[account(
+        mint::authority = contract,
+        mint::token_program = token_program,
+    )]
+    pub lot_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [b"lot", contract.key().as_ref(), lot_mint.key().as_ref()],
+        bump
+    )]
+    pub lot: Account<'info, Lot>,
+    pub token_program: Program<'info, Token2022>,
+}
+
+#[derive(Accounts)]
+pub struct RetireLot<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"contract", contract.contract_id.as_ref()],
+        bump,
+        has_one = admin,
+    )]
+    pub contract: Account<'info, Contract>,
+    pub manager: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"manager", contract.key().as_ref(), manager.key().as_ref()],
+        bump
+    )]
+    pub manager_record: Account<'info, ManagerRecord>,
+    #[account(
+        mint::authority = contract,
+        mint::token_program = token_program,
+    )]
+    pub lot_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [b"lot", contract.key().as_ref(), lot_mint.key().as_ref()],
+        bump
+    )]
+    pub lot: Account<'info, Lot>,
+    pub token_program: Program<'info, Token2022>,
+}
@@ -7,14 +7,18 @@ use anchor_spl::token_2022::{
 };
 use anchor_spl::token_interface::spl_pod::optional_keys::OptionalNonZeroPubkey;
 use anchor_spl::token_interface::{
-    default_account_state_initialize, metadata_pointer_initialize, mint_close_authority_initialize,
-    permanent_delegate_initialize, token_metadata_initialize, token_metadata_update_field,
-    DefaultAccountStateInitialize, MetadataPointerInitialize, Mint, MintCloseAuthorityInitialize,
-    PermanentDelegateInitialize, TokenAccount, TokenMetadataInitialize, TokenMetadataUpdateField,
+    default_account_state_initialize, group_member_pointer_initialize, group_pointer_initialize,
+    metadata_pointer_initialize, mint_close_authority_initialize, permanent_delegate_initialize,
+    token_group_initialize, token_member_initialize, token_metadata_initialize,
+    token_metadata_update_field, DefaultAccountStateInitialize, GroupMemberPointerInitialize,
+    GroupPointerInitialize, MetadataPointerInitialize, Mint, MintCloseAuthorityInitialize,
+    PermanentDelegateInitialize, TokenAccount, TokenGroupInitialize, TokenMemberInitialize,
+    TokenMetadataInitialize, TokenMetadataUpdateField,
 };
 use solana_program::program_option::COption;
 use spl_token_2022::extension::{BaseStateWithExtensions, PodStateWithExtensions};
 use spl_token_2022::pod::PodMint;
+use spl_token_group_interface::state::TokenGroupMember;
 use spl_token_metadata_interface::state::{Field, TokenMetadata};
 
 use anchor_lang::system_program::CreateAccount;
@@ -33,6 +37,15 @@ pub fn get_token_metadata(mint: &InterfaceAccount<Mint>) -> Result<TokenMetadata
         .map_err(|e| anchor_lang::error::Error::from(e))
 }
 
+// Returns the group a mint was made a member of in `init_mint`.
+pub fn get_group(mint: &InterfaceAccount<Mint>) -> Result<Pubkey> {
+    let account_info = &mint.to_account_info();
+    let buffer = &account_info.data.borrow();
+    let mint = PodStateWithExtensions::<PodMint>::unpack(buffer)?;
+    let member = mint.get_extension::<TokenGroupMember>()?;
+    Ok(member.group)
+}
+
 pub fn get_value(metadata: &TokenMetadata, key: &str) -> Result<String> {
     metadata
         .additional_metadata
@@ -59,11 +72,14 @@ pub fn price_cents_2_usdc(price: &u64) -> u64 {
 pub struct InitMint<'info> {
     pub payer: Signer<'info>,
     #[account(
-        seeds = [b"contract", contract.admin.as_ref()],
+        seeds = [b"contract", contract.contract_id.as_ref()],
         bump
     )]
     pub contract: Account<'info, Contract>,
     pub mint: Signer<'info>,
+    /// CHECK: The group the new mint joins, only for `MintGroup::Member`. Token-2022 checks it.
+    #[account(mut)]
+    pub group: Option<AccountInfo<'info>>,
     pub rent: Sysvar<'info, Rent>,
     /// CHECK: Read-only
     pub token_program: AccountInfo<'info>,
@@ -71,21 +87,52 @@ pub struct InitMint<'info> {
     pub system_program: AccountInfo<'info>,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MintGroup {
+    None,   // A standalone mint, like the certification mint
+    Group,  // A collection other mints join, like the contract's offers
+    Member, // A member of `InitMint.group`, like an offer's order mint
+}
+
 pub fn init_mint<'info>(
     ctx: Context<'_, '_, '_, 'info, InitMint<'info>>,
     mint_metadata: &MintMetadata,
+    mint_group: MintGroup,
+    decimals: u8,
 ) -> Result<()> {
     let signer_seeds: &[&[&[u8]]] = &[&[
         b"contract",
-        ctx.accounts.contract.admin.as_ref(),
+        ctx.accounts.contract.contract_id.as_ref(),
         &[ctx.bumps.contract],
     ]];
-    let extension_space = ExtensionType::try_calculate_account_len::<spl_Mint>(&[
+    let mut extension_types = vec![
         ExtensionType::DefaultAccountState,
         ExtensionType::MetadataPointer,
         ExtensionType::PermanentDelegate,
         ExtensionType::MintCloseAuthority,
-    ])?;
+    ];
+    match mint_group {
+        MintGroup::None => {}
+        MintGroup::Group => extension_types.push(ExtensionType::GroupPointer),
+        MintGroup::Member => extension_types.push(ExtensionType::GroupMemberPointer),
+    }
+    let extension_space = ExtensionType::try_calculate_account_len::<spl_Mint>(&extension_types)?;
+
+    // Like the metadata, the group (or member) data is reallocated into the mint after it is
+    // initialized, so we only pay rent for it up front.
+    let group_space = match mint_group {
+        MintGroup::None => 0,
+        MintGroup::Group => {
+            extension_types.push(ExtensionType::TokenGroup);
+            ExtensionType::try_calculate_account_len::<spl_Mint>(&extension_types)?
+                - extension_space
+        }
+        MintGroup::Member => {
+            extension_types.push(ExtensionType::TokenGroupMember);
+            ExtensionType::try_calculate_account_len::<spl_Mint>(&extension_types)?
+                - extension_space
+        }
+    };
 
     let token_metadata_space;
     {
@@ -107,7 +154,6 @@ pub fn init_mint<'info>(
                             ),
                             ("variety".to_string(), location_variety_price[1].to_string()),
                             ("manager".to_string(), manager_for_lot.to_string()),
-                            ("state".to_string(), "0".to_string()),
                         ]
                     } else {
                         vec![
@@ -127,7 +173,7 @@ pub fn init_mint<'info>(
     }
 
     let space_buffer: usize = 8;
-    let total_space = extension_space + token_metadata_space + space_buffer;
+    let total_space = extension_space + token_metadata_space + group_space + space_buffer;
 
     create_account(
         CpiContext::new(
@@ -188,6 +234,32 @@ pub fn init_mint<'info>(
         Some(&ctx.accounts.contract.key()),
     )?;
 
+    match mint_group {
+        MintGroup::None => {}
+        MintGroup::Group => group_pointer_initialize(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                GroupPointerInitialize {
+                    token_program_id: ctx.accounts.token_program.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                },
+            ),
+            Some(ctx.accounts.contract.key()),
+            Some(ctx.accounts.mint.key()),
+        )?,
+        MintGroup::Member => group_member_pointer_initialize(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                GroupMemberPointerInitialize {
+                    token_program_id: ctx.accounts.token_program.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                },
+            ),
+            Some(ctx.accounts.contract.key()),
+            Some(ctx.accounts.mint.key()),
+        )?,
+    }
+
     initialize_mint(
         CpiContext::new(
             ctx.accounts.token_program.to_account_info(),
@@ -196,7 +268,7 @@ pub fn init_mint<'info>(
                 rent: ctx.accounts.rent.to_account_info(),
             },
         ),
-        0,
+        decimals,
         &ctx.accounts.contract.key(),
         Some(&ctx.accounts.contract.key()),
     )?;
@@ -232,7 +304,6 @@ pub fn init_mint<'info>(
                 ("location".to_string(), location_variety_price[0].clone()),
                 ("variety".to_string(), location_variety_price[1].clone()),
                 ("manager".to_string(), manager_for_lot.clone()),
-                ("state".to_string(), "0".to_string()),
             ];
         } else {
             additional_metadata = vec![
@@ -259,6 +330,44 @@ pub fn init_mint<'info>(
         }
     }
 
+    // Initialize the group or join it
+    match mint_group {
+        MintGroup::None => {}
+        MintGroup::Group => token_group_initialize(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TokenGroupInitialize {
+                    token_program_id: ctx.accounts.token_program.to_account_info(),
+                    group: ctx.accounts.mint.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    mint_authority: ctx.accounts.contract.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            Some(ctx.accounts.contract.key()),
+            u32::MAX,
+        )?,
+        MintGroup::Member => {
+            let group = ctx
+                .accounts
+                .group
+                .as_ref()
+                .ok_or(ErrorCode::AccountNotEnoughKeys)?;
+            token_member_initialize(CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TokenMemberInitialize {
+                    token_program_id: ctx.accounts.token_program.to_account_info(),
+                    member: ctx.accounts.mint.to_account_info(),
+                    member_mint: ctx.accounts.mint.to_account_info(),
+                    member_mint_authority: ctx.accounts.contract.to_account_info(),
+                    group: group.to_account_info(),
+                    group_update_authority: ctx.accounts.contract.to_account_info(),
+                },
+                signer_seeds,
+            ))?
+        }
+    }
+
     Ok(())
 }
 
@@ -273,7 +382,7 @@ pub fn burn_frozen_tokens_from(ctx: Context<BurnFrozenTokensFrom>, amount: u64)
         },
         &[&[
             b"contract",
-            ctx.accounts.contract.admin.as_ref(),
+            ctx.accounts.contract.contract_id.as_ref(),
             &[ctx.bumps.contract],
         ]],
     ))?;
@@ -287,7 +396,7 @@ pub fn burn_frozen_tokens_from(ctx: Context<BurnFrozenTokensFrom>, amount: u64)
             },
             &[&[
                 b"contract",
-                ctx.accounts.contract.admin.as_ref(),
+                ctx.accounts.contract.contract_id.as_ref(),
                 &[ctx.bumps.contract],
             ]],
         ),
@@ -303,7 +412,7 @@ pub fn burn_frozen_tokens_from(ctx: Context<BurnFrozenTokensFrom>, amount: u64)
         },
         &[&[
             b"contract",
-            ctx.accounts.contract.admin.as_ref(),
+            ctx.accounts.contract.contract_id.as_ref(),
             &[ctx.bumps.contract],
         ]],
     ))?;
@@ -321,7 +430,7 @@ pub fn mint_frozen_tokens_to(ctx: Context<MintFrozenTokensTo>, amount: u64) -> R
         },
         &[&[
             b"contract",
-            ctx.accounts.contract.admin.as_ref(),
+            ctx.accounts.contract.contract_id.as_ref(),
             &[ctx.bumps.contract],
         ]],
     ))?;
@@ -335,7 +444,7 @@ pub fn mint_frozen_tokens_to(ctx: Context<MintFrozenTokensTo>, amount: u64) -> R
             },
             &[&[
                 b"contract",
-                ctx.accounts.contract.admin.as_ref(),
+                ctx.accounts.contract.contract_id.as_ref(),
                 &[ctx.bumps.contract],
             ]],
         ),
@@ -351,7 +460,7 @@ pub fn mint_frozen_tokens_to(ctx: Context<MintFrozenTokensTo>, amount: u64) -> R
         },
         &[&[
             b"contract",
-            ctx.accounts.contract.admin.as_ref(),
+            ctx.accounts.contract.contract_id.as_ref(),
             &[ctx.bumps.contract],
         ]],
     ))?;
@@ -372,7 +481,7 @@ pub struct MintFrozenTokensTo<'info> {
     /// CHECK: Only used for getting the associated token address.
     pub authority: AccountInfo<'info>,
     #[account(mut,
-        seeds = [b"contract", contract.admin.as_ref()],
+        seeds = [b"contract", contract.contract_id.as_ref()],
         bump
     )]
     pub contract: Account<'info, Contract>,
@@ -389,7 +498,7 @@ pub struct BurnFrozenTokensFrom<'info> {
     /// CHECK: Only used for getting the associated token address.
     pub authority: AccountInfo<'info>,
     #[account(
-        seeds = [b"contract", contract.admin.as_ref()],
+        seeds = [b"contract", contract.contract_id.as_ref()],
         bump
     )]
     pub contract: Account<'info, Contract>,